@@ -0,0 +1,465 @@
+//! Parsing and walking a generated multi-megabyte Lox program, comparing the
+//! boxed AST with its arena-backed copy.
+//!
+//! Run with `cargo bench --bench ast`.
+
+use rlox::arena::{Ast, ExprId, ExprNodeVisitor, StmtId, StmtNodeVisitor};
+use rlox::ast::{Expr, ExprVisitor, Object, Stmt, StmtVisitor};
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::token::Token;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CHUNK: &str = r#"
+class Point {
+    init(x, y) { this.x = x; this.y = y; }
+    length { return sqrt(this.x * this.x + this.y * this.y); }
+    set x(value) { this.x = value; }
+    class origin() { return Point(0, 0); }
+}
+
+fun fib(n) {
+    if (n <= 1) return n;
+    return fib(n - 2) + fib(n - 1);
+}
+
+var total = 0;
+for (var i = 0; i < 1000; i = i + 1) {
+    total = total + fib(i / 10) * 16 - (total > 100 ? 1 : 0);
+    if (total != nil and !(i == 3 or i >= 7)) print "total so far: ${total}";
+}
+
+var points = [Point(1, 2), Point(3, 4)];
+points[0] = points[1]?.length > 4 ? points[1] : [];
+var origin = { "x": 0, y: total };
+"#;
+
+// How many times the program is walked, as an interpreter running loops
+// would revisit the same nodes.
+const WALKS: u32 = 10;
+
+fn source(target_bytes: usize) -> String {
+    let mut source = String::with_capacity(target_bytes + CHUNK.len());
+    while source.len() < target_bytes {
+        source.push_str(CHUNK);
+    }
+    source
+}
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let tokens = Scanner::new(source)
+        .scan_tokens()
+        .expect("benchmark source scans");
+    Parser::new(tokens)
+        .parse()
+        .expect("benchmark source parses")
+}
+
+fn best_of<T>(iterations: u32, mut run: impl FnMut() -> T) -> (Duration, T) {
+    let mut best = Duration::MAX;
+    let mut result = None;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let value = black_box(run());
+        best = best.min(start.elapsed());
+        result = Some(value);
+    }
+    (best, result.expect("at least one iteration"))
+}
+
+fn report(name: &str, time: Duration) {
+    println!("{name:<36} best {time:>10.2?}");
+}
+
+fn main() {
+    let source = source(4 * 1024 * 1024);
+    let program = parse(&source);
+    let mut ast = Ast::new();
+    let roots = ast.lower(&program);
+
+    let boxed_nodes = BoxedCounter.count(&program);
+    let arena_nodes = ArenaCounter.count(&ast, ast.stmt_list(roots));
+    assert_eq!(boxed_nodes, arena_nodes, "both trees hold the same nodes");
+    println!(
+        "{:.2} MiB of source, {boxed_nodes} nodes, {WALKS} walks",
+        source.len() as f64 / (1024.0 * 1024.0)
+    );
+
+    let (parse_time, _) = best_of(5, || parse(black_box(&source)));
+    let (lower_time, _) = best_of(5, || {
+        let mut ast = Ast::new();
+        ast.lower(black_box(&program));
+        ast
+    });
+    let (boxed_walk, _) = best_of(5, || BoxedCounter.count(black_box(&program)));
+    let (arena_walk, _) = best_of(5, || {
+        ArenaCounter.count(black_box(&ast), ast.stmt_list(roots))
+    });
+
+    report("parse (boxed)", parse_time);
+    report("lower to arena", lower_time);
+    report("walk (boxed)", boxed_walk);
+    report("walk (arena)", arena_walk);
+    report(
+        &format!("parse + {WALKS} walks (boxed)"),
+        parse_time + boxed_walk * WALKS,
+    );
+    report(
+        &format!("parse + lower + {WALKS} walks (arena)"),
+        parse_time + lower_time + arena_walk * WALKS,
+    );
+}
+
+// Counts the nodes of the boxed tree.
+struct BoxedCounter;
+
+impl BoxedCounter {
+    fn count(&mut self, statements: &[Stmt]) -> usize {
+        statements.iter().map(|s| self.visit_stmt(s)).sum()
+    }
+
+    fn exprs(&mut self, exprs: &[&Expr]) -> usize {
+        1 + exprs.iter().map(|e| self.visit_expr(e)).sum::<usize>()
+    }
+}
+
+impl ExprVisitor<usize> for BoxedCounter {
+    fn visit_array_expr(&mut self, elements: &[Expr]) -> usize {
+        1 + elements.iter().map(|e| self.visit_expr(e)).sum::<usize>()
+    }
+    fn visit_assign_expr(&mut self, _: &Token, value: &Expr) -> usize {
+        self.exprs(&[value])
+    }
+    fn visit_binary_expr(&mut self, left: &Expr, _: &Token, right: &Expr) -> usize {
+        self.exprs(&[left, right])
+    }
+    fn visit_call_expr(&mut self, callee: &Expr, _: &Token, arguments: &[Expr]) -> usize {
+        self.exprs(&[callee]) + arguments.iter().map(|a| self.visit_expr(a)).sum::<usize>()
+    }
+    fn visit_comma_expr(&mut self, expressions: &[Expr]) -> usize {
+        1 + expressions
+            .iter()
+            .map(|e| self.visit_expr(e))
+            .sum::<usize>()
+    }
+    fn visit_get_expr(&mut self, object: &Expr, _: &Token) -> usize {
+        self.exprs(&[object])
+    }
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> usize {
+        self.exprs(&[expression])
+    }
+    fn visit_index_expr(&mut self, object: &Expr, _: &Token, index: &Expr) -> usize {
+        self.exprs(&[object, index])
+    }
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> usize {
+        1 + parts.iter().map(|p| self.visit_expr(p)).sum::<usize>()
+    }
+    fn visit_literal_expr(&mut self, _: &Object) -> usize {
+        1
+    }
+    fn visit_logical_expr(&mut self, left: &Expr, _: &Token, right: &Expr) -> usize {
+        self.exprs(&[left, right])
+    }
+    fn visit_map_expr(&mut self, keys: &[Expr], values: &[Expr]) -> usize {
+        1 + keys
+            .iter()
+            .chain(values)
+            .map(|e| self.visit_expr(e))
+            .sum::<usize>()
+    }
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) -> usize {
+        self.exprs(&[expression])
+    }
+    fn visit_optional_get_expr(&mut self, object: &Expr, _: &Token) -> usize {
+        self.exprs(&[object])
+    }
+    fn visit_set_expr(&mut self, object: &Expr, _: &Token, value: &Expr) -> usize {
+        self.exprs(&[object, value])
+    }
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> usize {
+        self.exprs(&[object, index, value])
+    }
+    fn visit_super_expr(&mut self, _: &Token, _: &Token) -> usize {
+        1
+    }
+    fn visit_ternary_expr(&mut self, condition: &Expr, then: &Expr, otherwise: &Expr) -> usize {
+        self.exprs(&[condition, then, otherwise])
+    }
+    fn visit_this_expr(&mut self, _: &Token) -> usize {
+        1
+    }
+    fn visit_unary_expr(&mut self, _: &Token, right: &Expr) -> usize {
+        self.exprs(&[right])
+    }
+    fn visit_variable_expr(&mut self, _: &Token) -> usize {
+        1
+    }
+}
+
+impl StmtVisitor<usize> for BoxedCounter {
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> usize {
+        1 + self.count(statements)
+    }
+    fn visit_break_stmt(&mut self, _: &Token) -> usize {
+        1
+    }
+    fn visit_case_stmt(&mut self, _: &Token, values: &[Expr], body: &[Stmt]) -> usize {
+        1 + values.iter().map(|v| self.visit_expr(v)).sum::<usize>() + self.count(body)
+    }
+    fn visit_class_stmt(
+        &mut self,
+        _: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+        getters: &[Stmt],
+        setters: &[Stmt],
+        class_methods: &[Stmt],
+    ) -> usize {
+        1 + superclass.map_or(0, |s| self.visit_expr(s))
+            + self.count(methods)
+            + self.count(getters)
+            + self.count(setters)
+            + self.count(class_methods)
+    }
+    fn visit_continue_stmt(&mut self, _: &Token) -> usize {
+        1
+    }
+    fn visit_do_while_stmt(&mut self, body: &Stmt, condition: &Expr) -> usize {
+        1 + self.visit_stmt(body) + self.visit_expr(condition)
+    }
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> usize {
+        1 + self.visit_expr(expression)
+    }
+    fn visit_function_stmt(&mut self, _: &Token, _: &[Token], body: &[Stmt]) -> usize {
+        1 + self.count(body)
+    }
+    fn visit_if_stmt(&mut self, condition: &Expr, then: &Stmt, otherwise: Option<&Stmt>) -> usize {
+        1 + self.visit_expr(condition)
+            + self.visit_stmt(then)
+            + otherwise.map_or(0, |s| self.visit_stmt(s))
+    }
+    fn visit_print_stmt(&mut self, expression: &Expr) -> usize {
+        1 + self.visit_expr(expression)
+    }
+    fn visit_return_stmt(&mut self, _: &Token, value: Option<&Expr>) -> usize {
+        1 + value.map_or(0, |v| self.visit_expr(v))
+    }
+    fn visit_switch_stmt(&mut self, _: &Token, subject: &Expr, cases: &[Stmt]) -> usize {
+        1 + self.visit_expr(subject) + self.count(cases)
+    }
+    fn visit_var_stmt(&mut self, _: &Token, initializer: Option<&Expr>) -> usize {
+        1 + initializer.map_or(0, |i| self.visit_expr(i))
+    }
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> usize {
+        1 + self.visit_expr(condition)
+            + self.visit_stmt(body)
+            + increment.map_or(0, |i| self.visit_expr(i))
+    }
+}
+
+// Counts the nodes of the arena copy.
+struct ArenaCounter;
+
+impl ArenaCounter {
+    fn count(&mut self, ast: &Ast, statements: &[StmtId]) -> usize {
+        statements.iter().map(|&s| self.visit_stmt(ast, s)).sum()
+    }
+
+    fn exprs(&mut self, ast: &Ast, exprs: &[ExprId]) -> usize {
+        1 + exprs
+            .iter()
+            .map(|&e| self.visit_expr(ast, e))
+            .sum::<usize>()
+    }
+}
+
+impl ExprNodeVisitor<usize> for ArenaCounter {
+    fn visit_array_expr(&mut self, ast: &Ast, elements: &[ExprId]) -> usize {
+        self.exprs(ast, elements)
+    }
+    fn visit_assign_expr(&mut self, ast: &Ast, _: &Token, value: ExprId) -> usize {
+        self.exprs(ast, &[value])
+    }
+    fn visit_binary_expr(&mut self, ast: &Ast, left: ExprId, _: &Token, right: ExprId) -> usize {
+        self.exprs(ast, &[left, right])
+    }
+    fn visit_call_expr(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        _: &Token,
+        arguments: &[ExprId],
+    ) -> usize {
+        self.exprs(ast, &[callee])
+            + arguments
+                .iter()
+                .map(|&a| self.visit_expr(ast, a))
+                .sum::<usize>()
+    }
+    fn visit_comma_expr(&mut self, ast: &Ast, expressions: &[ExprId]) -> usize {
+        self.exprs(ast, expressions)
+    }
+    fn visit_get_expr(&mut self, ast: &Ast, object: ExprId, _: &Token) -> usize {
+        self.exprs(ast, &[object])
+    }
+    fn visit_grouping_expr(&mut self, ast: &Ast, expression: ExprId) -> usize {
+        self.exprs(ast, &[expression])
+    }
+    fn visit_index_expr(&mut self, ast: &Ast, object: ExprId, _: &Token, index: ExprId) -> usize {
+        self.exprs(ast, &[object, index])
+    }
+    fn visit_interpolation_expr(&mut self, ast: &Ast, parts: &[ExprId]) -> usize {
+        self.exprs(ast, parts)
+    }
+    fn visit_literal_expr(&mut self, _: &Ast, _: &Object) -> usize {
+        1
+    }
+    fn visit_logical_expr(&mut self, ast: &Ast, left: ExprId, _: &Token, right: ExprId) -> usize {
+        self.exprs(ast, &[left, right])
+    }
+    fn visit_map_expr(&mut self, ast: &Ast, keys: &[ExprId], values: &[ExprId]) -> usize {
+        1 + keys
+            .iter()
+            .chain(values)
+            .map(|&e| self.visit_expr(ast, e))
+            .sum::<usize>()
+    }
+    fn visit_optional_chain_expr(&mut self, ast: &Ast, expression: ExprId) -> usize {
+        self.exprs(ast, &[expression])
+    }
+    fn visit_optional_get_expr(&mut self, ast: &Ast, object: ExprId, _: &Token) -> usize {
+        self.exprs(ast, &[object])
+    }
+    fn visit_set_expr(&mut self, ast: &Ast, object: ExprId, _: &Token, value: ExprId) -> usize {
+        self.exprs(ast, &[object, value])
+    }
+    fn visit_set_index_expr(
+        &mut self,
+        ast: &Ast,
+        object: ExprId,
+        _: &Token,
+        index: ExprId,
+        value: ExprId,
+    ) -> usize {
+        self.exprs(ast, &[object, index, value])
+    }
+    fn visit_super_expr(&mut self, _: &Ast, _: &Token, _: &Token) -> usize {
+        1
+    }
+    fn visit_ternary_expr(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then: ExprId,
+        otherwise: ExprId,
+    ) -> usize {
+        self.exprs(ast, &[condition, then, otherwise])
+    }
+    fn visit_this_expr(&mut self, _: &Ast, _: &Token) -> usize {
+        1
+    }
+    fn visit_unary_expr(&mut self, ast: &Ast, _: &Token, right: ExprId) -> usize {
+        self.exprs(ast, &[right])
+    }
+    fn visit_variable_expr(&mut self, _: &Ast, _: &Token) -> usize {
+        1
+    }
+}
+
+impl StmtNodeVisitor<usize> for ArenaCounter {
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[StmtId]) -> usize {
+        1 + self.count(ast, statements)
+    }
+    fn visit_break_stmt(&mut self, _: &Ast, _: &Token) -> usize {
+        1
+    }
+    fn visit_case_stmt(
+        &mut self,
+        ast: &Ast,
+        _: &Token,
+        values: &[ExprId],
+        body: &[StmtId],
+    ) -> usize {
+        self.exprs(ast, values) + self.count(ast, body)
+    }
+    fn visit_class_stmt(
+        &mut self,
+        ast: &Ast,
+        _: &Token,
+        superclass: Option<ExprId>,
+        methods: &[StmtId],
+        getters: &[StmtId],
+        setters: &[StmtId],
+        class_methods: &[StmtId],
+    ) -> usize {
+        1 + superclass.map_or(0, |s| self.visit_expr(ast, s))
+            + self.count(ast, methods)
+            + self.count(ast, getters)
+            + self.count(ast, setters)
+            + self.count(ast, class_methods)
+    }
+    fn visit_continue_stmt(&mut self, _: &Ast, _: &Token) -> usize {
+        1
+    }
+    fn visit_do_while_stmt(&mut self, ast: &Ast, body: StmtId, condition: ExprId) -> usize {
+        1 + self.visit_stmt(ast, body) + self.visit_expr(ast, condition)
+    }
+    fn visit_expression_stmt(&mut self, ast: &Ast, expression: ExprId) -> usize {
+        1 + self.visit_expr(ast, expression)
+    }
+    fn visit_function_stmt(&mut self, ast: &Ast, _: &Token, _: &[Token], body: &[StmtId]) -> usize {
+        1 + self.count(ast, body)
+    }
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then: StmtId,
+        otherwise: Option<StmtId>,
+    ) -> usize {
+        1 + self.visit_expr(ast, condition)
+            + self.visit_stmt(ast, then)
+            + otherwise.map_or(0, |s| self.visit_stmt(ast, s))
+    }
+    fn visit_print_stmt(&mut self, ast: &Ast, expression: ExprId) -> usize {
+        1 + self.visit_expr(ast, expression)
+    }
+    fn visit_return_stmt(&mut self, ast: &Ast, _: &Token, value: Option<ExprId>) -> usize {
+        1 + value.map_or(0, |v| self.visit_expr(ast, v))
+    }
+    fn visit_switch_stmt(
+        &mut self,
+        ast: &Ast,
+        _: &Token,
+        subject: ExprId,
+        cases: &[StmtId],
+    ) -> usize {
+        1 + self.visit_expr(ast, subject) + self.count(ast, cases)
+    }
+    fn visit_var_stmt(&mut self, ast: &Ast, _: &Token, initializer: Option<ExprId>) -> usize {
+        1 + initializer.map_or(0, |i| self.visit_expr(ast, i))
+    }
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        body: StmtId,
+        increment: Option<ExprId>,
+    ) -> usize {
+        1 + self.visit_expr(ast, condition)
+            + self.visit_stmt(ast, body)
+            + increment.map_or(0, |i| self.visit_expr(ast, i))
+    }
+}
@@ -0,0 +1,75 @@
+//! Running small programs that spend their time reading and writing local
+//! variables: recursive calls, nested loops and closures.
+//!
+//! Run with `cargo bench --bench interpreter`.
+
+use rlox::check;
+use rlox::interpreter::Interpreter;
+use std::hint::black_box;
+use std::io;
+use std::time::{Duration, Instant};
+
+const FIB: &str = r#"
+fun fib(n) {
+    if (n < 2) return n;
+    return fib(n - 2) + fib(n - 1);
+}
+print fib(22);
+"#;
+
+const LOOPS: &str = r#"
+fun loops() {
+    var total = 0;
+    for (var i = 0; i < 300; i = i + 1) {
+        for (var j = 0; j < 300; j = j + 1) {
+            var product = i * j;
+            total = total + product - j;
+        }
+    }
+    return total;
+}
+print loops();
+"#;
+
+const CLOSURES: &str = r#"
+fun counter() {
+    var count = 0;
+    fun increment() {
+        count = count + 1;
+        return count;
+    }
+    return increment;
+}
+
+fun run() {
+    var next = counter();
+    var last = 0;
+    var i = 0;
+    while (i < 50000) {
+        last = next();
+        i = i + 1;
+    }
+    return last;
+}
+print run();
+"#;
+
+fn measure(name: &str, source: &str, iterations: u32) {
+    let program = check(source).expect("benchmark source checks");
+    let mut best = Duration::MAX;
+    for _ in 0..iterations {
+        let mut interpreter = Interpreter::with_output(io::sink());
+        let start = Instant::now();
+        interpreter
+            .interpret(black_box(&program))
+            .expect("benchmark program runs");
+        best = best.min(start.elapsed());
+    }
+    println!("{name:<12} best {best:>10.2?}");
+}
+
+fn main() {
+    measure("fib", FIB, 30);
+    measure("loops", LOOPS, 30);
+    measure("closures", CLOSURES, 30);
+}
@@ -0,0 +1,108 @@
+//! Scanner throughput on a generated multi-megabyte Lox program.
+//!
+//! Run with `cargo bench --bench scanner`.
+
+use rlox::helpers::keyword_type;
+use rlox::scanner::Scanner;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CHUNK: &str = r#"
+// Compute a few things in a loop.
+class Point {
+    init(x, y) { this.x = x; this.y = y; }
+    length() { return sqrt(this.x * this.x + this.y * this.y); }
+}
+
+fun fib(n) {
+    if (n <= 1) return n;
+    return fib(n - 2) + fib(n - 1);
+}
+
+/* A block comment
+   spanning lines. */
+var total = 0;
+for (var i = 0; i < 1_000; i = i + 1) {
+    total = total + fib(i % 10) * 0x10;
+    print "total so far: ${total}";
+}
+"#;
+
+fn source(target_bytes: usize) -> String {
+    let mut source = String::with_capacity(target_bytes + CHUNK.len());
+    while source.len() < target_bytes {
+        source.push_str(CHUNK);
+    }
+    source
+}
+
+fn measure(name: &str, source: &str, iterations: u32) {
+    time(&format!("{name} (owned)"), source, iterations, |source| {
+        Scanner::new(source).count()
+    });
+    time(
+        &format!("{name} (borrowed)"),
+        source,
+        iterations,
+        |source| Scanner::new(source).borrowed_tokens().count(),
+    );
+}
+
+fn time(name: &str, source: &str, iterations: u32, scan: impl Fn(&str) -> usize) {
+    let mut best = Duration::MAX;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let count = scan(black_box(source));
+        best = best.min(start.elapsed());
+        black_box(count);
+    }
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{name:<28} {megabytes:>6.2} MiB  best {:>8.2?}  {:>8.1} MiB/s",
+        best,
+        megabytes / best.as_secs_f64()
+    );
+}
+
+fn measure_keyword_lookup(iterations: u32) {
+    let words = [
+        "var", "format", "fun", "forest", "classy", "return", "this", "thistle", "or", "nil",
+        "and", "whiled", "alpha", "count", "print", "x",
+    ];
+    let mut best = Duration::MAX;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        for _ in 0..100_000 {
+            for word in words {
+                black_box(keyword_type(black_box(word)));
+            }
+        }
+        best = best.min(start.elapsed());
+    }
+
+    let lookups = 100_000 * words.len();
+    println!(
+        "{:<28} {lookups:>6} lookups  best {:>8.2?}  {:>6.2} ns/lookup",
+        "keyword_type",
+        best,
+        best.as_secs_f64() * 1e9 / lookups as f64
+    );
+}
+
+fn main() {
+    let source = source(4 * 1024 * 1024);
+    measure("mixed program", &source, 10);
+
+    let identifiers = "alpha beta gamma delta epsilon zeta eta theta\n".repeat(100_000);
+    measure("identifiers", &identifiers, 10);
+
+    let keywords = "var format = fun forest(classy) { return this.thistle or nil and whiled; }\n"
+        .repeat(60_000);
+    measure("keywords", &keywords, 10);
+
+    let unicode = "var café = \"naïve ünïcödé\"; // ☕\n".repeat(100_000);
+    measure("non-ASCII", &unicode, 10);
+
+    measure_keyword_lookup(10);
+}
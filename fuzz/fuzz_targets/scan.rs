@@ -0,0 +1,11 @@
+//! Feeds arbitrary bytes to the scanner.
+//!
+//! Run with `cargo +nightly fuzz run scan` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    rlox::scan_bytes(data);
+});
@@ -0,0 +1,820 @@
+// AUTO-GENERATED by src/bin/generate_ast.rs. Do not hand-edit.
+
+//! A compact, index-based copy of the AST. All nodes of a program live in
+//! one [`Ast`]: children are small ids into its vectors and tokens and
+//! literal values are stored out of line, so a node is a few words and a
+//! walk over the tree touches little memory. [`Ast::lower`] copies a parsed
+//! program into an arena, and the `*NodeVisitor` traits walk it the way
+//! the visitors in [`crate::ast`] walk the boxed tree.
+
+// Visitor methods take the arena and then one parameter per field.
+#![allow(clippy::too_many_arguments)]
+
+use crate::ast::{Expr, Object, Stmt};
+use crate::span::Span;
+use crate::token::Token;
+
+/// Where a token is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenId(u32);
+
+/// Where a literal value is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValueId(u32);
+
+/// A run of consecutive entries in one of an [`Ast`]'s vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListRange {
+    start: u32,
+    len: u32,
+}
+
+impl ListRange {
+    fn slice<T>(self, items: &[T]) -> &[T] {
+        let start = self.start as usize;
+        &items[start..start + self.len as usize]
+    }
+}
+
+pub type TokenList = ListRange;
+pub type ExprList = ListRange;
+pub type StmtList = ListRange;
+
+fn index(len: usize) -> u32 {
+    u32::try_from(len).expect("an AST has fewer than 2^32 nodes")
+}
+
+/// Every node, token and value of a lowered program.
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    exprs: Vec<ExprNode>,
+    stmts: Vec<StmtNode>,
+    // Kept apart from the nodes, which walks read far more often.
+    expr_spans: Vec<Span>,
+    stmt_spans: Vec<Span>,
+    tokens: Vec<Token>,
+    values: Vec<Object>,
+    expr_lists: Vec<ExprId>,
+    stmt_lists: Vec<StmtId>,
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies `statements` into the arena and returns their ids.
+    pub fn lower(&mut self, statements: &[Stmt]) -> StmtList {
+        self.lower_stmts(statements)
+    }
+
+    pub fn expr(&self, id: ExprId) -> &ExprNode {
+        &self.exprs[id.0 as usize]
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &StmtNode {
+        &self.stmts[id.0 as usize]
+    }
+
+    /// The source node `id` was parsed from, see [`Expr::span`].
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self.expr_spans[id.0 as usize]
+    }
+
+    /// The source node `id` was parsed from, see [`Stmt::span`].
+    pub fn stmt_span(&self, id: StmtId) -> Span {
+        self.stmt_spans[id.0 as usize]
+    }
+
+    pub fn token(&self, id: TokenId) -> &Token {
+        &self.tokens[id.0 as usize]
+    }
+
+    pub fn value(&self, id: ValueId) -> &Object {
+        &self.values[id.0 as usize]
+    }
+
+    pub fn token_list(&self, list: TokenList) -> &[Token] {
+        list.slice(&self.tokens)
+    }
+
+    pub fn expr_list(&self, list: ExprList) -> &[ExprId] {
+        list.slice(&self.expr_lists)
+    }
+
+    pub fn stmt_list(&self, list: StmtList) -> &[StmtId] {
+        list.slice(&self.stmt_lists)
+    }
+
+    fn push_token(&mut self, token: &Token) -> TokenId {
+        self.tokens.push(token.clone());
+        TokenId(index(self.tokens.len() - 1))
+    }
+
+    fn push_tokens(&mut self, tokens: &[Token]) -> TokenList {
+        let start = index(self.tokens.len());
+        self.tokens.extend_from_slice(tokens);
+        ListRange {
+            start,
+            len: index(tokens.len()),
+        }
+    }
+
+    fn push_value(&mut self, value: &Object) -> ValueId {
+        self.values.push(value.clone());
+        ValueId(index(self.values.len() - 1))
+    }
+
+    fn lower_exprs(&mut self, exprs: &[Expr]) -> ExprList {
+        let ids: Vec<ExprId> = exprs.iter().map(|expr| self.lower_expr(expr)).collect();
+        let start = index(self.expr_lists.len());
+        self.expr_lists.extend(ids);
+        ListRange {
+            start,
+            len: index(exprs.len()),
+        }
+    }
+
+    fn lower_stmts(&mut self, stmts: &[Stmt]) -> StmtList {
+        let ids: Vec<StmtId> = stmts.iter().map(|stmt| self.lower_stmt(stmt)).collect();
+        let start = index(self.stmt_lists.len());
+        self.stmt_lists.extend(ids);
+        ListRange {
+            start,
+            len: index(stmts.len()),
+        }
+    }
+}
+
+/// Where a `Expr` node is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(u32);
+
+/// A `Expr` stored in an [`Ast`], with ids in place of its children.
+#[derive(Debug, Clone, Copy)]
+pub enum ExprNode {
+    Array {
+        elements: ExprList,
+    },
+    Assign {
+        name: TokenId,
+        value: ExprId,
+    },
+    Binary {
+        left: ExprId,
+        operator: TokenId,
+        right: ExprId,
+    },
+    Call {
+        callee: ExprId,
+        paren: TokenId,
+        arguments: ExprList,
+    },
+    Comma {
+        expressions: ExprList,
+    },
+    Get {
+        object: ExprId,
+        name: TokenId,
+    },
+    Grouping {
+        expression: ExprId,
+    },
+    Index {
+        object: ExprId,
+        bracket: TokenId,
+        index: ExprId,
+    },
+    Interpolation {
+        parts: ExprList,
+    },
+    Literal {
+        value: ValueId,
+    },
+    Logical {
+        left: ExprId,
+        operator: TokenId,
+        right: ExprId,
+    },
+    Map {
+        keys: ExprList,
+        values: ExprList,
+    },
+    OptionalChain {
+        expression: ExprId,
+    },
+    OptionalGet {
+        object: ExprId,
+        name: TokenId,
+    },
+    Set {
+        object: ExprId,
+        name: TokenId,
+        value: ExprId,
+    },
+    SetIndex {
+        object: ExprId,
+        bracket: TokenId,
+        index: ExprId,
+        value: ExprId,
+    },
+    Super {
+        keyword: TokenId,
+        method: TokenId,
+    },
+    Ternary {
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    },
+    This {
+        keyword: TokenId,
+    },
+    Unary {
+        operator: TokenId,
+        right: ExprId,
+    },
+    Variable {
+        name: TokenId,
+    },
+}
+
+/// Walks `Expr` nodes in an [`Ast`], like [`crate::ast::ExprVisitor`]
+/// does for the boxed tree. Children are passed as ids to visit through
+/// `ast`; tokens and values are passed resolved.
+pub trait ExprNodeVisitor<R> {
+    /// Dispatches to the method for `expr`'s variant.
+    fn visit_expr(&mut self, ast: &Ast, expr: ExprId) -> R
+    where
+        Self: Sized,
+    {
+        ast.accept_expr(expr, self)
+    }
+
+    fn visit_array_expr(&mut self, ast: &Ast, elements: &[ExprId]) -> R;
+    fn visit_assign_expr(&mut self, ast: &Ast, name: &Token, value: ExprId) -> R;
+    fn visit_binary_expr(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId) -> R;
+    fn visit_call_expr(
+        &mut self,
+        ast: &Ast,
+        callee: ExprId,
+        paren: &Token,
+        arguments: &[ExprId],
+    ) -> R;
+    fn visit_comma_expr(&mut self, ast: &Ast, expressions: &[ExprId]) -> R;
+    fn visit_get_expr(&mut self, ast: &Ast, object: ExprId, name: &Token) -> R;
+    fn visit_grouping_expr(&mut self, ast: &Ast, expression: ExprId) -> R;
+    fn visit_index_expr(&mut self, ast: &Ast, object: ExprId, bracket: &Token, index: ExprId) -> R;
+    fn visit_interpolation_expr(&mut self, ast: &Ast, parts: &[ExprId]) -> R;
+    fn visit_literal_expr(&mut self, ast: &Ast, value: &Object) -> R;
+    fn visit_logical_expr(&mut self, ast: &Ast, left: ExprId, operator: &Token, right: ExprId)
+    -> R;
+    fn visit_map_expr(&mut self, ast: &Ast, keys: &[ExprId], values: &[ExprId]) -> R;
+    fn visit_optional_chain_expr(&mut self, ast: &Ast, expression: ExprId) -> R;
+    fn visit_optional_get_expr(&mut self, ast: &Ast, object: ExprId, name: &Token) -> R;
+    fn visit_set_expr(&mut self, ast: &Ast, object: ExprId, name: &Token, value: ExprId) -> R;
+    fn visit_set_index_expr(
+        &mut self,
+        ast: &Ast,
+        object: ExprId,
+        bracket: &Token,
+        index: ExprId,
+        value: ExprId,
+    ) -> R;
+    fn visit_super_expr(&mut self, ast: &Ast, keyword: &Token, method: &Token) -> R;
+    fn visit_ternary_expr(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
+    ) -> R;
+    fn visit_this_expr(&mut self, ast: &Ast, keyword: &Token) -> R;
+    fn visit_unary_expr(&mut self, ast: &Ast, operator: &Token, right: ExprId) -> R;
+    fn visit_variable_expr(&mut self, ast: &Ast, name: &Token) -> R;
+}
+
+/// Where a `Stmt` node is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StmtId(u32);
+
+/// A `Stmt` stored in an [`Ast`], with ids in place of its children.
+#[derive(Debug, Clone, Copy)]
+pub enum StmtNode {
+    Block {
+        statements: StmtList,
+    },
+    Break {
+        keyword: TokenId,
+    },
+    Case {
+        keyword: TokenId,
+        values: ExprList,
+        body: StmtList,
+    },
+    Class {
+        name: TokenId,
+        superclass: Option<ExprId>,
+        methods: StmtList,
+        getters: StmtList,
+        setters: StmtList,
+        class_methods: StmtList,
+    },
+    Continue {
+        keyword: TokenId,
+    },
+    DoWhile {
+        body: StmtId,
+        condition: ExprId,
+    },
+    Expression {
+        expression: ExprId,
+    },
+    Function {
+        name: TokenId,
+        params: TokenList,
+        body: StmtList,
+    },
+    If {
+        condition: ExprId,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    },
+    Print {
+        expression: ExprId,
+    },
+    Return {
+        keyword: TokenId,
+        value: Option<ExprId>,
+    },
+    Switch {
+        keyword: TokenId,
+        subject: ExprId,
+        cases: StmtList,
+    },
+    Var {
+        name: TokenId,
+        initializer: Option<ExprId>,
+    },
+    While {
+        condition: ExprId,
+        body: StmtId,
+        increment: Option<ExprId>,
+    },
+}
+
+/// Walks `Stmt` nodes in an [`Ast`], like [`crate::ast::StmtVisitor`]
+/// does for the boxed tree. Children are passed as ids to visit through
+/// `ast`; tokens and values are passed resolved.
+pub trait StmtNodeVisitor<R> {
+    /// Dispatches to the method for `stmt`'s variant.
+    fn visit_stmt(&mut self, ast: &Ast, stmt: StmtId) -> R
+    where
+        Self: Sized,
+    {
+        ast.accept_stmt(stmt, self)
+    }
+
+    fn visit_block_stmt(&mut self, ast: &Ast, statements: &[StmtId]) -> R;
+    fn visit_break_stmt(&mut self, ast: &Ast, keyword: &Token) -> R;
+    fn visit_case_stmt(
+        &mut self,
+        ast: &Ast,
+        keyword: &Token,
+        values: &[ExprId],
+        body: &[StmtId],
+    ) -> R;
+    fn visit_class_stmt(
+        &mut self,
+        ast: &Ast,
+        name: &Token,
+        superclass: Option<ExprId>,
+        methods: &[StmtId],
+        getters: &[StmtId],
+        setters: &[StmtId],
+        class_methods: &[StmtId],
+    ) -> R;
+    fn visit_continue_stmt(&mut self, ast: &Ast, keyword: &Token) -> R;
+    fn visit_do_while_stmt(&mut self, ast: &Ast, body: StmtId, condition: ExprId) -> R;
+    fn visit_expression_stmt(&mut self, ast: &Ast, expression: ExprId) -> R;
+    fn visit_function_stmt(
+        &mut self,
+        ast: &Ast,
+        name: &Token,
+        params: &[Token],
+        body: &[StmtId],
+    ) -> R;
+    fn visit_if_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) -> R;
+    fn visit_print_stmt(&mut self, ast: &Ast, expression: ExprId) -> R;
+    fn visit_return_stmt(&mut self, ast: &Ast, keyword: &Token, value: Option<ExprId>) -> R;
+    fn visit_switch_stmt(
+        &mut self,
+        ast: &Ast,
+        keyword: &Token,
+        subject: ExprId,
+        cases: &[StmtId],
+    ) -> R;
+    fn visit_var_stmt(&mut self, ast: &Ast, name: &Token, initializer: Option<ExprId>) -> R;
+    fn visit_while_stmt(
+        &mut self,
+        ast: &Ast,
+        condition: ExprId,
+        body: StmtId,
+        increment: Option<ExprId>,
+    ) -> R;
+}
+
+impl Ast {
+    /// Copies `expr` and everything under it into the arena.
+    pub fn lower_expr(&mut self, expr: &Expr) -> ExprId {
+        let node = match expr {
+            Expr::Array { elements, .. } => ExprNode::Array {
+                elements: self.lower_exprs(elements),
+            },
+            Expr::Assign { name, value, .. } => ExprNode::Assign {
+                name: self.push_token(name),
+                value: self.lower_expr(value),
+            },
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => ExprNode::Binary {
+                left: self.lower_expr(left),
+                operator: self.push_token(operator),
+                right: self.lower_expr(right),
+            },
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+                ..
+            } => ExprNode::Call {
+                callee: self.lower_expr(callee),
+                paren: self.push_token(paren),
+                arguments: self.lower_exprs(arguments),
+            },
+            Expr::Comma { expressions, .. } => ExprNode::Comma {
+                expressions: self.lower_exprs(expressions),
+            },
+            Expr::Get { object, name, .. } => ExprNode::Get {
+                object: self.lower_expr(object),
+                name: self.push_token(name),
+            },
+            Expr::Grouping { expression, .. } => ExprNode::Grouping {
+                expression: self.lower_expr(expression),
+            },
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => ExprNode::Index {
+                object: self.lower_expr(object),
+                bracket: self.push_token(bracket),
+                index: self.lower_expr(index),
+            },
+            Expr::Interpolation { parts, .. } => ExprNode::Interpolation {
+                parts: self.lower_exprs(parts),
+            },
+            Expr::Literal { value, .. } => ExprNode::Literal {
+                value: self.push_value(value),
+            },
+            Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => ExprNode::Logical {
+                left: self.lower_expr(left),
+                operator: self.push_token(operator),
+                right: self.lower_expr(right),
+            },
+            Expr::Map { keys, values, .. } => ExprNode::Map {
+                keys: self.lower_exprs(keys),
+                values: self.lower_exprs(values),
+            },
+            Expr::OptionalChain { expression, .. } => ExprNode::OptionalChain {
+                expression: self.lower_expr(expression),
+            },
+            Expr::OptionalGet { object, name, .. } => ExprNode::OptionalGet {
+                object: self.lower_expr(object),
+                name: self.push_token(name),
+            },
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => ExprNode::Set {
+                object: self.lower_expr(object),
+                name: self.push_token(name),
+                value: self.lower_expr(value),
+            },
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+                ..
+            } => ExprNode::SetIndex {
+                object: self.lower_expr(object),
+                bracket: self.push_token(bracket),
+                index: self.lower_expr(index),
+                value: self.lower_expr(value),
+            },
+            Expr::Super {
+                keyword, method, ..
+            } => ExprNode::Super {
+                keyword: self.push_token(keyword),
+                method: self.push_token(method),
+            },
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => ExprNode::Ternary {
+                condition: self.lower_expr(condition),
+                then_branch: self.lower_expr(then_branch),
+                else_branch: self.lower_expr(else_branch),
+            },
+            Expr::This { keyword, .. } => ExprNode::This {
+                keyword: self.push_token(keyword),
+            },
+            Expr::Unary {
+                operator, right, ..
+            } => ExprNode::Unary {
+                operator: self.push_token(operator),
+                right: self.lower_expr(right),
+            },
+            Expr::Variable { name, .. } => ExprNode::Variable {
+                name: self.push_token(name),
+            },
+        };
+        self.exprs.push(node);
+        self.expr_spans.push(expr.span());
+        ExprId(index(self.exprs.len() - 1))
+    }
+
+    /// Calls the `visitor` method for the variant of node `id`.
+    pub fn accept_expr<R, V: ExprNodeVisitor<R>>(&self, id: ExprId, visitor: &mut V) -> R {
+        match *self.expr(id) {
+            ExprNode::Array { elements } => {
+                visitor.visit_array_expr(self, self.expr_list(elements))
+            }
+            ExprNode::Assign { name, value } => {
+                visitor.visit_assign_expr(self, self.token(name), value)
+            }
+            ExprNode::Binary {
+                left,
+                operator,
+                right,
+            } => visitor.visit_binary_expr(self, left, self.token(operator), right),
+            ExprNode::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                visitor.visit_call_expr(self, callee, self.token(paren), self.expr_list(arguments))
+            }
+            ExprNode::Comma { expressions } => {
+                visitor.visit_comma_expr(self, self.expr_list(expressions))
+            }
+            ExprNode::Get { object, name } => {
+                visitor.visit_get_expr(self, object, self.token(name))
+            }
+            ExprNode::Grouping { expression } => visitor.visit_grouping_expr(self, expression),
+            ExprNode::Index {
+                object,
+                bracket,
+                index,
+            } => visitor.visit_index_expr(self, object, self.token(bracket), index),
+            ExprNode::Interpolation { parts } => {
+                visitor.visit_interpolation_expr(self, self.expr_list(parts))
+            }
+            ExprNode::Literal { value } => visitor.visit_literal_expr(self, self.value(value)),
+            ExprNode::Logical {
+                left,
+                operator,
+                right,
+            } => visitor.visit_logical_expr(self, left, self.token(operator), right),
+            ExprNode::Map { keys, values } => {
+                visitor.visit_map_expr(self, self.expr_list(keys), self.expr_list(values))
+            }
+            ExprNode::OptionalChain { expression } => {
+                visitor.visit_optional_chain_expr(self, expression)
+            }
+            ExprNode::OptionalGet { object, name } => {
+                visitor.visit_optional_get_expr(self, object, self.token(name))
+            }
+            ExprNode::Set {
+                object,
+                name,
+                value,
+            } => visitor.visit_set_expr(self, object, self.token(name), value),
+            ExprNode::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+            } => visitor.visit_set_index_expr(self, object, self.token(bracket), index, value),
+            ExprNode::Super { keyword, method } => {
+                visitor.visit_super_expr(self, self.token(keyword), self.token(method))
+            }
+            ExprNode::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => visitor.visit_ternary_expr(self, condition, then_branch, else_branch),
+            ExprNode::This { keyword } => visitor.visit_this_expr(self, self.token(keyword)),
+            ExprNode::Unary { operator, right } => {
+                visitor.visit_unary_expr(self, self.token(operator), right)
+            }
+            ExprNode::Variable { name } => visitor.visit_variable_expr(self, self.token(name)),
+        }
+    }
+
+    /// Copies `stmt` and everything under it into the arena.
+    pub fn lower_stmt(&mut self, stmt: &Stmt) -> StmtId {
+        let node = match stmt {
+            Stmt::Block { statements, .. } => StmtNode::Block {
+                statements: self.lower_stmts(statements),
+            },
+            Stmt::Break { keyword, .. } => StmtNode::Break {
+                keyword: self.push_token(keyword),
+            },
+            Stmt::Case {
+                keyword,
+                values,
+                body,
+                ..
+            } => StmtNode::Case {
+                keyword: self.push_token(keyword),
+                values: self.lower_exprs(values),
+                body: self.lower_stmts(body),
+            },
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                getters,
+                setters,
+                class_methods,
+                ..
+            } => StmtNode::Class {
+                name: self.push_token(name),
+                superclass: superclass.as_deref().map(|e| self.lower_expr(e)),
+                methods: self.lower_stmts(methods),
+                getters: self.lower_stmts(getters),
+                setters: self.lower_stmts(setters),
+                class_methods: self.lower_stmts(class_methods),
+            },
+            Stmt::Continue { keyword, .. } => StmtNode::Continue {
+                keyword: self.push_token(keyword),
+            },
+            Stmt::DoWhile {
+                body, condition, ..
+            } => StmtNode::DoWhile {
+                body: self.lower_stmt(body),
+                condition: self.lower_expr(condition),
+            },
+            Stmt::Expression { expression, .. } => StmtNode::Expression {
+                expression: self.lower_expr(expression),
+            },
+            Stmt::Function {
+                name, params, body, ..
+            } => StmtNode::Function {
+                name: self.push_token(name),
+                params: self.push_tokens(params),
+                body: self.lower_stmts(body),
+            },
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => StmtNode::If {
+                condition: self.lower_expr(condition),
+                then_branch: self.lower_stmt(then_branch),
+                else_branch: else_branch.as_deref().map(|s| self.lower_stmt(s)),
+            },
+            Stmt::Print { expression, .. } => StmtNode::Print {
+                expression: self.lower_expr(expression),
+            },
+            Stmt::Return { keyword, value, .. } => StmtNode::Return {
+                keyword: self.push_token(keyword),
+                value: value.as_deref().map(|e| self.lower_expr(e)),
+            },
+            Stmt::Switch {
+                keyword,
+                subject,
+                cases,
+                ..
+            } => StmtNode::Switch {
+                keyword: self.push_token(keyword),
+                subject: self.lower_expr(subject),
+                cases: self.lower_stmts(cases),
+            },
+            Stmt::Var {
+                name, initializer, ..
+            } => StmtNode::Var {
+                name: self.push_token(name),
+                initializer: initializer.as_deref().map(|e| self.lower_expr(e)),
+            },
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => StmtNode::While {
+                condition: self.lower_expr(condition),
+                body: self.lower_stmt(body),
+                increment: increment.as_deref().map(|e| self.lower_expr(e)),
+            },
+        };
+        self.stmts.push(node);
+        self.stmt_spans.push(stmt.span());
+        StmtId(index(self.stmts.len() - 1))
+    }
+
+    /// Calls the `visitor` method for the variant of node `id`.
+    pub fn accept_stmt<R, V: StmtNodeVisitor<R>>(&self, id: StmtId, visitor: &mut V) -> R {
+        match *self.stmt(id) {
+            StmtNode::Block { statements } => {
+                visitor.visit_block_stmt(self, self.stmt_list(statements))
+            }
+            StmtNode::Break { keyword } => visitor.visit_break_stmt(self, self.token(keyword)),
+            StmtNode::Case {
+                keyword,
+                values,
+                body,
+            } => visitor.visit_case_stmt(
+                self,
+                self.token(keyword),
+                self.expr_list(values),
+                self.stmt_list(body),
+            ),
+            StmtNode::Class {
+                name,
+                superclass,
+                methods,
+                getters,
+                setters,
+                class_methods,
+            } => visitor.visit_class_stmt(
+                self,
+                self.token(name),
+                superclass,
+                self.stmt_list(methods),
+                self.stmt_list(getters),
+                self.stmt_list(setters),
+                self.stmt_list(class_methods),
+            ),
+            StmtNode::Continue { keyword } => {
+                visitor.visit_continue_stmt(self, self.token(keyword))
+            }
+            StmtNode::DoWhile { body, condition } => {
+                visitor.visit_do_while_stmt(self, body, condition)
+            }
+            StmtNode::Expression { expression } => visitor.visit_expression_stmt(self, expression),
+            StmtNode::Function { name, params, body } => visitor.visit_function_stmt(
+                self,
+                self.token(name),
+                self.token_list(params),
+                self.stmt_list(body),
+            ),
+            StmtNode::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => visitor.visit_if_stmt(self, condition, then_branch, else_branch),
+            StmtNode::Print { expression } => visitor.visit_print_stmt(self, expression),
+            StmtNode::Return { keyword, value } => {
+                visitor.visit_return_stmt(self, self.token(keyword), value)
+            }
+            StmtNode::Switch {
+                keyword,
+                subject,
+                cases,
+            } => {
+                visitor.visit_switch_stmt(self, self.token(keyword), subject, self.stmt_list(cases))
+            }
+            StmtNode::Var { name, initializer } => {
+                visitor.visit_var_stmt(self, self.token(name), initializer)
+            }
+            StmtNode::While {
+                condition,
+                body,
+                increment,
+            } => visitor.visit_while_stmt(self, condition, body, increment),
+        }
+    }
+}
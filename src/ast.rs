@@ -0,0 +1,466 @@
+// AUTO-GENERATED by src/bin/generate_ast.rs. Do not hand-edit.
+
+#![allow(clippy::explicit_auto_deref)]
+
+use crate::span::Span;
+use crate::token::Token;
+use crate::value::Value;
+
+pub type Object = Value;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Array {
+        elements: Vec<Expr>,
+        span: Span,
+    },
+    Assign {
+        name: Token,
+        value: Box<Expr>,
+        span: Span,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+        span: Span,
+    },
+    Comma {
+        expressions: Vec<Expr>,
+        span: Span,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+        span: Span,
+    },
+    Grouping {
+        expression: Box<Expr>,
+        span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        span: Span,
+    },
+    Interpolation {
+        parts: Vec<Expr>,
+        span: Span,
+    },
+    Literal {
+        value: Object,
+        span: Span,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Map {
+        keys: Vec<Expr>,
+        values: Vec<Expr>,
+        span: Span,
+    },
+    OptionalChain {
+        expression: Box<Expr>,
+        span: Span,
+    },
+    OptionalGet {
+        object: Box<Expr>,
+        name: Token,
+        span: Span,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+        span: Span,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+        span: Span,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+        span: Span,
+    },
+    This {
+        keyword: Token,
+        span: Span,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Variable {
+        name: Token,
+        span: Span,
+    },
+}
+
+/// A pass over `Expr` nodes, with one method per variant. Each pass
+/// (printer, resolver, interpreter, ...) implements this trait for its
+/// own result type `R`.
+pub trait ExprVisitor<R> {
+    /// Dispatches to the method for `expr`'s variant.
+    fn visit_expr(&mut self, expr: &Expr) -> R
+    where
+        Self: Sized,
+    {
+        expr.accept(self)
+    }
+
+    fn visit_array_expr(&mut self, elements: &[Expr]) -> R;
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> R;
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> R;
+    fn visit_comma_expr(&mut self, expressions: &[Expr]) -> R;
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> R;
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> R;
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> R;
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> R;
+    fn visit_literal_expr(&mut self, value: &Object) -> R;
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;
+    fn visit_map_expr(&mut self, keys: &[Expr], values: &[Expr]) -> R;
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) -> R;
+    fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> R;
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> R;
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> R;
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> R;
+    fn visit_ternary_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr)
+    -> R;
+    fn visit_this_expr(&mut self, keyword: &Token) -> R;
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> R;
+    fn visit_variable_expr(&mut self, name: &Token) -> R;
+}
+
+impl Expr {
+    /// Calls the `visitor` method for this variant with its fields.
+    pub fn accept<R, V: ExprVisitor<R>>(&self, visitor: &mut V) -> R {
+        match self {
+            Expr::Array { elements, .. } => visitor.visit_array_expr(elements),
+            Expr::Assign { name, value, .. } => visitor.visit_assign_expr(name, &**value),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => visitor.visit_binary_expr(&**left, operator, &**right),
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+                ..
+            } => visitor.visit_call_expr(&**callee, paren, arguments),
+            Expr::Comma { expressions, .. } => visitor.visit_comma_expr(expressions),
+            Expr::Get { object, name, .. } => visitor.visit_get_expr(&**object, name),
+            Expr::Grouping { expression, .. } => visitor.visit_grouping_expr(&**expression),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => visitor.visit_index_expr(&**object, bracket, &**index),
+            Expr::Interpolation { parts, .. } => visitor.visit_interpolation_expr(parts),
+            Expr::Literal { value, .. } => visitor.visit_literal_expr(value),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => visitor.visit_logical_expr(&**left, operator, &**right),
+            Expr::Map { keys, values, .. } => visitor.visit_map_expr(keys, values),
+            Expr::OptionalChain { expression, .. } => {
+                visitor.visit_optional_chain_expr(&**expression)
+            }
+            Expr::OptionalGet { object, name, .. } => {
+                visitor.visit_optional_get_expr(&**object, name)
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => visitor.visit_set_expr(&**object, name, &**value),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+                ..
+            } => visitor.visit_set_index_expr(&**object, bracket, &**index, &**value),
+            Expr::Super {
+                keyword, method, ..
+            } => visitor.visit_super_expr(keyword, method),
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => visitor.visit_ternary_expr(&**condition, &**then_branch, &**else_branch),
+            Expr::This { keyword, .. } => visitor.visit_this_expr(keyword),
+            Expr::Unary {
+                operator, right, ..
+            } => visitor.visit_unary_expr(operator, &**right),
+            Expr::Variable { name, .. } => visitor.visit_variable_expr(name),
+        }
+    }
+
+    /// The source this node was parsed from, from its first token
+    /// through its last.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Array { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Comma { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::Interpolation { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Map { span, .. }
+            | Expr::OptionalChain { span, .. }
+            | Expr::OptionalGet { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::SetIndex { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::Ternary { span, .. }
+            | Expr::This { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Variable { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Stmt {
+    Block {
+        statements: Vec<Stmt>,
+        span: Span,
+    },
+    Break {
+        keyword: Token,
+        span: Span,
+    },
+    Case {
+        keyword: Token,
+        values: Vec<Expr>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    Class {
+        name: Token,
+        superclass: Option<Box<Expr>>,
+        methods: Vec<Stmt>,
+        getters: Vec<Stmt>,
+        setters: Vec<Stmt>,
+        class_methods: Vec<Stmt>,
+        span: Span,
+    },
+    Continue {
+        keyword: Token,
+        span: Span,
+    },
+    DoWhile {
+        body: Box<Stmt>,
+        condition: Box<Expr>,
+        span: Span,
+    },
+    Expression {
+        expression: Box<Expr>,
+        span: Span,
+    },
+    Function {
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    If {
+        condition: Box<Expr>,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+        span: Span,
+    },
+    Print {
+        expression: Box<Expr>,
+        span: Span,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Box<Expr>>,
+        span: Span,
+    },
+    Switch {
+        keyword: Token,
+        subject: Box<Expr>,
+        cases: Vec<Stmt>,
+        span: Span,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Box<Expr>>,
+        span: Span,
+    },
+    While {
+        condition: Box<Expr>,
+        body: Box<Stmt>,
+        increment: Option<Box<Expr>>,
+        span: Span,
+    },
+}
+
+/// A pass over `Stmt` nodes, with one method per variant. Each pass
+/// (printer, resolver, interpreter, ...) implements this trait for its
+/// own result type `R`.
+pub trait StmtVisitor<R> {
+    /// Dispatches to the method for `stmt`'s variant.
+    fn visit_stmt(&mut self, stmt: &Stmt) -> R
+    where
+        Self: Sized,
+    {
+        stmt.accept(self)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> R;
+    fn visit_break_stmt(&mut self, keyword: &Token) -> R;
+    fn visit_case_stmt(&mut self, keyword: &Token, values: &[Expr], body: &[Stmt]) -> R;
+    fn visit_class_stmt(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+        getters: &[Stmt],
+        setters: &[Stmt],
+        class_methods: &[Stmt],
+    ) -> R;
+    fn visit_continue_stmt(&mut self, keyword: &Token) -> R;
+    fn visit_do_while_stmt(&mut self, body: &Stmt, condition: &Expr) -> R;
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> R;
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &[Stmt]) -> R;
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> R;
+    fn visit_print_stmt(&mut self, expression: &Expr) -> R;
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) -> R;
+    fn visit_switch_stmt(&mut self, keyword: &Token, subject: &Expr, cases: &[Stmt]) -> R;
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> R;
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) -> R;
+}
+
+impl Stmt {
+    /// Calls the `visitor` method for this variant with its fields.
+    pub fn accept<R, V: StmtVisitor<R>>(&self, visitor: &mut V) -> R {
+        match self {
+            Stmt::Block { statements, .. } => visitor.visit_block_stmt(statements),
+            Stmt::Break { keyword, .. } => visitor.visit_break_stmt(keyword),
+            Stmt::Case {
+                keyword,
+                values,
+                body,
+                ..
+            } => visitor.visit_case_stmt(keyword, values, body),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                getters,
+                setters,
+                class_methods,
+                ..
+            } => visitor.visit_class_stmt(
+                name,
+                superclass.as_deref(),
+                methods,
+                getters,
+                setters,
+                class_methods,
+            ),
+            Stmt::Continue { keyword, .. } => visitor.visit_continue_stmt(keyword),
+            Stmt::DoWhile {
+                body, condition, ..
+            } => visitor.visit_do_while_stmt(&**body, &**condition),
+            Stmt::Expression { expression, .. } => visitor.visit_expression_stmt(&**expression),
+            Stmt::Function {
+                name, params, body, ..
+            } => visitor.visit_function_stmt(name, params, body),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => visitor.visit_if_stmt(&**condition, &**then_branch, else_branch.as_deref()),
+            Stmt::Print { expression, .. } => visitor.visit_print_stmt(&**expression),
+            Stmt::Return { keyword, value, .. } => {
+                visitor.visit_return_stmt(keyword, value.as_deref())
+            }
+            Stmt::Switch {
+                keyword,
+                subject,
+                cases,
+                ..
+            } => visitor.visit_switch_stmt(keyword, &**subject, cases),
+            Stmt::Var {
+                name, initializer, ..
+            } => visitor.visit_var_stmt(name, initializer.as_deref()),
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => visitor.visit_while_stmt(&**condition, &**body, increment.as_deref()),
+        }
+    }
+
+    /// The source this node was parsed from, from its first token
+    /// through its last.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block { span, .. }
+            | Stmt::Break { span, .. }
+            | Stmt::Case { span, .. }
+            | Stmt::Class { span, .. }
+            | Stmt::Continue { span, .. }
+            | Stmt::DoWhile { span, .. }
+            | Stmt::Expression { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Switch { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::While { span, .. } => *span,
+        }
+    }
+}
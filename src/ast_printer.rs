@@ -0,0 +1,402 @@
+use crate::ast::{Expr, ExprVisitor, Object, Stmt, StmtVisitor};
+use crate::token::Token;
+use crate::value::Value;
+
+/// Renders the AST in a parenthesized prefix form, as in the book:
+/// `-123 * (45.67)` prints as `(* (- 123) (group 45.67))`. Statements use
+/// the same form, e.g. `(var a 1)` or `(while cond body)`; an expression
+/// statement prints as `(; expr)`, an array literal as `(array 1 2)`,
+/// indexing as `([] a i)`, a map literal as `(map (: a 1) (: b 2))` and
+/// an interpolated string as `(interpolate "x = " x)`. `a?.b.c` prints as
+/// `(optional (. (?. a b) c))`, marking where the optional chain ends.
+pub struct AstPrinter;
+
+impl AstPrinter {
+    /// Prints each statement on its own line.
+    pub fn print(&mut self, statements: &[Stmt]) -> String {
+        statements
+            .iter()
+            .map(|stmt| self.visit_stmt(stmt))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn print_expr(&mut self, expr: &Expr) -> String {
+        self.visit_expr(expr)
+    }
+
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String {
+        self.visit_stmt(stmt)
+    }
+
+    fn parenthesize(&mut self, name: &str, parts: impl IntoIterator<Item = String>) -> String {
+        let mut out = format!("({name}");
+        for part in parts {
+            out.push(' ');
+            out.push_str(&part);
+        }
+        out.push(')');
+        out
+    }
+
+    fn exprs(&mut self, exprs: &[&Expr]) -> Vec<String> {
+        exprs.iter().map(|expr| self.visit_expr(expr)).collect()
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> Vec<String> {
+        stmts.iter().map(|stmt| self.visit_stmt(stmt)).collect()
+    }
+
+    // A function declaration, printed as `(keyword name (params) body...)`.
+    // A getter has no parameter list to print.
+    fn function(
+        &mut self,
+        keyword: &str,
+        name: &Token,
+        params: Option<&[Token]>,
+        body: &[Stmt],
+    ) -> String {
+        let mut parts = vec![name.lexeme.to_string()];
+        if let Some(params) = params {
+            let params: Vec<&str> = params.iter().map(|param| param.lexeme.as_str()).collect();
+            parts.push(format!("({})", params.join(" ")));
+        }
+        parts.extend(self.stmts(body));
+        self.parenthesize(keyword, parts)
+    }
+
+    // Class members other than plain methods, each printed under `keyword`.
+    fn members(&mut self, keyword: &str, members: &[Stmt]) -> Vec<String> {
+        members
+            .iter()
+            .map(|member| match member {
+                Stmt::Function {
+                    name, params, body, ..
+                } => {
+                    let params = (keyword != "get").then_some(params.as_slice());
+                    self.function(keyword, name, params, body)
+                }
+                other => self.visit_stmt(other),
+            })
+            .collect()
+    }
+}
+
+impl ExprVisitor<String> for AstPrinter {
+    fn visit_array_expr(&mut self, elements: &[Expr]) -> String {
+        let parts: Vec<String> = elements.iter().map(|e| self.visit_expr(e)).collect();
+        self.parenthesize("array", parts)
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> String {
+        let value = self.visit_expr(value);
+        self.parenthesize("=", [name.lexeme.to_string(), value])
+    }
+
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        let parts = self.exprs(&[left, right]);
+        self.parenthesize(operator.lexeme.as_str(), parts)
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) -> String {
+        let mut parts = vec![self.visit_expr(callee)];
+        parts.extend(arguments.iter().map(|argument| self.visit_expr(argument)));
+        self.parenthesize("call", parts)
+    }
+
+    fn visit_comma_expr(&mut self, expressions: &[Expr]) -> String {
+        let parts: Vec<String> = expressions.iter().map(|e| self.visit_expr(e)).collect();
+        self.parenthesize(",", parts)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> String {
+        let object = self.visit_expr(object);
+        self.parenthesize(".", [object, name.lexeme.to_string()])
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> String {
+        let parts = self.exprs(&[expression]);
+        self.parenthesize("group", parts)
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> String {
+        let parts = self.exprs(&[object, index]);
+        self.parenthesize("[]", parts)
+    }
+
+    // The string parts are quoted so they stand apart from the expressions.
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> String {
+        let parts: Vec<String> = parts
+            .iter()
+            .map(|part| match part {
+                Expr::Literal {
+                    value: Value::String(s),
+                    ..
+                } => format!("{s:?}"),
+                other => self.visit_expr(other),
+            })
+            .collect();
+        self.parenthesize("interpolate", parts)
+    }
+
+    fn visit_literal_expr(&mut self, value: &Object) -> String {
+        value.to_string()
+    }
+
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> String {
+        let parts = self.exprs(&[left, right]);
+        self.parenthesize(operator.lexeme.as_str(), parts)
+    }
+
+    fn visit_map_expr(&mut self, keys: &[Expr], values: &[Expr]) -> String {
+        let entries: Vec<String> = keys
+            .iter()
+            .zip(values)
+            .map(|(key, value)| {
+                let parts = self.exprs(&[key, value]);
+                self.parenthesize(":", parts)
+            })
+            .collect();
+        self.parenthesize("map", entries)
+    }
+
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) -> String {
+        let parts = self.exprs(&[expression]);
+        self.parenthesize("optional", parts)
+    }
+
+    fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> String {
+        let object = self.visit_expr(object);
+        self.parenthesize("?.", [object, name.lexeme.to_string()])
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> String {
+        let target = self.visit_get_expr(object, name);
+        let value = self.visit_expr(value);
+        self.parenthesize("=", [target, value])
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> String {
+        let target = self.visit_index_expr(object, bracket, index);
+        let value = self.visit_expr(value);
+        self.parenthesize("=", [target, value])
+    }
+
+    fn visit_super_expr(&mut self, _keyword: &Token, method: &Token) -> String {
+        self.parenthesize("super", [method.lexeme.to_string()])
+    }
+
+    fn visit_ternary_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> String {
+        let parts = self.exprs(&[condition, then_branch, else_branch]);
+        self.parenthesize("?:", parts)
+    }
+
+    fn visit_this_expr(&mut self, _keyword: &Token) -> String {
+        "this".to_string()
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> String {
+        let parts = self.exprs(&[right]);
+        self.parenthesize(operator.lexeme.as_str(), parts)
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) -> String {
+        name.lexeme.to_string()
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> String {
+        let parts = self.stmts(statements);
+        self.parenthesize("block", parts)
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) -> String {
+        "(break)".to_string()
+    }
+
+    fn visit_case_stmt(&mut self, keyword: &Token, values: &[Expr], body: &[Stmt]) -> String {
+        let mut parts = Vec::new();
+        if !values.is_empty() {
+            let values: Vec<String> = values.iter().map(|v| self.visit_expr(v)).collect();
+            parts.push(format!("({})", values.join(" ")));
+        }
+        parts.extend(self.stmts(body));
+        self.parenthesize(keyword.lexeme.as_str(), parts)
+    }
+
+    fn visit_class_stmt(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+        getters: &[Stmt],
+        setters: &[Stmt],
+        class_methods: &[Stmt],
+    ) -> String {
+        let mut parts = vec![name.lexeme.to_string()];
+        if let Some(superclass) = superclass {
+            parts.push("<".to_string());
+            parts.push(self.visit_expr(superclass));
+        }
+        parts.extend(self.stmts(methods));
+        parts.extend(self.members("get", getters));
+        parts.extend(self.members("set", setters));
+        parts.extend(self.members("static", class_methods));
+        self.parenthesize("class", parts)
+    }
+
+    fn visit_continue_stmt(&mut self, _keyword: &Token) -> String {
+        "(continue)".to_string()
+    }
+
+    fn visit_do_while_stmt(&mut self, body: &Stmt, condition: &Expr) -> String {
+        let parts = vec![self.visit_stmt(body), self.visit_expr(condition)];
+        self.parenthesize("do", parts)
+    }
+
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> String {
+        let parts = self.exprs(&[expression]);
+        self.parenthesize(";", parts)
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &[Stmt]) -> String {
+        self.function("fun", name, Some(params), body)
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> String {
+        let mut parts = vec![self.visit_expr(condition), self.visit_stmt(then_branch)];
+        if let Some(else_branch) = else_branch {
+            parts.push(self.visit_stmt(else_branch));
+        }
+        self.parenthesize("if", parts)
+    }
+
+    fn visit_print_stmt(&mut self, expression: &Expr) -> String {
+        let parts = self.exprs(&[expression]);
+        self.parenthesize("print", parts)
+    }
+
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> String {
+        let parts = self.exprs(&value.into_iter().collect::<Vec<_>>());
+        self.parenthesize("return", parts)
+    }
+
+    fn visit_switch_stmt(&mut self, _keyword: &Token, subject: &Expr, cases: &[Stmt]) -> String {
+        let mut parts = vec![self.visit_expr(subject)];
+        parts.extend(self.stmts(cases));
+        self.parenthesize("switch", parts)
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> String {
+        let mut parts = vec![name.lexeme.to_string()];
+        if let Some(initializer) = initializer {
+            parts.push(self.visit_expr(initializer));
+        }
+        self.parenthesize("var", parts)
+    }
+
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> String {
+        let mut parts = vec![self.visit_expr(condition), self.visit_stmt(body)];
+        if let Some(increment) = increment {
+            parts.push(self.visit_expr(increment));
+        }
+        self.parenthesize("while", parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+    use crate::span::Span;
+
+    fn token(lexeme: &str) -> Token {
+        Scanner::new(lexeme).scan_tokens().expect("lexeme scans")[0].clone()
+    }
+
+    fn number(n: f64) -> Box<Expr> {
+        Box::new(Expr::Literal {
+            value: Value::Number(n),
+            span: Span::default(),
+        })
+    }
+
+    #[test]
+    fn prints_the_book_example() {
+        let expr = Expr::Binary {
+            left: Box::new(Expr::Unary {
+                operator: token("-"),
+                right: number(123.0),
+                span: Span::default(),
+            }),
+            operator: token("*"),
+            right: Box::new(Expr::Grouping {
+                expression: number(45.67),
+                span: Span::default(),
+            }),
+            span: Span::default(),
+        };
+        assert_eq!(AstPrinter.print_expr(&expr), "(* (- 123) (group 45.67))");
+    }
+
+    #[test]
+    fn prints_assignments_and_setters() {
+        let a = token("a");
+        let assign = Expr::Assign {
+            name: a.clone(),
+            value: number(1.0),
+            span: Span::default(),
+        };
+        assert_eq!(AstPrinter.print_expr(&assign), "(= a 1)");
+
+        let set = Expr::Set {
+            object: Box::new(Expr::Variable {
+                name: a,
+                span: Span::default(),
+            }),
+            name: token("b"),
+            value: number(2.0),
+            span: Span::default(),
+        };
+        assert_eq!(AstPrinter.print_expr(&set), "(= (. a b) 2)");
+    }
+
+    #[test]
+    fn prints_one_statement_per_line() {
+        let statements = [
+            Stmt::Var {
+                name: token("a"),
+                initializer: None,
+                span: Span::default(),
+            },
+            Stmt::Return {
+                keyword: token("return"),
+                value: None,
+                span: Span::default(),
+            },
+        ];
+        assert_eq!(AstPrinter.print(&statements), "(var a)\n(return)");
+    }
+}
@@ -14,49 +14,66 @@ fn main() -> io::Result<()> {
         }
     };
 
-    define_ast(
-        &output_dir,
-        "Expr",
-        &[
-            "Assign   : Token name, Expr value",
-            "Binary   : Expr left, Token operator, Expr right",
-            "Call     : Expr callee, Token paren, List<Expr> arguments",
-            "Get      : Expr object, Token name",
-            "Grouping : Expr expression",
-            "Literal  : Object value",
-            "Logical  : Expr left, Token operator, Expr right",
-            "Set      : Expr object, Token name, Expr value",
-            "Super    : Token keyword, Token method",
-            "This     : Token keyword",
-            "Unary    : Token operator, Expr right",
-            "Variable : Token name",
-        ],
-    )?;
+    let bases: &[(&str, &[&str])] = &[
+        (
+            "Expr",
+            &[
+                "Array    : List<Expr> elements",
+                "Assign   : Token name, Expr value",
+                "Binary   : Expr left, Token operator, Expr right",
+                "Call     : Expr callee, Token paren, List<Expr> arguments",
+                "Comma    : List<Expr> expressions",
+                "Get      : Expr object, Token name",
+                "Grouping : Expr expression",
+                "Index    : Expr object, Token bracket, Expr index",
+                "Interpolation : List<Expr> parts",
+                "Literal  : Object value",
+                "Logical  : Expr left, Token operator, Expr right",
+                "Map      : List<Expr> keys, List<Expr> values",
+                "OptionalChain : Expr expression",
+                "OptionalGet : Expr object, Token name",
+                "Set      : Expr object, Token name, Expr value",
+                "SetIndex : Expr object, Token bracket, Expr index, Expr value",
+                "Super    : Token keyword, Token method",
+                "Ternary  : Expr condition, Expr thenBranch, Expr elseBranch",
+                "This     : Token keyword",
+                "Unary    : Token operator, Expr right",
+                "Variable : Token name",
+            ],
+        ),
+        (
+            "Stmt",
+            &[
+                "Block      : List<Stmt> statements",
+                "Break      : Token keyword",
+                "Case       : Token keyword, List<Expr> values, List<Stmt> body",
+                "Class      : Token name, Expr.Variable? superclass, List<Stmt.Function> methods, List<Stmt.Function> getters, List<Stmt.Function> setters, List<Stmt.Function> classMethods",
+                "Continue   : Token keyword",
+                "DoWhile    : Stmt body, Expr condition",
+                "Expression : Expr expression",
+                "Function   : Token name, List<Token> params, List<Stmt> body",
+                "If         : Expr condition, Stmt thenBranch, Stmt? elseBranch",
+                "Print      : Expr expression",
+                "Return     : Token keyword, Expr? value",
+                "Switch     : Token keyword, Expr subject, List<Stmt.Case> cases",
+                "Var        : Token name, Expr? initializer",
+                "While      : Expr condition, Stmt body, Expr? increment",
+            ],
+        ),
+    ];
 
-    define_ast(
-        &output_dir,
-        "Stmt",
-        &[
-            "Block      : List<Stmt> statements",
-            "Class      : Token name, Expr.Variable superclass, List<Stmt.Function> methods",
-            "Expression : Expr expression",
-            "Function   : Token name, List<Token> params, List<Stmt> body",
-            "If         : Expr condition, Stmt thenBranch, Stmt elseBranch",
-            "Print      : Expr expression",
-            "Return     : Token keyword, Expr value",
-            "Var        : Token name, Expr initializer",
-            "While      : Expr condition, Stmt body",
-        ],
-    )?;
+    define_ast(&output_dir, bases)?;
+    define_arena(&output_dir, bases)?;
 
     Ok(())
 }
 
-fn define_ast(output_dir: &str, base_name: &str, types: &[&str]) -> io::Result<()> {
+// Writes every base type, with its visitor trait and `accept`, into a
+// single `ast.rs`.
+fn define_ast(output_dir: &str, bases: &[(&str, &[&str])]) -> io::Result<()> {
     fs::create_dir_all(output_dir)?;
 
-    let filename = format!("{}.rs", base_name.to_lowercase());
-    let path: PathBuf = Path::new(output_dir).join(filename);
+    let path: PathBuf = Path::new(output_dir).join("ast.rs");
     let mut w = File::create(path)?;
 
     writeln!(
@@ -64,12 +81,24 @@ fn define_ast(output_dir: &str, base_name: &str, types: &[&str]) -> io::Result<(
         "// AUTO-GENERATED by src/bin/generate_ast.rs. Do not hand-edit."
     )?;
     writeln!(w)?;
+    // `accept` spells out `&**field` for boxed fields.
+    writeln!(w, "#![allow(clippy::explicit_auto_deref)]")?;
+    writeln!(w)?;
+    writeln!(w, "use crate::span::Span;")?;
     writeln!(w, "use crate::token::Token;")?;
     writeln!(w, "use crate::value::Value;")?;
     writeln!(w)?;
     writeln!(w, "pub type Object = Value;")?;
     writeln!(w)?;
 
+    for (base_name, types) in bases {
+        define_type(&mut w, base_name, types)?;
+    }
+
+    Ok(())
+}
+
+fn define_type<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Result<()> {
     // Enum
     writeln!(w, "#[derive(Debug, Clone)]")?;
     writeln!(w, "pub enum {base} {{", base = base_name)?;
@@ -81,20 +110,46 @@ fn define_ast(output_dir: &str, base_name: &str, types: &[&str]) -> io::Result<(
         for (fname, fty) in rust_fields {
             writeln!(w, "        {fname}: {fty},")?;
         }
+        // Every node also records the source it was parsed from.
+        writeln!(w, "        span: Span,")?;
         writeln!(w, "    }},")?;
     }
     writeln!(w, "}}")?;
     writeln!(w)?;
 
-    define_visitor(&mut w, base_name, types)?;
-    define_accept(&mut w, base_name, types)?;
+    define_visitor(w, base_name, types)?;
+    define_accept(w, base_name, types)?;
 
     Ok(())
 }
 
 fn define_visitor<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Result<()> {
     let trait_name = format!("{base_name}Visitor");
+    let lower = base_name.to_lowercase();
+    writeln!(
+        w,
+        "/// A pass over `{base_name}` nodes, with one method per variant. Each pass"
+    )?;
+    writeln!(
+        w,
+        "/// (printer, resolver, interpreter, ...) implements this trait for its"
+    )?;
+    writeln!(w, "/// own result type `R`.")?;
     writeln!(w, "pub trait {trait_name}<R> {{")?;
+    writeln!(
+        w,
+        "    /// Dispatches to the method for `{lower}`'s variant."
+    )?;
+    writeln!(
+        w,
+        "    fn visit_{lower}(&mut self, {lower}: &{base_name}) -> R"
+    )?;
+    writeln!(w, "    where")?;
+    writeln!(w, "        Self: Sized,")?;
+    writeln!(w, "    {{")?;
+    writeln!(w, "        {lower}.accept(self)")?;
+    writeln!(w, "    }}")?;
+    writeln!(w)?;
 
     for t in types {
         let (variant, fields) = split_type(t);
@@ -103,7 +158,7 @@ fn define_visitor<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::R
         let rust_fields = parse_fields(&fields);
         let params = rust_fields
             .into_iter()
-            .map(|(n, ty)| format!("{n}: &{}", visitor_ref_type(&ty)))
+            .map(|(n, ty)| format!("{n}: {}", visitor_param_type(&ty)))
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -119,6 +174,10 @@ fn define_accept<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Re
     let trait_name = format!("{base_name}Visitor");
 
     writeln!(w, "impl {base_name} {{")?;
+    writeln!(
+        w,
+        "    /// Calls the `visitor` method for this variant with its fields."
+    )?;
     writeln!(
         w,
         "    pub fn accept<R, V: {trait_name}<R>>(&self, visitor: &mut V) -> R {{"
@@ -145,7 +204,7 @@ fn define_accept<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Re
 
         writeln!(
             w,
-            "            {base}::{variant} {{ {bind_list} }} => visitor.{method}({args}),",
+            "            {base}::{variant} {{ {bind_list}, .. }} => visitor.{method}({args}),",
             base = base_name,
             variant = variant,
             bind_list = bind_list,
@@ -156,11 +215,396 @@ fn define_accept<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Re
 
     writeln!(w, "        }}")?;
     writeln!(w, "    }}")?;
+    writeln!(w)?;
+
+    let patterns = types
+        .iter()
+        .map(|t| format!("{base_name}::{} {{ span, .. }}", split_type(t).0))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    writeln!(
+        w,
+        "    /// The source this node was parsed from, from its first token"
+    )?;
+    writeln!(w, "    /// through its last.")?;
+    writeln!(w, "    pub fn span(&self) -> Span {{")?;
+    writeln!(w, "        match self {{")?;
+    writeln!(w, "            {patterns} => *span,")?;
+    writeln!(w, "        }}")?;
+    writeln!(w, "    }}")?;
+    writeln!(w, "}}")?;
+    writeln!(w)?;
+    Ok(())
+}
+
+// Writes `arena.rs`: a compact, index-based copy of the AST. Each base gets
+// an id type, a `Node` enum whose children are ids, a lowering from the
+// boxed tree, and a visitor trait mirroring the boxed one.
+fn define_arena(output_dir: &str, bases: &[(&str, &[&str])]) -> io::Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    let path: PathBuf = Path::new(output_dir).join("arena.rs");
+    let mut w = File::create(path)?;
+
+    writeln!(
+        w,
+        "// AUTO-GENERATED by src/bin/generate_ast.rs. Do not hand-edit."
+    )?;
+    writeln!(w)?;
+    writeln!(w, "{}", ARENA_PRELUDE.trim_start())?;
+
+    for (base_name, types) in bases {
+        define_arena_type(&mut w, base_name, types)?;
+    }
+
+    define_arena_impl(&mut w, bases)?;
+    Ok(())
+}
+
+// The parts of `arena.rs` that don't depend on the node types.
+const ARENA_PRELUDE: &str = r#"
+//! A compact, index-based copy of the AST. All nodes of a program live in
+//! one [`Ast`]: children are small ids into its vectors and tokens and
+//! literal values are stored out of line, so a node is a few words and a
+//! walk over the tree touches little memory. [`Ast::lower`] copies a parsed
+//! program into an arena, and the `*NodeVisitor` traits walk it the way
+//! the visitors in [`crate::ast`] walk the boxed tree.
+
+// Visitor methods take the arena and then one parameter per field.
+#![allow(clippy::too_many_arguments)]
+
+use crate::ast::{Expr, Object, Stmt};
+use crate::span::Span;
+use crate::token::Token;
+
+/// Where a token is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenId(u32);
+
+/// Where a literal value is in an [`Ast`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ValueId(u32);
+
+/// A run of consecutive entries in one of an [`Ast`]'s vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListRange {
+    start: u32,
+    len: u32,
+}
+
+impl ListRange {
+    fn slice<T>(self, items: &[T]) -> &[T] {
+        let start = self.start as usize;
+        &items[start..start + self.len as usize]
+    }
+}
+
+pub type TokenList = ListRange;
+pub type ExprList = ListRange;
+pub type StmtList = ListRange;
+
+fn index(len: usize) -> u32 {
+    u32::try_from(len).expect("an AST has fewer than 2^32 nodes")
+}
+
+/// Every node, token and value of a lowered program.
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    exprs: Vec<ExprNode>,
+    stmts: Vec<StmtNode>,
+    // Kept apart from the nodes, which walks read far more often.
+    expr_spans: Vec<Span>,
+    stmt_spans: Vec<Span>,
+    tokens: Vec<Token>,
+    values: Vec<Object>,
+    expr_lists: Vec<ExprId>,
+    stmt_lists: Vec<StmtId>,
+}
+
+impl Ast {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies `statements` into the arena and returns their ids.
+    pub fn lower(&mut self, statements: &[Stmt]) -> StmtList {
+        self.lower_stmts(statements)
+    }
+
+    pub fn expr(&self, id: ExprId) -> &ExprNode {
+        &self.exprs[id.0 as usize]
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &StmtNode {
+        &self.stmts[id.0 as usize]
+    }
+
+    /// The source node `id` was parsed from, see [`Expr::span`].
+    pub fn expr_span(&self, id: ExprId) -> Span {
+        self.expr_spans[id.0 as usize]
+    }
+
+    /// The source node `id` was parsed from, see [`Stmt::span`].
+    pub fn stmt_span(&self, id: StmtId) -> Span {
+        self.stmt_spans[id.0 as usize]
+    }
+
+    pub fn token(&self, id: TokenId) -> &Token {
+        &self.tokens[id.0 as usize]
+    }
+
+    pub fn value(&self, id: ValueId) -> &Object {
+        &self.values[id.0 as usize]
+    }
+
+    pub fn token_list(&self, list: TokenList) -> &[Token] {
+        list.slice(&self.tokens)
+    }
+
+    pub fn expr_list(&self, list: ExprList) -> &[ExprId] {
+        list.slice(&self.expr_lists)
+    }
+
+    pub fn stmt_list(&self, list: StmtList) -> &[StmtId] {
+        list.slice(&self.stmt_lists)
+    }
+
+    fn push_token(&mut self, token: &Token) -> TokenId {
+        self.tokens.push(token.clone());
+        TokenId(index(self.tokens.len() - 1))
+    }
+
+    fn push_tokens(&mut self, tokens: &[Token]) -> TokenList {
+        let start = index(self.tokens.len());
+        self.tokens.extend_from_slice(tokens);
+        ListRange {
+            start,
+            len: index(tokens.len()),
+        }
+    }
+
+    fn push_value(&mut self, value: &Object) -> ValueId {
+        self.values.push(value.clone());
+        ValueId(index(self.values.len() - 1))
+    }
+
+    fn lower_exprs(&mut self, exprs: &[Expr]) -> ExprList {
+        let ids: Vec<ExprId> = exprs.iter().map(|expr| self.lower_expr(expr)).collect();
+        let start = index(self.expr_lists.len());
+        self.expr_lists.extend(ids);
+        ListRange {
+            start,
+            len: index(exprs.len()),
+        }
+    }
+
+    fn lower_stmts(&mut self, stmts: &[Stmt]) -> StmtList {
+        let ids: Vec<StmtId> = stmts.iter().map(|stmt| self.lower_stmt(stmt)).collect();
+        let start = index(self.stmt_lists.len());
+        self.stmt_lists.extend(ids);
+        ListRange {
+            start,
+            len: index(stmts.len()),
+        }
+    }
+}
+"#;
+
+fn define_arena_type<W: Write>(w: &mut W, base_name: &str, types: &[&str]) -> io::Result<()> {
+    let lower = base_name.to_lowercase();
+
+    writeln!(w, "/// Where a `{base_name}` node is in an [`Ast`].")?;
+    writeln!(w, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]")?;
+    writeln!(w, "pub struct {base_name}Id(u32);")?;
+    writeln!(w)?;
+
+    writeln!(
+        w,
+        "/// A `{base_name}` stored in an [`Ast`], with ids in place of its children."
+    )?;
+    writeln!(w, "#[derive(Debug, Clone, Copy)]")?;
+    writeln!(w, "pub enum {base_name}Node {{")?;
+    for t in types {
+        let (variant, fields) = split_type(t);
+        writeln!(w, "    {variant} {{")?;
+        for (fname, fty) in parse_fields(&fields) {
+            writeln!(w, "        {fname}: {},", arena_type(&fty))?;
+        }
+        writeln!(w, "    }},")?;
+    }
+    writeln!(w, "}}")?;
+    writeln!(w)?;
+
+    writeln!(
+        w,
+        "/// Walks `{base_name}` nodes in an [`Ast`], like [`crate::ast::{base_name}Visitor`]"
+    )?;
+    writeln!(
+        w,
+        "/// does for the boxed tree. Children are passed as ids to visit through"
+    )?;
+    writeln!(w, "/// `ast`; tokens and values are passed resolved.")?;
+    writeln!(w, "pub trait {base_name}NodeVisitor<R> {{")?;
+    writeln!(
+        w,
+        "    /// Dispatches to the method for `{lower}`'s variant."
+    )?;
+    writeln!(
+        w,
+        "    fn visit_{lower}(&mut self, ast: &Ast, {lower}: {base_name}Id) -> R"
+    )?;
+    writeln!(w, "    where")?;
+    writeln!(w, "        Self: Sized,")?;
+    writeln!(w, "    {{")?;
+    writeln!(w, "        ast.accept_{lower}({lower}, self)")?;
+    writeln!(w, "    }}")?;
+    writeln!(w)?;
+    for t in types {
+        let (variant, fields) = split_type(t);
+        let method = format!("visit_{}_{lower}", to_snake(&variant));
+        let params = parse_fields(&fields)
+            .into_iter()
+            .map(|(n, ty)| format!(", {n}: {}", arena_param_type(&ty)))
+            .collect::<String>();
+        writeln!(w, "    fn {method}(&mut self, ast: &Ast{params}) -> R;")?;
+    }
     writeln!(w, "}}")?;
     writeln!(w)?;
     Ok(())
 }
 
+fn define_arena_impl<W: Write>(w: &mut W, bases: &[(&str, &[&str])]) -> io::Result<()> {
+    writeln!(w, "impl Ast {{")?;
+    for (i, (base_name, types)) in bases.iter().enumerate() {
+        let lower = base_name.to_lowercase();
+        if i > 0 {
+            writeln!(w)?;
+        }
+
+        writeln!(
+            w,
+            "    /// Copies `{lower}` and everything under it into the arena."
+        )?;
+        writeln!(
+            w,
+            "    pub fn lower_{lower}(&mut self, {lower}: &{base_name}) -> {base_name}Id {{"
+        )?;
+        writeln!(w, "        let node = match {lower} {{")?;
+        for t in *types {
+            let (variant, fields) = split_type(t);
+            let rust_fields = parse_fields(&fields);
+            let bind_list = rust_fields
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let inits = rust_fields
+                .iter()
+                .map(|(n, ty)| format!("{n}: {}", lower_expr(n, ty)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                w,
+                "            {base_name}::{variant} {{ {bind_list}, .. }} => {base_name}Node::{variant} {{ {inits} }},"
+            )?;
+        }
+        writeln!(w, "        }};")?;
+        writeln!(w, "        self.{lower}s.push(node);")?;
+        writeln!(w, "        self.{lower}_spans.push({lower}.span());")?;
+        writeln!(w, "        {base_name}Id(index(self.{lower}s.len() - 1))")?;
+        writeln!(w, "    }}")?;
+        writeln!(w)?;
+
+        writeln!(
+            w,
+            "    /// Calls the `visitor` method for the variant of node `id`."
+        )?;
+        writeln!(
+            w,
+            "    pub fn accept_{lower}<R, V: {base_name}NodeVisitor<R>>(&self, id: {base_name}Id, visitor: &mut V) -> R {{"
+        )?;
+        writeln!(w, "        match *self.{lower}(id) {{")?;
+        for t in *types {
+            let (variant, fields) = split_type(t);
+            let method = format!("visit_{}_{lower}", to_snake(&variant));
+            let rust_fields = parse_fields(&fields);
+            let bind_list = rust_fields
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let args = rust_fields
+                .iter()
+                .map(|(n, ty)| format!(", {}", arena_arg(n, ty)))
+                .collect::<String>();
+            writeln!(
+                w,
+                "            {base_name}Node::{variant} {{ {bind_list} }} => visitor.{method}(self{args}),"
+            )?;
+        }
+        writeln!(w, "        }}")?;
+        writeln!(w, "    }}")?;
+    }
+    writeln!(w, "}}")?;
+    Ok(())
+}
+
+// The arena field type for a boxed-tree field type from `map_type`.
+fn arena_type(ty: &str) -> String {
+    if let Some(inner) = ty.strip_prefix("Option<").and_then(|s| s.strip_suffix('>')) {
+        return format!("Option<{}>", arena_type(inner));
+    }
+    match ty {
+        "Box<Expr>" => "ExprId".to_string(),
+        "Box<Stmt>" => "StmtId".to_string(),
+        "Vec<Expr>" => "ExprList".to_string(),
+        "Vec<Stmt>" => "StmtList".to_string(),
+        "Vec<Token>" => "TokenList".to_string(),
+        "Token" => "TokenId".to_string(),
+        "Object" => "ValueId".to_string(),
+        other => panic!("no arena representation for {other}"),
+    }
+}
+
+// Arena visitors get ids for children but resolved tokens and values.
+fn arena_param_type(ty: &str) -> String {
+    match arena_type(ty).as_str() {
+        "ExprList" => "&[ExprId]".to_string(),
+        "StmtList" => "&[StmtId]".to_string(),
+        "TokenList" => "&[Token]".to_string(),
+        "TokenId" => "&Token".to_string(),
+        "ValueId" => "&Object".to_string(),
+        id => id.to_string(),
+    }
+}
+
+fn arena_arg(name: &str, ty: &str) -> String {
+    match arena_type(ty).as_str() {
+        "ExprList" => format!("self.expr_list({name})"),
+        "StmtList" => format!("self.stmt_list({name})"),
+        "TokenList" => format!("self.token_list({name})"),
+        "TokenId" => format!("self.token({name})"),
+        "ValueId" => format!("self.value({name})"),
+        _ => name.to_string(),
+    }
+}
+
+// How `lower_*` copies a boxed-tree field into the arena.
+fn lower_expr(name: &str, ty: &str) -> String {
+    match ty {
+        "Box<Expr>" => format!("self.lower_expr({name})"),
+        "Box<Stmt>" => format!("self.lower_stmt({name})"),
+        "Option<Box<Expr>>" => format!("{name}.as_deref().map(|e| self.lower_expr(e))"),
+        "Option<Box<Stmt>>" => format!("{name}.as_deref().map(|s| self.lower_stmt(s))"),
+        "Vec<Expr>" => format!("self.lower_exprs({name})"),
+        "Vec<Stmt>" => format!("self.lower_stmts({name})"),
+        "Vec<Token>" => format!("self.push_tokens({name})"),
+        "Token" => format!("self.push_token({name})"),
+        "Object" => format!("self.push_value({name})"),
+        other => panic!("no arena representation for {other}"),
+    }
+}
+
 fn parse_fields(field_list: &str) -> Vec<(String, String)> {
     if field_list.trim().is_empty() {
         return vec![];
@@ -205,6 +649,11 @@ fn parse_fields(field_list: &str) -> Vec<(String, String)> {
 // }
 
 fn map_type(java_ty: &str) -> String {
+    // A trailing `?` marks an optional field, e.g. `Expr? initializer`
+    if let Some(inner) = java_ty.strip_suffix('?') {
+        return format!("Option<{}>", map_type(inner));
+    }
+
     // Handle List<...> first
     if let Some(inner) = java_ty
         .strip_prefix("List<")
@@ -231,18 +680,28 @@ fn map_type(java_ty: &str) -> String {
     }
 }
 
-// Visitor wants &Expr, not &Box<Expr>
-fn visitor_ref_type(ty: &str) -> String {
-    if let Some(inner) = ty.strip_prefix("Box<").and_then(|s| s.strip_suffix('>')) {
-        inner.to_string()
+// Visitor wants &Expr, not &Box<Expr>, &[Expr] rather than &Vec<Expr>,
+// and Option<&Expr> rather than &Option<Box<Expr>>
+fn visitor_param_type(ty: &str) -> String {
+    if let Some(inner) = ty.strip_prefix("Option<").and_then(|s| s.strip_suffix('>')) {
+        let inner = visitor_param_type(inner);
+        format!("Option<{inner}>")
+    } else if let Some(inner) = ty.strip_prefix("Box<").and_then(|s| s.strip_suffix('>')) {
+        format!("&{inner}")
+    } else if let Some(inner) = ty.strip_prefix("Vec<").and_then(|s| s.strip_suffix('>')) {
+        format!("&[{inner}]")
     } else {
-        ty.to_string()
+        format!("&{ty}")
     }
 }
 
 // When passing args to visitor, boxed fields should pass &Expr, not &Box<Expr>
 fn arg_expr(name: &str, ty: &str) -> String {
-    if ty.starts_with("Box<") {
+    if ty.starts_with("Option<Box<") {
+        format!("{name}.as_deref()")
+    } else if ty.starts_with("Option<") {
+        format!("{name}.as_ref()")
+    } else if ty.starts_with("Box<") {
         format!("&**{name}")
     } else {
         name.to_string()
@@ -276,14 +735,16 @@ mod tests {
     use super::*;
     use std::fs;
 
-    fn generate_into_temp() -> (String, String) {
+    fn generate_into_temp() -> String {
         let dir = tempfile::tempdir().expect("tempdir");
         let out = dir.path().to_string_lossy().to_string();
 
         define_ast(
             &out,
-            "Expr",
             &[
+                (
+                    "Expr",
+                    &[
                 "Assign   : Token name, Expr value",
                 "Binary   : Expr left, Token operator, Expr right",
                 "Call     : Expr callee, Token paren, List<Expr> arguments",
@@ -296,14 +757,11 @@ mod tests {
                 "This     : Token keyword",
                 "Unary    : Token operator, Expr right",
                 "Variable : Token name",
-            ],
-        )
-        .expect("define_ast Expr");
-
-        define_ast(
-            &out,
-            "Stmt",
-            &[
+                    ],
+                ),
+                (
+                    "Stmt",
+                    &[
                 "Block      : List<Stmt> statements",
                 "Class      : Token name, Expr.Variable superclass, List<Stmt.Function> methods",
                 "Expression : Expr expression",
@@ -311,24 +769,25 @@ mod tests {
                 "If         : Expr condition, Stmt thenBranch, Stmt elseBranch",
                 "Print      : Expr expression",
                 "Return     : Token keyword, Expr value",
-                "Var        : Token name, Expr initializer",
+                "Var        : Token name, Expr? initializer",
                 "While      : Expr condition, Stmt body",
+                    ],
+                ),
             ],
         )
-        .expect("define_ast Stmt");
+        .expect("define_ast");
 
-        let expr = fs::read_to_string(dir.path().join("expr.rs")).expect("read expr.rs");
-        let stmt = fs::read_to_string(dir.path().join("stmt.rs")).expect("read stmt.rs");
+        let ast = fs::read_to_string(dir.path().join("ast.rs")).expect("read ast.rs");
 
         // Keep tempdir alive until reads are done
         drop(dir);
 
-        (expr, stmt)
+        ast
     }
 
     #[test]
     fn expr_assign_has_name_and_value() {
-        let (expr, _) = generate_into_temp();
+        let expr = generate_into_temp();
 
         assert!(expr.contains("pub enum Expr"), "missing Expr enum");
         assert!(
@@ -341,7 +800,7 @@ mod tests {
 
     #[test]
     fn expr_call_has_arguments_vec_expr() {
-        let (expr, _) = generate_into_temp();
+        let expr = generate_into_temp();
 
         assert!(
             expr.contains("Call {")
@@ -354,7 +813,7 @@ mod tests {
 
     #[test]
     fn stmt_class_methods_are_vec_stmt_not_list() {
-        let (_, stmt) = generate_into_temp();
+        let stmt = generate_into_temp();
 
         // This catches the earlier "List<Stmt," bug.
         assert!(
@@ -369,24 +828,116 @@ mod tests {
         );
     }
 
+    #[test]
+    fn optional_fields_become_options() {
+        let stmt = generate_into_temp();
+
+        assert!(
+            stmt.contains("initializer: Option<Box<Expr>>"),
+            "Var.initializer should be optional:\n{stmt}"
+        );
+        assert!(
+            stmt.contains(
+                "fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> R;"
+            ),
+            "optional fields should reach visitors as Option<&T>:\n{stmt}"
+        );
+        assert!(
+            stmt.contains("initializer.as_deref()"),
+            "accept() should pass optional boxes with as_deref():\n{stmt}"
+        );
+    }
+
     #[test]
     fn visitor_signatures_use_refs_and_deref_boxes() {
-        let (expr, stmt) = generate_into_temp();
+        let ast = generate_into_temp();
 
         // Because we generate Box<Expr> fields but visitor wants &Expr
         assert!(
-            expr.contains(
+            ast.contains(
                 "fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;"
-            ) || expr.contains(
+            ) || ast.contains(
                 "fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> R;"
             ),
-            "Binary visitor signature should take &Expr and &Token:\n{expr}"
+            "Binary visitor signature should take &Expr and &Token:\n{ast}"
         );
 
         // And accept() should deref boxes when calling visitor: &**field
         assert!(
-            stmt.contains("&**superclass") || stmt.contains("&**condition"),
-            "accept() should deref Box fields when calling visitor:\n{stmt}"
+            ast.contains("&**superclass") || ast.contains("&**condition"),
+            "accept() should deref Box fields when calling visitor:\n{ast}"
+        );
+    }
+
+    #[test]
+    fn visitors_can_dispatch_on_the_base_type() {
+        let ast = generate_into_temp();
+
+        assert!(
+            ast.contains("fn visit_expr(&mut self, expr: &Expr) -> R")
+                && ast.contains("fn visit_stmt(&mut self, stmt: &Stmt) -> R"),
+            "visitor traits should provide a dispatching method:\n{ast}"
+        );
+        assert!(
+            ast.contains("expr.accept(self)"),
+            "the dispatching method should go through accept():\n{ast}"
+        );
+    }
+
+    #[test]
+    fn every_node_records_its_span() {
+        let ast = generate_into_temp();
+
+        assert!(
+            ast.contains("        span: Span,\n    },")
+                && ast.contains("pub fn span(&self) -> Span"),
+            "nodes should carry a span with an accessor:\n{ast}"
+        );
+        assert!(
+            ast.contains("Expr::Variable { name, .. } => visitor.visit_variable_expr(name),"),
+            "accept() should not pass spans to visitors:\n{ast}"
+        );
+    }
+
+    #[test]
+    fn arena_nodes_refer_to_children_by_id() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().to_string_lossy().to_string();
+        define_arena(
+            &out,
+            &[
+                (
+                    "Expr",
+                    &[
+                        "Call     : Expr callee, Token paren, List<Expr> arguments",
+                        "Literal  : Object value",
+                    ],
+                ),
+                ("Stmt", &["Return : Token keyword, Expr? value"]),
+            ],
+        )
+        .expect("define_arena");
+        let arena = fs::read_to_string(dir.path().join("arena.rs")).expect("read arena.rs");
+
+        assert!(
+            arena.contains("callee: ExprId,")
+                && arena.contains("paren: TokenId,")
+                && arena.contains("arguments: ExprList,")
+                && arena.contains("value: ValueId,")
+                && arena.contains("value: Option<ExprId>,"),
+            "arena nodes should hold ids:\n{arena}"
+        );
+        assert!(
+            arena.contains(
+                "fn visit_call_expr(&mut self, ast: &Ast, callee: ExprId, paren: &Token, arguments: &[ExprId]) -> R;"
+            ),
+            "arena visitors should get ids for children and resolved tokens:\n{arena}"
+        );
+        assert!(
+            arena.contains("value.as_deref().map(|e| self.lower_expr(e))")
+                && arena.contains("visitor.visit_call_expr(self, callee, self.token(paren), self.expr_list(arguments))")
+                && arena.contains("self.expr_spans.push(expr.span());"),
+            "lowering and accept should convert between representations:\n{arena}"
         );
     }
 }
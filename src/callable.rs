@@ -0,0 +1,40 @@
+use crate::function::LoxFunction;
+use crate::heap::Tracer;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::native::NativeFunction;
+use crate::value::Value;
+use std::fmt;
+use std::rc::Rc;
+
+/// Something a Lox program can call. Displaying it gives what `print`
+/// shows for it.
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    /// How many arguments a call must pass. The interpreter checks this
+    /// before calling [`LoxCallable::call`].
+    fn arity(&self) -> usize;
+
+    /// Takes the callable's own `Rc` so that a class can keep hold of
+    /// itself in the instances it makes.
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+
+    /// Gives `tracer` the scopes and values the callable keeps hold of, so
+    /// the interpreter's [`crate::heap::Heap`] knows they are alive. A
+    /// callable that holds none needn't implement this.
+    fn trace(&self, _tracer: &mut Tracer) {}
+
+    /// The callable as a function declared in Lox, if it is one, which a
+    /// call in tail position can run in place of its caller.
+    fn as_function(&self) -> Option<&LoxFunction> {
+        None
+    }
+
+    /// The callable as a function written in Rust, if it is one, whose
+    /// errors are reported at the call being made to it.
+    fn as_native(&self) -> Option<&NativeFunction> {
+        None
+    }
+}
@@ -0,0 +1,166 @@
+use crate::callable::LoxCallable;
+use crate::function::LoxFunction;
+use crate::heap::Tracer;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::symbol::Symbol;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// Functions of a class, by name.
+pub type Methods = HashMap<Symbol, LoxFunction>;
+
+/// A class declared in Lox. Calling it makes an instance and runs its
+/// `init` method, if it has one, with the call's arguments.
+///
+/// Members the class doesn't declare itself are looked up in its
+/// superclass, and so on up the chain.
+#[derive(Debug)]
+pub struct LoxClass {
+    name: Symbol,
+    superclass: Option<Rc<LoxClass>>,
+    methods: Methods,
+    getters: Methods,
+    setters: Methods,
+    // Methods called on the class itself, with `this` bound to the class.
+    class_methods: Methods,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: Symbol,
+        superclass: Option<Rc<LoxClass>>,
+        methods: Methods,
+        getters: Methods,
+        setters: Methods,
+        class_methods: Methods,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+            getters,
+            setters,
+            class_methods,
+        }
+    }
+
+    pub fn find_method(&self, name: Symbol) -> Option<&LoxFunction> {
+        self.find(name, |class| &class.methods)
+    }
+
+    pub fn find_getter(&self, name: Symbol) -> Option<&LoxFunction> {
+        self.find(name, |class| &class.getters)
+    }
+
+    pub fn find_setter(&self, name: Symbol) -> Option<&LoxFunction> {
+        self.find(name, |class| &class.setters)
+    }
+
+    pub fn find_class_method(&self, name: Symbol) -> Option<&LoxFunction> {
+        self.find(name, |class| &class.class_methods)
+    }
+
+    // Looks `name` up in one kind of member, from this class upwards.
+    fn find(&self, name: Symbol, members: fn(&LoxClass) -> &Methods) -> Option<&LoxFunction> {
+        members(self).get(&name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find(name, members))
+        })
+    }
+}
+
+impl LoxCallable for LoxClass {
+    fn arity(&self) -> usize {
+        self.find_method(Symbol::INIT)
+            .map_or(0, |init| init.arity())
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let instance = LoxInstance::new(Rc::clone(&self));
+        let instance = Rc::new(RefCell::new(instance));
+        interpreter.heap().track_instance(&instance);
+        let instance = Value::Instance(instance);
+        if let Some(init) = self.find_method(Symbol::INIT) {
+            Rc::new(init.bind(instance.clone())).call(interpreter, arguments)?;
+        }
+        Ok(instance)
+    }
+
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(superclass) = &self.superclass {
+            tracer.class(superclass);
+        }
+        let members = [
+            &self.methods,
+            &self.getters,
+            &self.setters,
+            &self.class_methods,
+        ];
+        for function in members.into_iter().flat_map(HashMap::values) {
+            function.trace(tracer);
+        }
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// An instance of a class, holding whatever fields the program has set on
+/// it. Fields need no declaring: setting one creates it.
+#[derive(Debug)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<Symbol, Value>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+
+    /// The value of the field `name`, if it has been set.
+    pub fn field(&self, name: Symbol) -> Option<Value> {
+        self.fields.get(&name).cloned()
+    }
+
+    /// Sets the field `name`, creating it if it is new.
+    pub fn set_field(&mut self, name: Symbol, value: Value) {
+        self.fields.insert(name, value);
+    }
+
+    // Removes every field, for the heap to break a cycle.
+    pub(crate) fn take_fields(&mut self) -> HashMap<Symbol, Value> {
+        std::mem::take(&mut self.fields)
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        tracer.class(&self.class);
+        for value in self.fields.values() {
+            tracer.value(value);
+        }
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::parser::ParseError;
+use crate::resolver::ResolveError;
+use crate::scanner::ScanError;
+use crate::span::Span;
+use std::error::Error;
+use std::fmt;
+
+/// Everything found wrong with a program by the first stage that found
+/// anything. Later stages don't run on a program with scan errors, so
+/// only one kind of error is ever reported at a time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostics {
+    Scan(Vec<ScanError>),
+    Parse(Vec<ParseError>),
+    Resolve(Vec<ResolveError>),
+}
+
+/// One problem in a program: where it is and the error describing it.
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostic<'a> {
+    pub span: Span,
+    pub error: &'a (dyn Error + 'static),
+}
+
+impl Diagnostics {
+    /// The problems in source order, as far as each stage reports them in
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = Diagnostic<'_>> {
+        let (scan, parse, resolve) = match self {
+            Diagnostics::Scan(errors) => (errors.as_slice(), &[][..], &[][..]),
+            Diagnostics::Parse(errors) => (&[][..], errors.as_slice(), &[][..]),
+            Diagnostics::Resolve(errors) => (&[][..], &[][..], errors.as_slice()),
+        };
+        let scan = scan.iter().map(|error| Diagnostic {
+            span: error.span,
+            error,
+        });
+        let parse = parse.iter().map(|error| Diagnostic {
+            span: error.span,
+            error,
+        });
+        let resolve = resolve.iter().map(|error| Diagnostic {
+            span: error.span,
+            error,
+        });
+        scan.chain(parse).chain(resolve)
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Diagnostics::Scan(errors) => errors.len(),
+            Diagnostics::Parse(errors) => errors.len(),
+            Diagnostics::Resolve(errors) => errors.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<Vec<ScanError>> for Diagnostics {
+    fn from(errors: Vec<ScanError>) -> Self {
+        Diagnostics::Scan(errors)
+    }
+}
+
+impl From<Vec<ParseError>> for Diagnostics {
+    fn from(errors: Vec<ParseError>) -> Self {
+        Diagnostics::Parse(errors)
+    }
+}
+
+impl From<Vec<ResolveError>> for Diagnostics {
+    fn from(errors: Vec<ResolveError>) -> Self {
+        Diagnostics::Resolve(errors)
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.error, f)
+    }
+}
+
+/// One error per line, in the order of [`Diagnostics::iter`].
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{diagnostic}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_each_error_with_its_span() {
+        let diagnostics = crate::check("print @;\nvar 1;").unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert_eq!(diagnostic.span, Span::new(6, 7));
+        assert_eq!(
+            diagnostic.to_string(),
+            "[line 1] Error: Unexpected character '@'."
+        );
+    }
+
+    #[test]
+    fn displays_one_error_per_line() {
+        let diagnostics = crate::check("print 1\nprint 2;\nvar 3;").unwrap_err();
+        assert!(matches!(diagnostics, Diagnostics::Parse(_)));
+        assert_eq!(
+            diagnostics.to_string(),
+            "[line 2] Error at 'print': Expect ';' after print statement.\n\
+             [line 3] Error at '3': Expect variable name."
+        );
+    }
+}
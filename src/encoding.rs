@@ -0,0 +1,119 @@
+use std::fmt;
+use std::io::{self, BufRead};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Why a source file could not be decoded as UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingError {
+    /// The file looks like UTF-16, either from its byte order mark or from
+    /// an ASCII character padded with a zero byte at the very start.
+    Utf16 { big_endian: bool },
+    /// The file is not valid UTF-8; `offset` is the first bad byte.
+    InvalidUtf8 { offset: usize },
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::Utf16 { big_endian } => {
+                let order = if *big_endian { "big" } else { "little" };
+                write!(
+                    f,
+                    "file is UTF-16 ({order}-endian) encoded; save it as UTF-8"
+                )
+            }
+            EncodingError::InvalidUtf8 { offset } => {
+                write!(
+                    f,
+                    "file is not valid UTF-8 (invalid byte at offset {offset})"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// Decodes the contents of a source file, dropping a leading UTF-8 byte
+/// order mark so it does not reach the scanner as an unexpected character.
+pub fn decode_source(mut bytes: Vec<u8>) -> Result<String, EncodingError> {
+    if let Some(big_endian) = utf16_order(&bytes) {
+        return Err(EncodingError::Utf16 { big_endian });
+    }
+    if bytes.starts_with(UTF8_BOM) {
+        bytes.drain(..UTF8_BOM.len());
+    }
+    String::from_utf8(bytes).map_err(|error| EncodingError::InvalidUtf8 {
+        offset: error.utf8_error().valid_up_to(),
+    })
+}
+
+/// Checks the start of a source being read as [`decode_source`] checks a
+/// whole file, failing with an `InvalidData` error if it is UTF-16 and
+/// skipping a UTF-8 byte order mark.
+pub fn check_source_start(reader: &mut impl BufRead) -> io::Result<()> {
+    let start = reader.fill_buf()?;
+    if let Some(big_endian) = utf16_order(start) {
+        let error = EncodingError::Utf16 { big_endian };
+        return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+    }
+    if start.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+    Ok(())
+}
+
+fn utf16_order(bytes: &[u8]) -> Option<bool> {
+    match bytes {
+        [0xFF, 0xFE, ..] => Some(false),
+        [0xFE, 0xFF, ..] => Some(true),
+        [b, 0, ..] if *b != 0 && b.is_ascii() => Some(false),
+        [0, b, ..] if *b != 0 && b.is_ascii() => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_a_utf8_bom() {
+        let source = decode_source(b"\xEF\xBB\xBFprint 1;".to_vec()).unwrap();
+        assert_eq!(source, "print 1;");
+    }
+
+    #[test]
+    fn detects_utf16() {
+        assert_eq!(
+            decode_source(b"\xFF\xFEp\0r\0".to_vec()),
+            Err(EncodingError::Utf16 { big_endian: false })
+        );
+        assert_eq!(
+            decode_source(b"\0p\0r".to_vec()),
+            Err(EncodingError::Utf16 { big_endian: true })
+        );
+    }
+
+    #[test]
+    fn checks_the_start_of_a_source_being_read() {
+        let mut reader: &[u8] = b"\xEF\xBB\xBFprint 1;";
+        check_source_start(&mut reader).unwrap();
+        assert_eq!(reader, b"print 1;");
+
+        let mut reader: &[u8] = b"\xFF\xFEp\0r\0";
+        let error = check_source_start(&mut reader).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reports_offset_of_invalid_utf8() {
+        let error = decode_source(b"print \"caf\xE9\";".to_vec()).unwrap_err();
+        assert_eq!(error, EncodingError::InvalidUtf8 { offset: 10 });
+        assert_eq!(
+            error.to_string(),
+            "file is not valid UTF-8 (invalid byte at offset 10)"
+        );
+    }
+}
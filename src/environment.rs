@@ -0,0 +1,227 @@
+use crate::heap::Tracer;
+use crate::interpreter::RuntimeError;
+use crate::symbol::Symbol;
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// The variables of one scope, and the scope that encloses it. Lookups
+/// that miss here continue outwards, ending at the global scope, which
+/// has no enclosing scope.
+///
+/// The global scope holds its variables by name, since a program can
+/// refer to a global it hasn't declared yet. A local scope holds its
+/// variables in the slots the resolver numbered them with, see
+/// [`crate::resolver::Slot`].
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<Symbol, Value>,
+    slots: Vec<Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    /// A global scope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A scope nested inside `enclosing`.
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            slots: Vec::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// Declares `name` in this scope. Declaring a name again replaces its
+    /// value, which is what lets a script redefine a global.
+    pub fn define(&mut self, name: Symbol, value: Value) {
+        self.values.insert(name, value);
+    }
+
+    /// Declares the local variable in `slot`. A new variable's slot is the
+    /// next one, as variables are numbered in the order they're declared;
+    /// declaring a variable again replaces its value.
+    pub fn define_slot(&mut self, slot: usize, value: Value) {
+        debug_assert!(slot <= self.slots.len(), "slots are declared in order");
+        if slot == self.slots.len() {
+            self.slots.push(value);
+        } else {
+            self.slots[slot] = value;
+        }
+    }
+
+    /// The value of the innermost variable called `name`.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        self.lookup(name.lexeme.symbol())
+            .ok_or_else(|| undefined(name))
+    }
+
+    /// The value of the innermost variable called `name`, if there is one.
+    pub fn lookup(&self, name: Symbol) -> Option<Value> {
+        if let Some(value) = self.values.get(&name) {
+            return Some(value.clone());
+        }
+        self.enclosing
+            .as_ref()
+            .and_then(|enclosing| enclosing.borrow().lookup(name))
+    }
+
+    /// The value in `slot` of the scope `distance` scopes out from this
+    /// one, where the resolver found the variable declared.
+    pub fn get_at(&self, distance: usize, slot: usize) -> Value {
+        match distance {
+            0 => self.slots[slot].clone(),
+            _ => self.ancestor(distance).borrow().slots[slot].clone(),
+        }
+    }
+
+    /// Sets `slot` in the scope `distance` scopes out from this one.
+    pub fn assign_at(&mut self, distance: usize, slot: usize, value: Value) {
+        match distance {
+            0 => self.slots[slot] = value,
+            _ => self.ancestor(distance).borrow_mut().slots[slot] = value,
+        }
+    }
+
+    pub(crate) fn trace(&self, tracer: &mut Tracer) {
+        for value in self.values.values() {
+            tracer.value(value);
+        }
+        for value in &self.slots {
+            tracer.value(value);
+        }
+        if let Some(enclosing) = &self.enclosing {
+            tracer.environment(enclosing);
+        }
+    }
+
+    // The scope `distance` scopes out from this one, which is at least 1.
+    fn ancestor(&self, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(self.enclosing.as_ref().expect("resolved scopes exist"));
+        for _ in 1..distance {
+            let enclosing = environment.borrow().enclosing.clone();
+            environment = enclosing.expect("resolved scopes exist");
+        }
+        environment
+    }
+
+    /// Sets the innermost variable called `name`, which must already be
+    /// declared.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme.symbol()) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined(name)),
+        }
+    }
+}
+
+fn undefined(name: &Token) -> RuntimeError {
+    RuntimeError::new(name, format!("Undefined variable '{}'.", name.lexeme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::Span;
+    use crate::token_type::TokenType;
+
+    fn name(text: &str) -> Token {
+        Token::new(
+            TokenType::Identifier,
+            Symbol::intern(text).into(),
+            None,
+            1,
+            1,
+            1 + text.len(),
+            Span::default(),
+        )
+    }
+
+    #[test]
+    fn gets_what_was_defined() {
+        let mut globals = Environment::new();
+        globals.define(Symbol::intern("a"), Value::Number(1.0));
+        assert_eq!(globals.get(&name("a")), Ok(Value::Number(1.0)));
+        globals.define(Symbol::intern("a"), Value::Nil);
+        assert_eq!(globals.get(&name("a")), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn inner_scopes_shadow_and_see_outer_ones() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals
+            .borrow_mut()
+            .define(Symbol::intern("a"), Value::Number(1.0));
+        globals
+            .borrow_mut()
+            .define(Symbol::intern("b"), Value::Number(2.0));
+        let mut inner = Environment::with_enclosing(Rc::clone(&globals));
+        inner.define(Symbol::intern("a"), Value::Number(3.0));
+
+        assert_eq!(inner.get(&name("a")), Ok(Value::Number(3.0)));
+        assert_eq!(inner.get(&name("b")), Ok(Value::Number(2.0)));
+        assert_eq!(globals.borrow().get(&name("a")), Ok(Value::Number(1.0)));
+    }
+
+    #[test]
+    fn assigns_to_the_innermost_declaration() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals
+            .borrow_mut()
+            .define(Symbol::intern("a"), Value::Number(1.0));
+        let mut inner = Environment::with_enclosing(Rc::clone(&globals));
+
+        inner.assign(&name("a"), Value::Number(2.0)).unwrap();
+        assert_eq!(globals.borrow().get(&name("a")), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn gets_and_assigns_slots_at_a_distance() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let outer = Rc::new(RefCell::new(Environment::with_enclosing(globals)));
+        outer.borrow_mut().define_slot(0, Value::Number(1.0));
+        let middle = Rc::new(RefCell::new(Environment::with_enclosing(Rc::clone(&outer))));
+        middle.borrow_mut().define_slot(0, Value::Number(2.0));
+        let mut inner = Environment::with_enclosing(Rc::clone(&middle));
+        inner.define_slot(0, Value::Number(3.0));
+        inner.define_slot(1, Value::Number(4.0));
+
+        assert_eq!(inner.get_at(0, 1), Value::Number(4.0));
+        assert_eq!(inner.get_at(2, 0), Value::Number(1.0));
+        inner.assign_at(2, 0, Value::Nil);
+        assert_eq!(outer.borrow().get_at(0, 0), Value::Nil);
+        assert_eq!(middle.borrow().get_at(0, 0), Value::Number(2.0));
+    }
+
+    #[test]
+    fn declaring_a_slot_again_replaces_its_value() {
+        let mut scope = Environment::with_enclosing(Rc::new(RefCell::new(Environment::new())));
+        scope.define_slot(0, Value::Number(1.0));
+        scope.define_slot(1, Value::Number(2.0));
+        scope.define_slot(0, Value::Nil);
+        assert_eq!(scope.get_at(0, 0), Value::Nil);
+        assert_eq!(scope.get_at(0, 1), Value::Number(2.0));
+    }
+
+    #[test]
+    fn reports_undefined_variables() {
+        let mut inner = Environment::with_enclosing(Rc::new(RefCell::new(Environment::new())));
+        assert_eq!(
+            inner.get(&name("x")).unwrap_err().message,
+            "Undefined variable 'x'."
+        );
+        assert_eq!(
+            inner.assign(&name("x"), Value::Nil).unwrap_err().message,
+            "Undefined variable 'x'."
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use crate::ast::Stmt;
+use crate::callable::LoxCallable;
+use crate::environment::Environment;
+use crate::heap::Tracer;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::resolver::Locals;
+use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// A function declaration, shared by every function value made from it.
+#[derive(Debug)]
+pub struct FunctionDeclaration {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+    /// The resolutions of the program the function was declared in.
+    pub locals: Rc<Locals>,
+}
+
+/// A function declared in Lox, with the scope it was declared in.
+#[derive(Debug)]
+pub struct LoxFunction {
+    declaration: Rc<FunctionDeclaration>,
+    // Shared rather than copied, so the function sees later changes to the
+    // variables around it and can change them itself.
+    closure: Rc<RefCell<Environment>>,
+    // Whether this is a class's `init` method, which returns `this`
+    // whatever its body does.
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionDeclaration>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// The name the function was declared with.
+    pub fn name(&self) -> &Token {
+        &self.declaration.name
+    }
+
+    pub(crate) fn declaration(&self) -> &Rc<FunctionDeclaration> {
+        &self.declaration
+    }
+
+    /// Whether this is a class's `init` method.
+    pub fn is_initializer(&self) -> bool {
+        self.is_initializer
+    }
+
+    // Each call gets a scope of its own for the parameters, inside the
+    // scope the function was declared in.
+    pub(crate) fn frame(&self, arguments: Vec<Value>) -> Environment {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        for (slot, argument) in arguments.into_iter().enumerate() {
+            environment.define_slot(slot, argument);
+        }
+        environment
+    }
+
+    /// The method bound to `receiver`: the same function, with `this`
+    /// declared as `receiver` in a scope between it and its closure, where
+    /// it is the scope's only variable.
+    pub fn bind(&self, receiver: Value) -> LoxFunction {
+        let mut environment = Environment::with_enclosing(Rc::clone(&self.closure));
+        environment.define_slot(0, receiver);
+        LoxFunction::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let environment = self.frame(arguments);
+        let value = interpreter.execute_body(&self.declaration, environment)?;
+        if self.is_initializer {
+            // An initializer is only called bound, and `this` is the only
+            // variable of the scope `bind` made.
+            return Ok(self.closure.borrow().get_at(0, 0));
+        }
+        Ok(value)
+    }
+
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.environment(&self.closure);
+    }
+
+    fn as_function(&self) -> Option<&LoxFunction> {
+        Some(self)
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
@@ -0,0 +1,491 @@
+//! Collecting the reference cycles that `Rc` can't free.
+//!
+//! Values share what they refer to through `Rc`, so anything that refers
+//! back to itself keeps itself alive: a function declared in a scope is
+//! held by that scope and holds it as its closure, an instance can hold a
+//! method bound to itself, and an array can contain itself.
+//!
+//! The interpreter registers the scopes, instances, arrays and maps it
+//! makes with its [`Heap`], which holds them weakly. Every cycle passes
+//! through one of these or through the globals: classes and functions are
+//! immutable and only refer onwards to the scopes they close over, and the
+//! scope `this` is bound in refers onwards to the instance or class.
+//!
+//! Collecting works as CPython's collector does, without being told what
+//! the roots are. The heap finds every object reachable from the
+//! registered ones and counts the references among them. An object with
+//! more strong references than that is also held from outside: by the
+//! interpreter, by a value on the Rust stack that is being computed with,
+//! or by the host. Everything reachable from those is alive, and every
+//! other registered object is part of a cycle nothing else can reach. The
+//! heap empties those, which drops the references holding the cycles
+//! together, and `Rc` frees the rest. Since nothing outside is missed, a
+//! collection can run in the middle of executing a statement.
+
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::{Rc, Weak};
+
+// How many objects the heap holds before it first prunes or collects.
+const MIN_THRESHOLD: usize = 1024;
+
+/// The objects an interpreter has made that can be part of a cycle.
+pub struct Heap {
+    environments: Tracked<Environment>,
+    instances: Tracked<LoxInstance>,
+    arrays: Tracked<Vec<Value>>,
+    maps: Tracked<BTreeMap<String, Value>>,
+    // Dropping the weak references to freed objects, so their memory is
+    // released, is due when this many are held.
+    next_prune: usize,
+    // Collecting is due when this many are held, twice as many as
+    // survived the last collection.
+    next_collection: usize,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            environments: Tracked::default(),
+            instances: Tracked::default(),
+            arrays: Tracked::default(),
+            maps: Tracked::default(),
+            next_prune: MIN_THRESHOLD,
+            next_collection: MIN_THRESHOLD,
+        }
+    }
+
+    pub fn track_environment(&mut self, environment: &Rc<RefCell<Environment>>) {
+        self.environments.objects.push(Rc::downgrade(environment));
+        self.after_tracking();
+    }
+
+    pub fn track_instance(&mut self, instance: &Rc<RefCell<LoxInstance>>) {
+        self.instances.objects.push(Rc::downgrade(instance));
+        self.after_tracking();
+    }
+
+    pub fn track_array(&mut self, array: &Rc<RefCell<Vec<Value>>>) {
+        self.arrays.objects.push(Rc::downgrade(array));
+        self.after_tracking();
+    }
+
+    pub fn track_map(&mut self, map: &Rc<RefCell<BTreeMap<String, Value>>>) {
+        self.maps.objects.push(Rc::downgrade(map));
+        self.after_tracking();
+    }
+
+    fn after_tracking(&mut self) {
+        if self.len() >= self.next_prune {
+            self.prune();
+            self.next_prune = (self.len() * 2).max(MIN_THRESHOLD);
+        }
+    }
+
+    // How many objects are held, including freed ones not yet pruned.
+    fn len(&self) -> usize {
+        self.environments.objects.len()
+            + self.instances.objects.len()
+            + self.arrays.objects.len()
+            + self.maps.objects.len()
+    }
+
+    fn prune(&mut self) {
+        self.environments.prune();
+        self.instances.prune();
+        self.arrays.prune();
+        self.maps.prune();
+    }
+
+    /// How many of the registered objects are still alive.
+    pub fn live_objects(&self) -> usize {
+        self.environments.live_count()
+            + self.instances.live_count()
+            + self.arrays.live_count()
+            + self.maps.live_count()
+    }
+
+    /// Whether enough objects have been made since the last collection
+    /// that collecting is worthwhile.
+    pub fn collection_due(&self) -> bool {
+        self.len() >= self.next_collection
+    }
+
+    /// Empties every registered object that is only reachable through
+    /// cycles of objects nothing outside the heap holds, and gives how
+    /// many there were.
+    pub fn collect(&mut self) -> usize {
+        let mut tracer = Tracer::default();
+        for environment in self.environments.live() {
+            tracer.register(Object::Environment(environment));
+        }
+        for instance in self.instances.live() {
+            tracer.register(Object::Instance(instance));
+        }
+        for array in self.arrays.live() {
+            tracer.register(Object::Array(array));
+        }
+        for map in self.maps.live() {
+            tracer.register(Object::Map(map));
+        }
+        tracer.trace();
+
+        // The unreachable objects are held until all of them are emptied,
+        // so none is freed while another's contents are being dropped.
+        let garbage = tracer.unreachable();
+        for object in &garbage {
+            object.empty();
+        }
+        let collected = garbage.len();
+        drop(garbage);
+
+        self.prune();
+        self.next_collection = (self.len() * 2).max(MIN_THRESHOLD);
+        collected
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Weak references to the objects of one type the heap has registered.
+struct Tracked<T> {
+    objects: Vec<Weak<RefCell<T>>>,
+}
+
+impl<T> Default for Tracked<T> {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl<T> Tracked<T> {
+    fn prune(&mut self) {
+        self.objects.retain(|object| object.strong_count() > 0);
+    }
+
+    fn live_count(&self) -> usize {
+        self.objects
+            .iter()
+            .filter(|object| object.strong_count() > 0)
+            .count()
+    }
+
+    fn live(&self) -> impl Iterator<Item = Rc<RefCell<T>>> + '_ {
+        self.objects.iter().filter_map(Weak::upgrade)
+    }
+}
+
+// An object the collector can look inside, held by a strong reference of
+// the tracer's own while it collects.
+#[derive(Clone)]
+enum Object {
+    Environment(Rc<RefCell<Environment>>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+}
+
+impl Object {
+    fn address(&self) -> *const () {
+        match self {
+            Object::Environment(environment) => Rc::as_ptr(environment).cast(),
+            Object::Instance(instance) => Rc::as_ptr(instance).cast(),
+            Object::Array(elements) => Rc::as_ptr(elements).cast(),
+            Object::Map(entries) => Rc::as_ptr(entries).cast(),
+            Object::Callable(callable) => Rc::as_ptr(callable).cast(),
+            Object::Class(class) => Rc::as_ptr(class).cast(),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Object::Environment(environment) => Rc::strong_count(environment),
+            Object::Instance(instance) => Rc::strong_count(instance),
+            Object::Array(elements) => Rc::strong_count(elements),
+            Object::Map(entries) => Rc::strong_count(entries),
+            Object::Callable(callable) => Rc::strong_count(callable),
+            Object::Class(class) => Rc::strong_count(class),
+        }
+    }
+
+    // Gives `tracer` what the object refers to, or false if it is being
+    // changed, so that what it refers to can't be seen.
+    fn trace(&self, tracer: &mut Tracer) -> bool {
+        match self {
+            Object::Environment(environment) => environment
+                .try_borrow()
+                .map(|environment| environment.trace(tracer))
+                .is_ok(),
+            Object::Instance(instance) => instance
+                .try_borrow()
+                .map(|instance| instance.trace(tracer))
+                .is_ok(),
+            Object::Array(elements) => elements
+                .try_borrow()
+                .map(|elements| elements.iter().for_each(|element| tracer.value(element)))
+                .is_ok(),
+            Object::Map(entries) => entries
+                .try_borrow()
+                .map(|entries| entries.values().for_each(|value| tracer.value(value)))
+                .is_ok(),
+            Object::Callable(callable) => {
+                callable.trace(tracer);
+                true
+            }
+            Object::Class(class) => {
+                class.trace(tracer);
+                true
+            }
+        }
+    }
+
+    // Drops the object's contents. Callables and classes can't be changed,
+    // and are never registered: they are freed once the objects in their
+    // cycles are emptied.
+    fn empty(&self) {
+        match self {
+            Object::Environment(environment) => {
+                let contents = std::mem::take(&mut *environment.borrow_mut());
+                drop(contents);
+            }
+            Object::Instance(instance) => {
+                let fields = instance.borrow_mut().take_fields();
+                drop(fields);
+            }
+            Object::Array(array) => {
+                let elements = std::mem::take(&mut *array.borrow_mut());
+                drop(elements);
+            }
+            Object::Map(map) => {
+                let entries = std::mem::take(&mut *map.borrow_mut());
+                drop(entries);
+            }
+            Object::Callable(_) | Object::Class(_) => {}
+        }
+    }
+}
+
+// An object the tracer has found.
+struct Node {
+    object: Object,
+    // How many references to it the objects found hold.
+    references: usize,
+    // The objects it refers to, by index.
+    children: Vec<usize>,
+    // Whether it was being changed, so its children are unknown.
+    opaque: bool,
+    // Whether the heap registered it, rather than the tracer finding it.
+    registered: bool,
+}
+
+/// Finds the objects reachable from the heap's registered objects and the
+/// references among them. Objects give it the values and scopes they
+/// refer to, each reference once. Objects are recognised by their
+/// address, and are queued rather than visited recursively, so a long
+/// chain of them can't overflow the stack.
+#[derive(Default)]
+pub struct Tracer {
+    nodes: Vec<Node>,
+    index: HashMap<*const (), usize>,
+    pending: Vec<usize>,
+    // The object whose references are being given.
+    current: Option<usize>,
+}
+
+impl Tracer {
+    /// Records a reference to what `value` refers to.
+    pub fn value(&mut self, value: &Value) {
+        match value {
+            Value::Nil | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+            Value::Array(elements) => self.reference(Object::Array(Rc::clone(elements))),
+            Value::Map(entries) => self.reference(Object::Map(Rc::clone(entries))),
+            Value::Callable(callable) => self.reference(Object::Callable(Rc::clone(callable))),
+            Value::Class(class) => self.class(class),
+            Value::Instance(instance) => self.reference(Object::Instance(Rc::clone(instance))),
+        }
+    }
+
+    /// Records a reference to `environment`.
+    pub fn environment(&mut self, environment: &Rc<RefCell<Environment>>) {
+        self.reference(Object::Environment(Rc::clone(environment)));
+    }
+
+    /// Records a reference to `class`.
+    pub fn class(&mut self, class: &Rc<LoxClass>) {
+        self.reference(Object::Class(Rc::clone(class)));
+    }
+
+    // The clone in `object` is only kept if the object is new, so while
+    // tracing, the tracer holds exactly one reference to each object.
+    fn add(&mut self, object: Object) -> usize {
+        let address = object.address();
+        if let Some(&index) = self.index.get(&address) {
+            return index;
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            object,
+            references: 0,
+            children: Vec::new(),
+            opaque: false,
+            registered: false,
+        });
+        self.index.insert(address, index);
+        self.pending.push(index);
+        index
+    }
+
+    fn register(&mut self, object: Object) {
+        let index = self.add(object);
+        self.nodes[index].registered = true;
+    }
+
+    fn reference(&mut self, object: Object) {
+        let child = self.add(object);
+        self.nodes[child].references += 1;
+        let parent = self.current.expect("references are made from an object");
+        self.nodes[parent].children.push(child);
+    }
+
+    fn trace(&mut self) {
+        while let Some(index) = self.pending.pop() {
+            self.current = Some(index);
+            let object = self.nodes[index].object.clone();
+            if !object.trace(self) {
+                self.nodes[index].opaque = true;
+            }
+        }
+        self.current = None;
+    }
+
+    // The registered objects that can't be reached from any object held
+    // from outside. Emptying them frees the rest of their cycles. An object is held from outside when it has more
+    // strong references than the tracer's own and those it found.
+    fn unreachable(self) -> Vec<Object> {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut pending: Vec<usize> = (0..self.nodes.len())
+            .filter(|&index| {
+                let node = &self.nodes[index];
+                node.opaque || node.object.strong_count() > node.references + 1
+            })
+            .collect();
+        while let Some(index) = pending.pop() {
+            if !std::mem::replace(&mut reachable[index], true) {
+                pending.extend(&self.nodes[index].children);
+            }
+        }
+        self.nodes
+            .into_iter()
+            .zip(reachable)
+            .filter(|(node, reachable)| node.registered && !reachable)
+            .map(|(node, _)| node.object)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::Interpreter;
+    use crate::value::Value;
+    use std::cell::Cell;
+    use std::io;
+    use std::rc::Rc;
+
+    fn interpreter(source: &str) -> Interpreter {
+        let mut interpreter = Interpreter::with_output(io::sink());
+        let program = crate::check(source).expect("source parses");
+        interpreter.interpret(&program).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn frees_scopes_that_hold_their_own_closures() {
+        let mut interpreter =
+            interpreter("fun f() { fun g() {} } for (var i = 0; i < 100; i = i + 1) f();");
+        assert_eq!(interpreter.live_objects(), 100);
+        assert_eq!(interpreter.collect_garbage(), 100);
+        assert_eq!(interpreter.live_objects(), 0);
+    }
+
+    #[test]
+    fn frees_instances_arrays_and_maps_that_hold_themselves() {
+        let mut interpreter = interpreter(
+            "class A { m() {} }
+             { var a = A(); a.self = a; a.m = a.m; }
+             { var b = [nil]; b[0] = b; var c = {}; c[\"c\"] = c; }",
+        );
+        // The blocks' scopes aren't part of a cycle, so they're already
+        // freed.
+        assert_eq!(interpreter.live_objects(), 3);
+        assert_eq!(interpreter.collect_garbage(), 3);
+        assert_eq!(interpreter.live_objects(), 0);
+    }
+
+    #[test]
+    fn keeps_what_the_globals_reach() {
+        let mut interpreter = interpreter(
+            "var keep;
+             fun f() { var x = [1]; fun g() { return x; } keep = g; }
+             f();
+             class A {} var a = A(); a.self = a;",
+        );
+        assert_eq!(interpreter.collect_garbage(), 0);
+        let program = crate::check("print keep()[0] + 1; print a.self == a;").unwrap();
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(interpreter.live_objects(), 3);
+    }
+
+    #[test]
+    fn collects_between_statements_once_enough_is_made() {
+        let interpreter = interpreter(&"{ var a = [nil]; a[0] = a; }\n".repeat(3000));
+        assert!(interpreter.live_objects() < 3000);
+    }
+
+    #[test]
+    fn collects_cycles_made_inside_one_call_while_keeping_what_it_uses() {
+        let most = Rc::new(Cell::new(0));
+        let kept = Rc::new(Cell::new(0.0));
+        let mut interpreter = Interpreter::with_output(io::sink());
+        let seen = Rc::clone(&most);
+        interpreter.define_native("sample", 0, move |interpreter, _| {
+            seen.set(seen.get().max(interpreter.live_objects()));
+            Ok(Value::Nil)
+        });
+        let result = Rc::clone(&kept);
+        interpreter.define_native("finish", 1, move |_, arguments| {
+            if let Value::Number(n) = arguments[0] {
+                result.set(n);
+            }
+            Ok(Value::Nil)
+        });
+        let program = crate::check(
+            "fun churn() {
+                 var kept = [0];
+                 for (var i = 0; i < 5000; i = i + 1) {
+                     var a = [nil]; a[0] = a;
+                     class C {} var c = C(); c.self = c;
+                     kept[0] = kept[0] + 1;
+                     sample();
+                 }
+                 return kept[0];
+             }
+             finish(churn());",
+        )
+        .unwrap();
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(kept.get(), 5000.0);
+        assert!(most.get() < 3000, "{} objects were alive", most.get());
+    }
+}
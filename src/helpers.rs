@@ -5,34 +5,106 @@ pub fn is_digit(c: char) -> bool {
     c.is_ascii_digit()
 }
 
+/// Whether `c` can start an identifier: `_` or any Unicode XID_Start
+/// character, which covers letters in every script but not emoji or
+/// symbols.
 #[inline]
 pub fn is_alpha(c: char) -> bool {
-    c.is_ascii_alphabetic() || c == '_'
+    c == '_' || unicode_ident::is_xid_start(c)
 }
 
+/// Whether `c` can continue an identifier (Unicode XID_Continue).
 #[inline]
 pub fn is_alpha_numeric(c: char) -> bool {
-    c.is_ascii_alphanumeric() || c == '_'
+    unicode_ident::is_xid_continue(c)
 }
 
+/// The keyword spelled by `text`, if any. Candidates are narrowed by
+/// length and first byte, leaving at most two fixed-size comparisons, so
+/// most identifiers are rejected without looking past their first byte.
 pub fn keyword_type(text: &str) -> Option<TokenType> {
-    Some(match text {
-        "and" => TokenType::And,
-        "class" => TokenType::Class,
-        "else" => TokenType::Else,
-        "false" => TokenType::False,
-        "for" => TokenType::For,
-        "fun" => TokenType::Fun,
-        "if" => TokenType::If,
-        "nil" => TokenType::Nil,
-        "or" => TokenType::Or,
-        "print" => TokenType::Print,
-        "return" => TokenType::Return,
-        "super" => TokenType::Super,
-        "this" => TokenType::This,
-        "true" => TokenType::True,
-        "var" => TokenType::Var,
-        "while" => TokenType::While,
-        _ => return None,
-    })
+    let bytes = text.as_bytes();
+    match (bytes.len(), *bytes.first()?) {
+        (2, b'd') => keyword(bytes, b"do", TokenType::Do),
+        (2, b'i') => keyword(bytes, b"if", TokenType::If),
+        (2, b'o') => keyword(bytes, b"or", TokenType::Or),
+        (3, b'a') => keyword(bytes, b"and", TokenType::And),
+        (3, b'f') => keyword(bytes, b"for", TokenType::For)
+            .or_else(|| keyword(bytes, b"fun", TokenType::Fun)),
+        (3, b'n') => keyword(bytes, b"nil", TokenType::Nil),
+        (3, b'v') => keyword(bytes, b"var", TokenType::Var),
+        (4, b'c') => keyword(bytes, b"case", TokenType::Case),
+        (4, b'e') => keyword(bytes, b"else", TokenType::Else),
+        (4, b't') => keyword(bytes, b"this", TokenType::This)
+            .or_else(|| keyword(bytes, b"true", TokenType::True)),
+        (5, b'b') => keyword(bytes, b"break", TokenType::Break),
+        (5, b'c') => keyword(bytes, b"class", TokenType::Class),
+        (5, b'f') => keyword(bytes, b"false", TokenType::False),
+        (5, b'p') => keyword(bytes, b"print", TokenType::Print),
+        (5, b's') => keyword(bytes, b"super", TokenType::Super),
+        (5, b'w') => keyword(bytes, b"while", TokenType::While),
+        (6, b'r') => keyword(bytes, b"return", TokenType::Return),
+        (6, b's') => keyword(bytes, b"switch", TokenType::Switch),
+        (7, b'd') => keyword(bytes, b"default", TokenType::Default),
+        (8, b'c') => keyword(bytes, b"continue", TokenType::Continue),
+        _ => None,
+    }
+}
+
+// Comparing against a fixed-size array lets the compiler turn the check
+// into a couple of integer compares instead of a `memcmp` call.
+#[inline(always)]
+fn keyword<const N: usize>(
+    bytes: &[u8],
+    spelling: &[u8; N],
+    token_type: TokenType,
+) -> Option<TokenType> {
+    (bytes == spelling).then_some(token_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYWORDS: &[(&str, TokenType)] = &[
+        ("and", TokenType::And),
+        ("break", TokenType::Break),
+        ("case", TokenType::Case),
+        ("class", TokenType::Class),
+        ("continue", TokenType::Continue),
+        ("default", TokenType::Default),
+        ("do", TokenType::Do),
+        ("else", TokenType::Else),
+        ("false", TokenType::False),
+        ("for", TokenType::For),
+        ("fun", TokenType::Fun),
+        ("if", TokenType::If),
+        ("nil", TokenType::Nil),
+        ("or", TokenType::Or),
+        ("print", TokenType::Print),
+        ("return", TokenType::Return),
+        ("super", TokenType::Super),
+        ("switch", TokenType::Switch),
+        ("this", TokenType::This),
+        ("true", TokenType::True),
+        ("var", TokenType::Var),
+        ("while", TokenType::While),
+    ];
+
+    #[test]
+    fn keyword_type_finds_every_keyword() {
+        for &(keyword, token_type) in KEYWORDS {
+            assert_eq!(keyword_type(keyword), Some(token_type), "{keyword}");
+        }
+    }
+
+    #[test]
+    fn keyword_type_rejects_near_misses() {
+        for text in [
+            "", "i", "d", "doo", "fan", "thus", "tree", "forx", "Class", "printf", "contin",
+            "cases", "switches", "é",
+        ] {
+            assert_eq!(keyword_type(text), None, "{text}");
+        }
+    }
 }
@@ -0,0 +1,2347 @@
+use crate::Program;
+use crate::ast::{Expr, ExprVisitor, Object, Stmt, StmtVisitor};
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, Methods};
+use crate::environment::Environment;
+use crate::function::{FunctionDeclaration, LoxFunction};
+use crate::heap::Heap;
+use crate::native::{self, NativeFunction};
+use crate::resolver::Locals;
+use crate::symbol::Symbol;
+use crate::token::Token;
+use crate::token_type::TokenType;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// An error that stops a running program, reported at the token whose
+/// evaluation failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: impl Into<String>) -> Self {
+        Self {
+            token: token.clone(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n[line {}]", self.message, self.token.line)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+// Why evaluating an expression or executing a statement stopped early.
+enum Unwind {
+    Error(RuntimeError),
+    // A `?.` found a nil receiver, which makes its whole optional chain
+    // nil. The chain's `OptionalChain` node catches this.
+    NilReceiver,
+    // A `return` statement ran, leaving every block and loop up to the
+    // function call, which catches this and gives the value as its result.
+    Return(Value),
+    // A `return` of a call to a Lox function ran. Rather than making the
+    // call, it leaves up to the function call like `Return`, which then
+    // runs this declaration's body in this scope in its place.
+    TailCall(Rc<FunctionDeclaration>, Environment),
+    // A `break` ran, leaving every block up to the innermost loop or
+    // switch, which catches this and finishes.
+    Break,
+    // A `continue` ran, leaving every block and switch up to the innermost
+    // loop, which catches this and goes on to its next pass.
+    Continue,
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+impl Unwind {
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Unwind::Error(error) => error,
+            Unwind::NilReceiver => unreachable!("the parser puts each `?.` in a chain"),
+            Unwind::Return(_) | Unwind::TailCall(..) => {
+                unreachable!("the resolver only allows `return` in a function, which catches it")
+            }
+            Unwind::Break | Unwind::Continue => {
+                unreachable!("the parser only allows `break` and `continue` where they're caught")
+            }
+        }
+    }
+}
+
+/// Dialect switches for the interpreter. The defaults follow the book,
+/// except that `+` converts to strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterpreterOptions {
+    /// Whether dividing by zero is a runtime error, rather than giving an
+    /// infinity, or NaN for `0 / 0`.
+    pub division_by_zero_error: bool,
+    /// Whether `+` with a string on either side converts the other operand
+    /// to a string, as `print` would show it, and concatenates them.
+    pub string_coercion: bool,
+    /// How many calls may be in progress at once. A call beyond this is a
+    /// "Stack overflow." runtime error, instead of overflowing the stack of
+    /// the thread the interpreter runs on. A call in a `return` statement
+    /// takes the place of the call returning, so a chain of them counts
+    /// once.
+    pub max_call_depth: usize,
+}
+
+/// How many calls may be in progress at once by default. Each call takes
+/// about 19 KiB of Rust stack in a debug build and 2.5 KiB in a release
+/// build, so this many fit in a 512 MiB stack, which the `rlox` binary
+/// runs the interpreter on, with room for deeply nested expressions. A
+/// host running the interpreter on a smaller stack, such as a main
+/// thread's 8 MiB, should lower [`InterpreterOptions::max_call_depth`] to
+/// match.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+impl Default for InterpreterOptions {
+    fn default() -> Self {
+        Self {
+            division_by_zero_error: false,
+            string_coercion: true,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        }
+    }
+}
+
+impl InterpreterOptions {
+    /// The stricter checks `--strict` turns on: dividing by zero is an
+    /// error, and `+` never converts its operands to strings.
+    pub fn strict() -> Self {
+        Self {
+            division_by_zero_error: true,
+            string_coercion: false,
+            ..Self::default()
+        }
+    }
+}
+
+type Eval = Result<Value, Unwind>;
+type Exec = Result<(), Unwind>;
+
+/// Runs programs by walking the syntax tree directly with the
+/// [`ExprVisitor`] and [`StmtVisitor`] traits.
+///
+/// An instance whose class has one of these methods takes part in
+/// operators through it:
+///
+/// - `plus`, `minus`, `times`, `divide` and `modulo`, called with the
+///   right operand of `+`, `-`, `*`, `/` and `%` when the instance is the
+///   left one.
+/// - `compare`, called the same way for `<`, `<=`, `>` and `>=`, which
+///   returns a number that is negative, zero or positive as the instance
+///   is less than, equal to or greater than the operand.
+/// - `equals`, called for `==` and `!=` and to match a `switch` on the
+///   instance, whose result's truthiness is the answer.
+/// - `toString`, whose result is shown in place of the instance by
+///   `print`, by string interpolation and by `+` joining it to a string.
+///
+/// The globals start out holding `clock()` and the math library, see
+/// [`native::define_globals`].
+pub struct Interpreter {
+    // The outermost scope, and the innermost scope of the code being run.
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // Where each local variable reference in the code being run was
+    // resolved to. Spans are only unique within one program, so each
+    // function keeps the resolutions of the program that declared it.
+    locals: Rc<Locals>,
+    // Where `print` writes.
+    output: Box<dyn Write>,
+    options: InterpreterOptions,
+    // How many calls are in progress.
+    call_depth: usize,
+    // Where the native function being run was called, which is where its
+    // errors are reported.
+    native_call: Option<Token>,
+    // The objects made so far that could be part of a cycle.
+    heap: Heap,
+}
+
+impl Interpreter {
+    /// An interpreter that prints to stdout.
+    pub fn new() -> Self {
+        Self::with_output(io::stdout())
+    }
+
+    /// An interpreter that prints to `output`.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: Rc::default(),
+            output: Box::new(output),
+            options: InterpreterOptions::default(),
+            call_depth: 0,
+            native_call: None,
+            heap: Heap::new(),
+        };
+        native::define_globals(&mut interpreter);
+        interpreter
+    }
+
+    /// Sets the dialect switches, which default to
+    /// [`InterpreterOptions::default`].
+    pub fn with_options(mut self, options: InterpreterOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Declares a global function `name` that runs `function` when a
+    /// program calls it with `arity` arguments, replacing any global of
+    /// that name. Errors `function` gives stop the program as any runtime
+    /// error does; [`Interpreter::native_error`] makes them.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    ) {
+        let function = NativeFunction::new(name, arity, function);
+        self.define_global(name, Value::Callable(Rc::new(function)));
+    }
+
+    /// Declares a global variable `name` holding `value`, replacing any
+    /// global of that name.
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        self.globals
+            .borrow_mut()
+            .define(Symbol::intern(name), value);
+    }
+
+    /// An error for the native function being run to give, reported at
+    /// the call made to it.
+    ///
+    /// # Panics
+    ///
+    /// If no native function is being run.
+    pub fn native_error(&self, message: impl Into<String>) -> RuntimeError {
+        let token = self
+            .native_call
+            .as_ref()
+            .expect("only a native function being run reports its errors at its call");
+        RuntimeError::new(token, message)
+    }
+
+    /// Executes a program's statements in order, stopping at the first
+    /// runtime error. Globals the program declares stay declared for the
+    /// next program this interpreter is given.
+    ///
+    /// Between statements, on each pass through a loop and on each call,
+    /// once enough objects have been made, cycles that can no longer be
+    /// reached are collected, see
+    /// [`Interpreter::collect_garbage`].
+    pub fn interpret(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        self.locals = Rc::clone(&program.locals);
+        program.statements.iter().try_for_each(|stmt| {
+            self.visit_stmt(stmt).map_err(Unwind::into_error)?;
+            self.collect_if_due();
+            Ok(())
+        })
+    }
+
+    /// Breaks up the scopes, instances, arrays and maps that are only held
+    /// by cycles among themselves, so that they are freed, and gives how
+    /// many there were. Anything held from outside those cycles is kept,
+    /// whether by the interpreter, by a value being computed with, or by
+    /// the host.
+    pub fn collect_garbage(&mut self) -> usize {
+        self.heap.collect()
+    }
+
+    // Collects if enough objects have been made since the last collection.
+    fn collect_if_due(&mut self) {
+        if self.heap.collection_due() {
+            self.collect_garbage();
+        }
+    }
+
+    /// How many of the objects that could be part of a cycle are alive.
+    pub fn live_objects(&self) -> usize {
+        self.heap.live_objects()
+    }
+
+    pub(crate) fn heap(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    // Runs `statements` in `environment`, going back to the current
+    // environment afterwards even if they fail.
+    fn execute_block(&mut self, statements: &[Stmt], environment: Environment) -> Exec {
+        let environment = Rc::new(RefCell::new(environment));
+        self.heap.track_environment(&environment);
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = statements.iter().try_for_each(|stmt| self.visit_stmt(stmt));
+        self.environment = previous;
+        result
+    }
+
+    // Runs the body of a function in `environment`, which holds its
+    // parameters, and gives the value the call returns. The functions it
+    // calls in tail position run here in turn, in a loop, so a chain of
+    // tail calls takes neither Rust stack nor call depth.
+    pub(crate) fn execute_body(
+        &mut self,
+        declaration: &Rc<FunctionDeclaration>,
+        environment: Environment,
+    ) -> Result<Value, RuntimeError> {
+        let locals = Rc::clone(&self.locals);
+        let mut declaration = Rc::clone(declaration);
+        let mut environment = environment;
+        let result = loop {
+            self.locals = Rc::clone(&declaration.locals);
+            match self.execute_block(&declaration.body, environment) {
+                Ok(()) => break Ok(Value::Nil),
+                Err(Unwind::Return(value)) => break Ok(value),
+                Err(Unwind::TailCall(next, next_environment)) => {
+                    declaration = next;
+                    environment = next_environment;
+                    self.collect_if_due();
+                }
+                Err(unwind) => break Err(unwind.into_error()),
+            }
+        };
+        self.locals = locals;
+        result
+    }
+
+    // Runs one pass through a loop's body, giving whether the loop goes on:
+    // false if a `break` ended it. A `continue` ends only the pass.
+    fn loop_body(&mut self, body: &Stmt) -> Result<bool, Unwind> {
+        self.collect_if_due();
+        match self.visit_stmt(body) {
+            Ok(()) | Err(Unwind::Continue) => Ok(true),
+            Err(Unwind::Break) => Ok(false),
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    // A function declared in the current scope.
+    fn function(
+        &self,
+        name: &Token,
+        params: &[Token],
+        body: &[Stmt],
+        is_initializer: bool,
+    ) -> LoxFunction {
+        let declaration = Rc::new(FunctionDeclaration {
+            name: name.clone(),
+            params: params.to_vec(),
+            body: body.to_vec(),
+            locals: Rc::clone(&self.locals),
+        });
+        LoxFunction::new(declaration, Rc::clone(&self.environment), is_initializer)
+    }
+
+    // The functions declared by one kind of class member. A method named
+    // `init` is an initializer if `methods` are instance methods.
+    fn methods(&self, declarations: &[Stmt], instance_methods: bool) -> Methods {
+        declarations
+            .iter()
+            .map(|declaration| {
+                let Stmt::Function {
+                    name, params, body, ..
+                } = declaration
+                else {
+                    unreachable!("the parser only puts functions in a class body")
+                };
+                let is_initializer = instance_methods && name.lexeme.symbol() == Symbol::INIT;
+                let function = self.function(name, params, body, is_initializer);
+                (name.lexeme.symbol(), function)
+            })
+            .collect()
+    }
+
+    // Looks `name` up on `object`. An instance's fields come first, then
+    // its class's getters, which are called, and then its methods, bound
+    // to the instance. A class has its class methods as properties.
+    fn get_property(&mut self, object: Value, name: &Token) -> Eval {
+        let found = match &object {
+            Value::Instance(instance) => {
+                let instance = instance.borrow();
+                if let Some(value) = instance.field(name.lexeme.symbol()) {
+                    return Ok(value);
+                }
+                let class = instance.class();
+                if let Some(getter) = class.find_getter(name.lexeme.symbol()) {
+                    let getter = Rc::new(getter.bind(object.clone()));
+                    drop(instance);
+                    return self.call(getter, Vec::new(), name);
+                }
+                class
+                    .find_method(name.lexeme.symbol())
+                    .map(|method| method.bind(object.clone()))
+            }
+            Value::Class(class) => class
+                .find_class_method(name.lexeme.symbol())
+                .map(|method| method.bind(object.clone())),
+            _ => return Err(RuntimeError::new(name, "Only instances have properties.").into()),
+        };
+        match found {
+            Some(method) => Ok(Value::Callable(Rc::new(method))),
+            None => Err(
+                RuntimeError::new(name, format!("Undefined property '{}'.", name.lexeme)).into(),
+            ),
+        }
+    }
+
+    // Declares `name` in the current scope: in the slot the resolver gave
+    // it, or by name if it is a global.
+    fn declare(&mut self, name: &Token, value: Value) {
+        let mut environment = self.environment.borrow_mut();
+        match self.locals.get(&name.span) {
+            Some(slot) => environment.define_slot(slot.index, value),
+            None => environment.define(name.lexeme.symbol(), value),
+        }
+    }
+
+    // Reads a variable from the scope the resolver found it in, or from
+    // the globals if it found no local declaration.
+    fn look_up_variable(&self, name: &Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&name.span) {
+            Some(slot) => Ok(self.environment.borrow().get_at(slot.depth, slot.index)),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    /// Evaluates `expr` to a value.
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.visit_expr(expr).map_err(Unwind::into_error)
+    }
+
+    fn number_operand(&self, operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+        match operand {
+            Value::Number(n) => Ok(*n),
+            _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+        }
+    }
+
+    // Calls `callable` after checking the number of arguments and the
+    // depth of calls, reporting problems at `token`.
+    fn call(
+        &mut self,
+        callable: Rc<dyn LoxCallable>,
+        arguments: Vec<Value>,
+        token: &Token,
+    ) -> Eval {
+        check_arity(callable.as_ref(), &arguments, token)?;
+        if self.call_depth == self.options.max_call_depth {
+            return Err(RuntimeError::new(token, "Stack overflow.").into());
+        }
+        self.collect_if_due();
+        self.call_depth += 1;
+        let result = if callable.as_native().is_some() {
+            let caller = self.native_call.replace(token.clone());
+            let result = callable.call(self, arguments);
+            self.native_call = caller;
+            result
+        } else {
+            callable.call(self, arguments)
+        };
+        self.call_depth -= 1;
+        Ok(result?)
+    }
+
+    // Calls the method `name` of `receiver` if it is an instance whose
+    // class has one. Problems are reported at `token`, or at the method's
+    // name where the code using it has no token of its own.
+    fn call_special_method(
+        &mut self,
+        receiver: &Value,
+        name: Symbol,
+        arguments: Vec<Value>,
+        token: Option<&Token>,
+    ) -> Result<Option<Value>, Unwind> {
+        let Value::Instance(instance) = receiver else {
+            return Ok(None);
+        };
+        let class = Rc::clone(instance.borrow().class());
+        let Some(method) = class.find_method(name) else {
+            return Ok(None);
+        };
+        let bound = Rc::new(method.bind(receiver.clone()));
+        let value = self.call(bound, arguments, token.unwrap_or(method.name()))?;
+        Ok(Some(value))
+    }
+
+    // The value of `operator` applied to an instance and `right`, if the
+    // instance's class has a method for it.
+    fn overloaded_operator(
+        &mut self,
+        operator: &Token,
+        instance: &Value,
+        right: &Value,
+    ) -> Result<Option<Value>, Unwind> {
+        let name = match operator.token_type {
+            TokenType::Plus => Symbol::PLUS,
+            TokenType::Minus => Symbol::MINUS,
+            TokenType::Star => Symbol::TIMES,
+            TokenType::Slash => Symbol::DIVIDE,
+            TokenType::Percent => Symbol::MODULO,
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => Symbol::COMPARE,
+            TokenType::EqualEqual | TokenType::BangEqual => {
+                let equal = self.equals(instance, right, Some(operator))?;
+                return Ok(Some(Value::Bool(
+                    equal == (operator.token_type == TokenType::EqualEqual),
+                )));
+            }
+            _ => return Ok(None),
+        };
+        let arguments = vec![right.clone()];
+        let Some(value) = self.call_special_method(instance, name, arguments, Some(operator))?
+        else {
+            return Ok(None);
+        };
+        if name != Symbol::COMPARE {
+            return Ok(Some(value));
+        }
+        let Value::Number(order) = value else {
+            return Err(
+                RuntimeError::new(operator, "Method 'compare' must return a number.").into(),
+            );
+        };
+        let order = order.partial_cmp(&0.0);
+        Ok(Some(Value::Bool(is_ordered(operator.token_type, order))))
+    }
+
+    // Lox equality, asking the left operand's `equals` method if it is an
+    // instance with one.
+    fn equals(
+        &mut self,
+        left: &Value,
+        right: &Value,
+        token: Option<&Token>,
+    ) -> Result<bool, Unwind> {
+        match self.call_special_method(left, Symbol::EQUALS, vec![right.clone()], token)? {
+            Some(equal) => Ok(equal.is_truthy()),
+            None => Ok(left == right),
+        }
+    }
+
+    // What `print` shows for `value`: the result of its `toString` method
+    // if it is an instance with one. Instances inside arrays and maps are
+    // shown as usual.
+    fn stringify(&mut self, value: &Value, token: Option<&Token>) -> Result<String, Unwind> {
+        match self.call_special_method(value, Symbol::TO_STRING, Vec::new(), token)? {
+            Some(string) => Ok(string.to_string()),
+            None => Ok(value.to_string()),
+        }
+    }
+
+    // The operands of a bitwise operator, which must be numbers with no
+    // fractional part that fit in 64 bits.
+    fn integer_operands(
+        &self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(i64, i64), RuntimeError> {
+        let (a, b) = self.number_operands(operator, left, right)?;
+        match (to_integer(a), to_integer(b)) {
+            (Some(a), Some(b)) => Ok((a, b)),
+            _ => Err(RuntimeError::new(operator, "Operands must be integers.")),
+        }
+    }
+
+    fn integer_operand(&self, operator: &Token, operand: &Value) -> Result<i64, RuntimeError> {
+        let n = self.number_operand(operator, operand)?;
+        to_integer(n).ok_or_else(|| RuntimeError::new(operator, "Operand must be an integer."))
+    }
+
+    // Orders two numbers, or two strings by comparing their characters'
+    // code points in turn. NaN is unordered, so every comparison with it
+    // is false.
+    fn compare(
+        &self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<Option<Ordering>, RuntimeError> {
+        match (left, right) {
+            (Value::String(a), Value::String(b)) => Ok(Some(a.cmp(b))),
+            _ => {
+                let (a, b) = self.number_operands(operator, left, right)?;
+                Ok(a.partial_cmp(&b))
+            }
+        }
+    }
+
+    fn number_operands(
+        &self,
+        operator: &Token,
+        left: &Value,
+        right: &Value,
+    ) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(a), Value::Number(b)) => Ok((*a, *b)),
+            _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+        }
+    }
+}
+
+// The integer `n` is, if it is one that fits in 64 bits. NaN and the
+// infinities aren't integers.
+fn to_integer(n: f64) -> Option<i64> {
+    // -2^63 is the smallest i64 and 2^63 one past the largest.
+    let in_range = (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&n);
+    (n.fract() == 0.0 && in_range).then_some(n as i64)
+}
+
+// Applies a bitwise operator to two integers. Shifts move bits off the end
+// and `>>` copies the sign bit in; shifting by less than 0 or more than 63
+// is an error.
+fn bitwise(operator: &Token, a: i64, b: i64) -> Result<i64, RuntimeError> {
+    let shift = || {
+        u32::try_from(b)
+            .ok()
+            .filter(|&b| b < 64)
+            .ok_or_else(|| RuntimeError::new(operator, "Shift amount must be from 0 to 63."))
+    };
+    Ok(match operator.token_type {
+        TokenType::Ampersand => a & b,
+        TokenType::Pipe => a | b,
+        TokenType::Caret => a ^ b,
+        TokenType::LessLess => a << shift()?,
+        TokenType::GreaterGreater => a >> shift()?,
+        _ => unreachable!("only bitwise operators reach here"),
+    })
+}
+
+// The value being called at `paren`, which must be a function or class.
+fn callable(callee: Value, paren: &Token) -> Result<Rc<dyn LoxCallable>, RuntimeError> {
+    match callee {
+        Value::Callable(callable) => Ok(callable),
+        Value::Class(class) => Ok(class),
+        _ => Err(RuntimeError::new(
+            paren,
+            "Can only call functions and classes.",
+        )),
+    }
+}
+
+fn check_arity(
+    callable: &dyn LoxCallable,
+    arguments: &[Value],
+    token: &Token,
+) -> Result<(), RuntimeError> {
+    if arguments.len() != callable.arity() {
+        return Err(RuntimeError::new(
+            token,
+            format!(
+                "Expected {} arguments but got {}.",
+                callable.arity(),
+                arguments.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Whether two operands in `order` satisfy the comparison `operator`. An
+// unordered pair, where one is NaN, satisfies none.
+fn is_ordered(operator: TokenType, order: Option<Ordering>) -> bool {
+    match operator {
+        TokenType::Greater => order == Some(Ordering::Greater),
+        TokenType::GreaterEqual => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+        TokenType::Less => order == Some(Ordering::Less),
+        TokenType::LessEqual => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+        _ => unreachable!("only comparisons order their operands"),
+    }
+}
+
+// The position `index` names in an array of `len` elements.
+fn array_index(bracket: &Token, index: &Value, len: usize) -> Result<usize, RuntimeError> {
+    let Value::Number(n) = *index else {
+        return Err(RuntimeError::new(bracket, "Array index must be a number."));
+    };
+    if n.fract() != 0.0 {
+        return Err(RuntimeError::new(
+            bracket,
+            "Array index must be an integer.",
+        ));
+    }
+    if n < 0.0 || n >= len as f64 {
+        return Err(RuntimeError::new(
+            bracket,
+            format!(
+                "Array index {} is out of bounds for length {len}.",
+                Value::format_number(n)
+            ),
+        ));
+    }
+    Ok(n as usize)
+}
+
+fn map_key(bracket: &Token, key: &Value) -> Result<String, RuntimeError> {
+    match key {
+        Value::String(key) => Ok(key.clone()),
+        _ => Err(RuntimeError::new(bracket, "Map key must be a string.")),
+    }
+}
+
+impl ExprVisitor<Eval> for Interpreter {
+    fn visit_array_expr(&mut self, elements: &[Expr]) -> Eval {
+        let elements = elements
+            .iter()
+            .map(|element| self.visit_expr(element))
+            .collect::<Result<Vec<_>, _>>()?;
+        let array = Rc::new(RefCell::new(elements));
+        self.heap.track_array(&array);
+        Ok(Value::Array(array))
+    }
+
+    // The value is the result, so `a = b = 1` sets both.
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Eval {
+        let value = self.visit_expr(value)?;
+        match self.locals.get(&name.span) {
+            Some(slot) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(slot.depth, slot.index, value.clone());
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_binary_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Eval {
+        let left = self.visit_expr(left)?;
+        let right = self.visit_expr(right)?;
+        if let Value::Instance(_) = left
+            && let Some(value) = self.overloaded_operator(operator, &left, &right)?
+        {
+            return Ok(value);
+        }
+        let value = match operator.token_type {
+            TokenType::Plus => match (left, right) {
+                (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                (Value::String(a), Value::String(b)) => Value::String(a + &b),
+                (Value::String(a), b) if self.options.string_coercion => {
+                    Value::String(a + &self.stringify(&b, Some(operator))?)
+                }
+                (a, Value::String(b)) if self.options.string_coercion => {
+                    Value::String(self.stringify(&a, Some(operator))? + &b)
+                }
+                (Value::String(_), _) | (_, Value::String(_)) => {
+                    return Err(RuntimeError::new(
+                        operator,
+                        "Operands must be two numbers or two strings \
+                         (string coercion is off under --strict).",
+                    )
+                    .into());
+                }
+                _ => {
+                    return Err(RuntimeError::new(
+                        operator,
+                        "Operands must be two numbers or two strings.",
+                    )
+                    .into());
+                }
+            },
+            TokenType::Minus => {
+                let (a, b) = self.number_operands(operator, &left, &right)?;
+                Value::Number(a - b)
+            }
+            TokenType::Star => {
+                let (a, b) = self.number_operands(operator, &left, &right)?;
+                Value::Number(a * b)
+            }
+            TokenType::Slash => {
+                let (a, b) = self.number_operands(operator, &left, &right)?;
+                if b == 0.0 && self.options.division_by_zero_error {
+                    return Err(RuntimeError::new(operator, "Division by zero.").into());
+                }
+                Value::Number(a / b)
+            }
+            // The operands are integers, as for the bitwise operators, and
+            // the remainder has the sign of the dividend, as in C. A zero
+            // divisor gives NaN, as `0 / 0` does.
+            TokenType::Percent => {
+                let (a, b) = self.integer_operands(operator, &left, &right)?;
+                if b == 0 {
+                    if self.options.division_by_zero_error {
+                        return Err(RuntimeError::new(operator, "Division by zero.").into());
+                    }
+                    Value::Number(f64::NAN)
+                } else {
+                    // Only -2^63 % -1 overflows, and its remainder is 0.
+                    Value::Number(a.wrapping_rem(b) as f64)
+                }
+            }
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater => {
+                let (a, b) = self.integer_operands(operator, &left, &right)?;
+                Value::Number(bitwise(operator, a, b)? as f64)
+            }
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => {
+                let order = self.compare(operator, &left, &right)?;
+                Value::Bool(is_ordered(operator.token_type, order))
+            }
+            TokenType::EqualEqual => Value::Bool(left == right),
+            TokenType::BangEqual => Value::Bool(left != right),
+            _ => unreachable!("the parser only builds binary nodes for binary operators"),
+        };
+        Ok(value)
+    }
+
+    // The callee and then the arguments are evaluated left to right before
+    // anything is checked. Errors are reported at the call's `)`.
+    fn visit_call_expr(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) -> Eval {
+        let callee = self.visit_expr(callee)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.visit_expr(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        let callable = callable(callee, paren)?;
+        self.call(callable, arguments, paren)
+    }
+
+    fn visit_comma_expr(&mut self, expressions: &[Expr]) -> Eval {
+        let mut value = Value::Nil;
+        for expression in expressions {
+            value = self.visit_expr(expression)?;
+        }
+        Ok(value)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Eval {
+        let object = self.visit_expr(object)?;
+        self.get_property(object, name)
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Eval {
+        self.visit_expr(expression)
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Eval {
+        let object = self.visit_expr(object)?;
+        let index = self.visit_expr(index)?;
+        match object {
+            Value::Array(elements) => {
+                let elements = elements.borrow();
+                let i = array_index(bracket, &index, elements.len())?;
+                Ok(elements[i].clone())
+            }
+            Value::Map(entries) => {
+                let key = map_key(bracket, &index)?;
+                match entries.borrow().get(&key) {
+                    Some(value) => Ok(value.clone()),
+                    None => {
+                        Err(RuntimeError::new(bracket, format!("Undefined key {key:?}.")).into())
+                    }
+                }
+            }
+            _ => Err(RuntimeError::new(bracket, "Only arrays and maps can be indexed.").into()),
+        }
+    }
+
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) -> Eval {
+        let mut string = String::new();
+        for part in parts {
+            let value = self.visit_expr(part)?;
+            string.push_str(&self.stringify(&value, None)?);
+        }
+        Ok(Value::String(string))
+    }
+
+    fn visit_literal_expr(&mut self, value: &Object) -> Eval {
+        Ok(value.clone())
+    }
+
+    // The result is whichever operand decided it, not a boolean: `or`
+    // gives its left operand if that is truthy, `and` if it is falsey, and
+    // otherwise each gives its right operand without evaluating it early.
+    fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> Eval {
+        let left = self.visit_expr(left)?;
+        let decided = match operator.token_type {
+            TokenType::Or => left.is_truthy(),
+            TokenType::And => !left.is_truthy(),
+            _ => unreachable!("the parser only builds logical nodes for 'and' and 'or'"),
+        };
+        if decided {
+            Ok(left)
+        } else {
+            self.visit_expr(right)
+        }
+    }
+
+    fn visit_map_expr(&mut self, keys: &[Expr], values: &[Expr]) -> Eval {
+        let mut entries = BTreeMap::new();
+        for (key, value) in keys.iter().zip(values) {
+            let Value::String(key) = self.visit_expr(key)? else {
+                unreachable!("the parser only makes string map keys")
+            };
+            entries.insert(key, self.visit_expr(value)?);
+        }
+        let map = Rc::new(RefCell::new(entries));
+        self.heap.track_map(&map);
+        Ok(Value::Map(map))
+    }
+
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) -> Eval {
+        match self.visit_expr(expression) {
+            Err(Unwind::NilReceiver) => Ok(Value::Nil),
+            result => result,
+        }
+    }
+
+    fn visit_optional_get_expr(&mut self, object: &Expr, name: &Token) -> Eval {
+        match self.visit_expr(object)? {
+            Value::Nil => Err(Unwind::NilReceiver),
+            object => self.get_property(object, name),
+        }
+    }
+
+    // The object is checked before the value is evaluated. If the class
+    // has a setter for `name` it is called instead of setting a field.
+    fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> Eval {
+        let Value::Instance(instance) = self.visit_expr(object)? else {
+            return Err(RuntimeError::new(name, "Only instances have fields.").into());
+        };
+        let value = self.visit_expr(value)?;
+        let setter = instance
+            .borrow()
+            .class()
+            .find_setter(name.lexeme.symbol())
+            .map(|setter| setter.bind(Value::Instance(Rc::clone(&instance))));
+        match setter {
+            Some(setter) => {
+                self.call(Rc::new(setter), vec![value.clone()], name)?;
+            }
+            None => instance
+                .borrow_mut()
+                .set_field(name.lexeme.symbol(), value.clone()),
+        }
+        Ok(value)
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Eval {
+        let object = self.visit_expr(object)?;
+        let index = self.visit_expr(index)?;
+        let value = self.visit_expr(value)?;
+        match object {
+            Value::Array(elements) => {
+                let mut elements = elements.borrow_mut();
+                let i = array_index(bracket, &index, elements.len())?;
+                elements[i] = value.clone();
+            }
+            Value::Map(entries) => {
+                let key = map_key(bracket, &index)?;
+                entries.borrow_mut().insert(key, value.clone());
+            }
+            _ => {
+                return Err(
+                    RuntimeError::new(bracket, "Only arrays and maps can be indexed.").into(),
+                );
+            }
+        }
+        Ok(value)
+    }
+
+    // Finds `method` on the superclass, skipping any override, and binds
+    // it to `this`. In a class method it finds a class method.
+    fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> Eval {
+        // `this` is declared one scope inside the scope declaring `super`,
+        // and each is its scope's only variable.
+        let slot = self.locals[&keyword.span];
+        let environment = self.environment.borrow();
+        let Value::Class(superclass) = environment.get_at(slot.depth, slot.index) else {
+            unreachable!("`super` is always a class")
+        };
+        let this = environment.get_at(slot.depth - 1, 0);
+        drop(environment);
+
+        let found = match &this {
+            Value::Class(_) => superclass.find_class_method(method.lexeme.symbol()),
+            _ => {
+                if let Some(getter) = superclass.find_getter(method.lexeme.symbol()) {
+                    let getter = Rc::new(getter.bind(this));
+                    return self.call(getter, Vec::new(), method);
+                }
+                superclass.find_method(method.lexeme.symbol())
+            }
+        };
+        match found {
+            Some(found) => Ok(Value::Callable(Rc::new(found.bind(this)))),
+            None => Err(RuntimeError::new(
+                method,
+                format!("Undefined property '{}'.", method.lexeme),
+            )
+            .into()),
+        }
+    }
+
+    fn visit_ternary_expr(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Expr,
+        else_branch: &Expr,
+    ) -> Eval {
+        if self.visit_expr(condition)?.is_truthy() {
+            self.visit_expr(then_branch)
+        } else {
+            self.visit_expr(else_branch)
+        }
+    }
+
+    // The resolver rejects `this` outside of a class, so it is always
+    // local.
+    fn visit_this_expr(&mut self, keyword: &Token) -> Eval {
+        let slot = self.locals[&keyword.span];
+        Ok(self.environment.borrow().get_at(slot.depth, slot.index))
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Eval {
+        let right = self.visit_expr(right)?;
+        match operator.token_type {
+            TokenType::Minus => Ok(Value::Number(-self.number_operand(operator, &right)?)),
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Tilde => Ok(Value::Number(
+                !self.integer_operand(operator, &right)? as f64
+            )),
+            _ => unreachable!("the parser only builds unary nodes for '-', '!' and '~'"),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) -> Eval {
+        Ok(self.look_up_variable(name)?)
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StmtVisitor<Exec> for Interpreter {
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Exec {
+        let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) -> Exec {
+        Err(Unwind::Break)
+    }
+
+    fn visit_case_stmt(&mut self, _keyword: &Token, _values: &[Expr], _body: &[Stmt]) -> Exec {
+        unreachable!("a case only runs as part of its switch")
+    }
+
+    fn visit_class_stmt(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+        getters: &[Stmt],
+        setters: &[Stmt],
+        class_methods: &[Stmt],
+    ) -> Exec {
+        let superclass = match superclass {
+            Some(expr) => match self.visit_expr(expr)? {
+                Value::Class(superclass) => Some(superclass),
+                _ => {
+                    let Expr::Variable { name, .. } = expr else {
+                        unreachable!("the parser only makes superclasses from names")
+                    };
+                    return Err(RuntimeError::new(name, "Superclass must be a class.").into());
+                }
+            },
+            None => None,
+        };
+
+        // The members of a subclass close over a scope holding `super`,
+        // so `super` always means the superclass of the class a method
+        // was declared in, whatever the class of `this`.
+        let enclosing = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::with_enclosing(Rc::clone(&enclosing));
+            environment.define_slot(0, Value::Class(Rc::clone(superclass)));
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+        let class = LoxClass::new(
+            name.lexeme.symbol(),
+            superclass,
+            self.methods(methods, true),
+            self.methods(getters, false),
+            self.methods(setters, false),
+            self.methods(class_methods, false),
+        );
+        self.environment = enclosing;
+
+        let class = Value::Class(Rc::new(class));
+        self.declare(name, class);
+        Ok(())
+    }
+
+    fn visit_continue_stmt(&mut self, _keyword: &Token) -> Exec {
+        Err(Unwind::Continue)
+    }
+
+    // A `continue` goes on to the condition.
+    fn visit_do_while_stmt(&mut self, body: &Stmt, condition: &Expr) -> Exec {
+        loop {
+            if !self.loop_body(body)? {
+                return Ok(());
+            }
+            if !self.visit_expr(condition)?.is_truthy() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> Exec {
+        self.visit_expr(expression)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &[Stmt]) -> Exec {
+        let function = self.function(name, params, body, false);
+        self.declare(name, Value::Callable(Rc::new(function)));
+        Ok(())
+    }
+
+    fn visit_if_stmt(
+        &mut self,
+        condition: &Expr,
+        then_branch: &Stmt,
+        else_branch: Option<&Stmt>,
+    ) -> Exec {
+        if self.visit_expr(condition)?.is_truthy() {
+            self.visit_stmt(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.visit_stmt(else_branch)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Like `println!`, this panics if the output can't be written to.
+    fn visit_print_stmt(&mut self, expression: &Expr) -> Exec {
+        let value = self.visit_expr(expression)?;
+        let string = self.stringify(&value, None)?;
+        writeln!(self.output, "{string}").expect("failed printing to output");
+        Ok(())
+    }
+
+    // A bare `return;` returns nil. A call to a Lox function in a
+    // `return` is left to the function call to make, in place of the
+    // function returning; initializers are called as usual, since
+    // returning their `this` is left to the call of each.
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> Exec {
+        let value = match value {
+            Some(Expr::Call {
+                callee,
+                paren,
+                arguments,
+                ..
+            }) => {
+                let callee = self.visit_expr(callee)?;
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.visit_expr(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let callable = callable(callee, paren)?;
+                if let Some(function) = callable.as_function()
+                    && !function.is_initializer()
+                {
+                    check_arity(function, &arguments, paren)?;
+                    let environment = function.frame(arguments);
+                    return Err(Unwind::TailCall(
+                        Rc::clone(function.declaration()),
+                        environment,
+                    ));
+                }
+                self.call(callable, arguments, paren)?
+            }
+            Some(value) => self.visit_expr(value)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    // Runs the body of the first case with a value equal to the subject,
+    // or else the default case. Case values are evaluated in order, only
+    // until one matches. Each body is a scope of its own.
+    fn visit_switch_stmt(&mut self, _keyword: &Token, subject: &Expr, cases: &[Stmt]) -> Exec {
+        let subject = self.visit_expr(subject)?;
+        let mut default = None;
+        let mut chosen = None;
+        'cases: for case in cases {
+            let Stmt::Case {
+                keyword,
+                values,
+                body,
+                ..
+            } = case
+            else {
+                unreachable!("the parser only puts cases in a switch")
+            };
+            if keyword.token_type == TokenType::Default {
+                default = Some(body);
+            }
+            for value in values {
+                let value = self.visit_expr(value)?;
+                if self.equals(&subject, &value, Some(keyword))? {
+                    chosen = Some(body);
+                    break 'cases;
+                }
+            }
+        }
+        match chosen.or(default) {
+            Some(body) => {
+                let environment = Environment::with_enclosing(Rc::clone(&self.environment));
+                match self.execute_block(body, environment) {
+                    Err(Unwind::Break) => Ok(()),
+                    result => result,
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    // A variable declared without an initializer is nil.
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Exec {
+        let value = match initializer {
+            Some(initializer) => self.visit_expr(initializer)?,
+            None => Value::Nil,
+        };
+        self.declare(name, value);
+        Ok(())
+    }
+
+    // A `for` loop is a `while` loop with an increment, which runs after
+    // each pass through the body, including one a `continue` ends.
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Exec {
+        while self.visit_expr(condition)?.is_truthy() {
+            if !self.loop_body(body)? {
+                return Ok(());
+            }
+            if let Some(increment) = increment {
+                self.visit_expr(increment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Stmt;
+
+    fn evaluate(source: &str) -> Result<Value, RuntimeError> {
+        let program = crate::check(&format!("print {source};")).expect("source parses");
+        let [Stmt::Print { expression, .. }] = program.statements.as_slice() else {
+            panic!("expected one print statement");
+        };
+        Interpreter::new().evaluate(expression)
+    }
+
+    fn prints(source: &str) -> String {
+        evaluate(source).unwrap().to_string()
+    }
+
+    fn error(source: &str) -> String {
+        evaluate(source).unwrap_err().to_string()
+    }
+
+    // Collects what a program prints.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Runs `source` and returns what it printed, followed by the runtime
+    // error that stopped it, if any.
+    fn run(source: &str) -> String {
+        let program = crate::check(source).expect("source parses");
+        let output = Output::default();
+        let result = Interpreter::with_output(output.clone()).interpret(&program);
+        let mut printed = String::from_utf8(output.0.take()).unwrap();
+        if let Err(error) = result {
+            printed.push_str(&error.to_string());
+        }
+        printed
+    }
+
+    #[test]
+    fn executes_statements_in_order() {
+        assert_eq!(run("print 1; 2 + 3; print \"two\";"), "1\ntwo\n");
+        assert_eq!(run("{ print 1; { print 2; } } print 3;"), "1\n2\n3\n");
+        assert_eq!(run(""), "");
+    }
+
+    #[test]
+    fn declares_and_reads_variables() {
+        assert_eq!(run("var a = 1; var b; print a; print b;"), "1\nnil\n");
+        assert_eq!(run("var a = 1; var a = a + 1; print a;"), "2\n");
+        assert_eq!(run("var a = \"x\"; var b = [a]; print b;"), "[\"x\"]\n");
+    }
+
+    #[test]
+    fn blocks_scope_their_variables() {
+        assert_eq!(
+            run("var a = \"outer\"; { var a = \"inner\"; print a; } print a;"),
+            "inner\nouter\n"
+        );
+        assert_eq!(run("var a = 1; { { print a; } }"), "1\n");
+        assert_eq!(
+            run("{ var a = 1; }\nprint a;"),
+            "Undefined variable 'a'.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn assigns_to_declared_variables() {
+        assert_eq!(run("var a = 1; a = 2; print a;"), "2\n");
+        assert_eq!(run("var a; var b; a = b = 3; print a; print b;"), "3\n3\n");
+        assert_eq!(run("var a = 1; print a = \"x\";"), "x\n");
+        assert_eq!(run("var a = 1; { var a = 2; a = 3; } print a;"), "1\n");
+        assert_eq!(run("var a = 1; { a = 2; } print a;"), "2\n");
+    }
+
+    #[test]
+    fn assignment_needs_a_declared_variable() {
+        assert_eq!(
+            run("var a;\na = b = 1;"),
+            "Undefined variable 'b'.\n[line 2]"
+        );
+        assert_eq!(
+            run("{ var a; }\na = 1;"),
+            "Undefined variable 'a'.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn reports_undefined_variables() {
+        assert_eq!(
+            run("print 1;\nprint x;"),
+            "1\nUndefined variable 'x'.\n[line 2]"
+        );
+        assert_eq!(run("var a = b;"), "Undefined variable 'b'.\n[line 1]");
+    }
+
+    #[test]
+    fn a_failed_block_leaves_its_scope() {
+        let program = crate::check("var a = 1; { var a = 2; nil.x; }").unwrap();
+        let mut interpreter = Interpreter::with_output(Output::default());
+        assert!(interpreter.interpret(&program).is_err());
+        let program = crate::check("print a;").unwrap();
+        let output = Output::default();
+        interpreter.output = Box::new(output.clone());
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(output.0.take(), b"1\n");
+    }
+
+    #[test]
+    fn redeclaring_a_local_replaces_it() {
+        assert_eq!(
+            run("{ var a = 1; fun f() { return a; } var a = 2; print f(); print a; }"),
+            "2\n2\n"
+        );
+        assert_eq!(
+            run("fun f(a, b) { var a = b; return a; } print f(1, 2);"),
+            "2\n"
+        );
+    }
+
+    #[test]
+    fn programs_share_globals_but_not_resolutions() {
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_output(output.clone());
+        // Each program's spans start from 0, so `y` on the second line is
+        // where the first line's local `x` is.
+        for line in [
+            "var y = 2; fun f() { var x = 1; { return x; } }",
+            "                                     print y;",
+            "print f() + y;",
+        ] {
+            interpreter.interpret(&crate::check(line).unwrap()).unwrap();
+        }
+        assert_eq!(output.0.take(), b"2\n3\n");
+    }
+
+    #[test]
+    fn executes_if_statements() {
+        assert_eq!(run("if (1 < 2) print \"yes\"; else print \"no\";"), "yes\n");
+        assert_eq!(run("if (nil) print \"yes\"; else print \"no\";"), "no\n");
+        assert_eq!(run("if (false) print 1; print 2;"), "2\n");
+        assert_eq!(run("if (true) if (false) print 1; else print 2;"), "2\n");
+        assert_eq!(run("if (0) { var a = 1; print a; }"), "1\n");
+    }
+
+    #[test]
+    fn executes_loops() {
+        assert_eq!(
+            run("var i = 0; while (i < 3) { print i; i = i + 1; }"),
+            "0\n1\n2\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + 1) print i;"),
+            "0\n1\n2\n"
+        );
+        assert_eq!(run("for (var i = 0; i < 0;) print i;"), "");
+        assert_eq!(
+            run("var i = 5; do { print i; i = i + 1; } while (i < 3);"),
+            "5\n"
+        );
+        assert_eq!(
+            run("var n = 0; do n = n + 1; while (n < 3); print n;"),
+            "3\n"
+        );
+    }
+
+    #[test]
+    fn for_loop_variables_are_scoped_to_the_loop() {
+        assert_eq!(
+            run("var i = \"outer\"; for (var i = 0; i < 1; i = i + 1) {} print i;"),
+            "outer\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 1; i = i + 1) {}\nprint i;"),
+            "Undefined variable 'i'.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn break_and_continue_leave_the_innermost_loop() {
+        assert_eq!(
+            run("var i = 0; while (true) { if (i == 3) break; print i; i = i + 1; }"),
+            "0\n1\n2\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 5; i = i + 1) { if (i % 2 == 0) continue; print i; }"),
+            "1\n3\n"
+        );
+        assert_eq!(
+            run("var i = 0; do { i = i + 1; if (i < 3) continue; print i; } while (i < 5);"),
+            "3\n4\n5\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 2; i = i + 1) {
+                   for (var j = 0; j < 3; j = j + 1) { if (j == 1) break; print j; }
+                   print i;
+                 }"),
+            "0\n0\n0\n1\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 2; i = i + 1) {
+                   { var a = \"in\"; fun f() { return a; } continue; }
+                   print \"unreached\";
+                 }
+                 print \"done\";"),
+            "done\n"
+        );
+    }
+
+    #[test]
+    fn break_leaves_a_switch_and_continue_leaves_its_loop() {
+        assert_eq!(
+            run(
+                "switch (1) { case 1: print \"one\"; break; print \"unreached\"; }
+                 print \"after\";"
+            ),
+            "one\nafter\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + 1) {
+                   switch (i) { case 1: continue; default: print i; }
+                   print \"end\";
+                 }"),
+            "0\nend\n2\nend\n"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + 1) { switch (i) { case 1: break; } print i; }"),
+            "0\n1\n2\n"
+        );
+    }
+
+    #[test]
+    fn a_runtime_error_in_a_loop_condition_stops_the_loop() {
+        assert_eq!(
+            run(
+                "var i = 0;\nwhile (i < 3) {\n  print i;\n  i = i + 1;\n  if (i == 2) i = nil;\n}\nprint \"after\";"
+            ),
+            "0\n1\nOperands must be numbers.\n[line 2]"
+        );
+        assert_eq!(
+            run("for (var i = 0; i < 3; i = i + nil) print i;"),
+            "0\nOperands must be two numbers or two strings.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn executes_switch_statements() {
+        let source = "
+            for (var i = 0; i < 4; i = i + 1) {
+                switch (i) {
+                    case 0: print \"zero\";
+                    case 1, 2: var s = \"small\"; print s;
+                    default: print \"other\";
+                }
+            }";
+        assert_eq!(run(source), "zero\nsmall\nsmall\nother\n");
+        assert_eq!(run("switch (\"a\") { case \"b\": print 1; }"), "");
+        assert_eq!(
+            run("switch (1) { case 1: print 1; case nil.x: print 2; }"),
+            "1\n"
+        );
+        assert_eq!(
+            run("switch (1) { case 2: print 1; default: print 3; }"),
+            "3\n"
+        );
+    }
+
+    #[test]
+    fn calls_functions_with_their_arguments() {
+        assert_eq!(
+            run(
+                "fun greet(name, greeting) { print greeting + \", \" + name; }\n\
+                 greet(\"Lox\", \"Hello\");"
+            ),
+            "Hello, Lox\n"
+        );
+        assert_eq!(
+            run("fun count(n) { if (n > 0) { print n; count(n - 1); } } count(2);"),
+            "2\n1\n"
+        );
+        assert_eq!(
+            run("var a = \"global\"; fun f(a) { print a; } f(1); print a;"),
+            "1\nglobal\n"
+        );
+        assert_eq!(run("fun f() {} print f(); print f;"), "nil\n<fn f>\n");
+    }
+
+    #[test]
+    fn functions_close_over_the_scope_they_were_declared_in() {
+        assert_eq!(
+            run("var counter;
+                 fun makeCounter() {
+                     var i = 0;
+                     fun count() { i = i + 1; print i; }
+                     counter = count;
+                 }
+                 makeCounter();
+                 counter();
+                 counter();"),
+            "1\n2\n"
+        );
+        assert_eq!(
+            run("var add;
+                 fun makeAdder(n) { fun adder(x) { print n + x; } add = adder; }
+                 makeAdder(10);
+                 var first = add;
+                 makeAdder(20);
+                 first(1);
+                 add(1);"),
+            "11\n21\n"
+        );
+        assert_eq!(
+            run("{ var a = 1; fun show() { print a; } a = 2; show(); }"),
+            "2\n"
+        );
+    }
+
+    #[test]
+    fn closures_keep_the_variable_they_resolved_to() {
+        assert_eq!(
+            run("var a = \"global\";
+                 {
+                     fun showA() { print a; }
+                     showA();
+                     var a = \"block\";
+                     showA();
+                     a = \"assigned\";
+                     showA();
+                 }"),
+            "global\nglobal\nglobal\n"
+        );
+        assert_eq!(
+            run("fun outer() {
+                     var x = 1;
+                     fun inner() { x = x + 1; return x; }
+                     { var x = 10; print inner(); }
+                     print x;
+                 }
+                 outer();"),
+            "2\n2\n"
+        );
+    }
+
+    #[test]
+    fn returns_values_from_functions() {
+        assert_eq!(
+            run(
+                "fun fib(n) { if (n <= 1) return n; return fib(n - 2) + fib(n - 1); }\n\
+                 print fib(10);"
+            ),
+            "55\n"
+        );
+        assert_eq!(
+            run("fun f() { return; print \"unreachable\"; } print f();"),
+            "nil\n"
+        );
+        assert_eq!(
+            run("fun makeCounter() {
+                     var i = 0;
+                     fun count() { i = i + 1; return i; }
+                     return count;
+                 }
+                 var counter = makeCounter();
+                 counter();
+                 print counter();"),
+            "2\n"
+        );
+    }
+
+    #[test]
+    fn return_leaves_nested_blocks_and_loops() {
+        assert_eq!(
+            run("fun find(n) {
+                     for (var i = 0; i < 10; i = i + 1) {
+                         var j = 0;
+                         while (j < 1) {
+                             { if (i == n) return i * 10; }
+                             switch (i) { default: if (i > n) return -1; }
+                             j = j + 1;
+                         }
+                     }
+                     return nil;
+                 }
+                 print find(3);"),
+            "30\n"
+        );
+        assert_eq!(
+            run("var a = \"global\";
+                 fun f() { { var a = \"local\"; return; } }
+                 f();
+                 print a;"),
+            "global\n"
+        );
+        assert_eq!(
+            run("fun f() { do { return 1; } while (true); } print f();"),
+            "1\n"
+        );
+    }
+
+    #[test]
+    fn checks_arity_at_the_call_site() {
+        assert_eq!(
+            run("fun f(a, b) {}\nf(1,\n2, 3);"),
+            "Expected 2 arguments but got 3.\n[line 3]"
+        );
+        assert_eq!(
+            run("fun f(a) {}\nf();"),
+            "Expected 1 arguments but got 0.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn classes_make_instances_with_fields() {
+        assert_eq!(
+            run("class Point {}
+                 var p = Point();
+                 print Point;
+                 print p;
+                 p.x = 1;
+                 p.y = p.x + 1;
+                 print p.x + p.y;
+                 print p?.y;
+                 print p.x = 3;
+                 print p.x;"),
+            "Point\nPoint instance\n3\n2\n3\n3\n"
+        );
+        assert_eq!(
+            run(
+                "class A {} var a = A(); var b = a; b.f = \"shared\"; print a.f;
+                 print a == b; print a == A(); print A == A;"
+            ),
+            "shared\ntrue\nfalse\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn methods_are_bound_to_their_instance() {
+        assert_eq!(
+            run("class Counter {
+                     add(n) { this.count = this.count + n; return this; }
+                     show() { print this.count; }
+                 }
+                 var c = Counter();
+                 c.count = 1;
+                 c.add(2).add(3).show();
+                 var show = c.show;
+                 c.count = 0;
+                 show();
+                 print show;"),
+            "6\n0\n<fn show>\n"
+        );
+        assert_eq!(
+            run("class A { f() { return \"method\"; } }
+                 var a = A();
+                 a.f = \"field\";
+                 print a.f;"),
+            "field\n"
+        );
+        assert_eq!(
+            run("class A { f() { fun g() { return this; } return g; } }
+                 var a = A();
+                 print a.f()() == a;"),
+            "true\n"
+        );
+    }
+
+    #[test]
+    fn init_runs_when_an_instance_is_made() {
+        assert_eq!(
+            run("class Point {
+                     init(x, y) { this.x = x; this.y = y; }
+                     sum() { return this.x + this.y; }
+                 }
+                 print Point(1, 2).sum();"),
+            "3\n"
+        );
+        assert_eq!(
+            run("class A {
+                     init(n) { this.n = n; if (n > 0) return; this.n = \"none\"; }
+                 }
+                 var a = A(0);
+                 print a.n;
+                 print a.init(5) == a;
+                 print a.n;"),
+            "none\ntrue\n5\n"
+        );
+        assert_eq!(
+            run("class A { init(a, b) {} }\nA(1);"),
+            "Expected 2 arguments but got 1.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn getters_setters_and_class_methods() {
+        assert_eq!(
+            run("class Circle {
+                     init(r) { this.r = r; }
+                     area { return 3 * this.r * this.r; }
+                     set diameter(d) { this.r = d / 2; }
+                     class unit() { return this(1); }
+                 }
+                 var c = Circle.unit();
+                 print c.area;
+                 print c.diameter = 4;
+                 print c.r;
+                 print c.area;
+                 print Circle.unit;"),
+            "3\n4\n2\n12\n<fn unit>\n"
+        );
+        assert_eq!(
+            run("class A { class f() {} }\nA().f;"),
+            "Undefined property 'f'.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A { f() {} }\nA.f;"),
+            "Undefined property 'f'.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A {}\nA.f = 1;"),
+            "Only instances have fields.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn instances_can_overload_operators() {
+        let vector = "class Vec {
+                          init(x, y) { this.x = x; this.y = y; }
+                          plus(other) { return Vec(this.x + other.x, this.y + other.y); }
+                          minus(other) { return Vec(this.x - other.x, this.y - other.y); }
+                          times(k) { return Vec(this.x * k, this.y * k); }
+                          divide(k) { return Vec(this.x / k, this.y / k); }
+                          compare(other) { return this.x * this.x + this.y * this.y
+                                                - other.x * other.x - other.y * other.y; }
+                          equals(other) { return this.x == other.x and this.y == other.y; }
+                          toString() { return \"(${this.x}, ${this.y})\"; }
+                      }
+                      var a = Vec(1, 2);
+                      var b = Vec(3, 4);";
+        assert_eq!(
+            run(&format!(
+                "{vector}
+                 print a + b;
+                 print (b - a) * 2 / 4;
+                 print [a < b, a <= a, a > b, b >= a];
+                 print [a == Vec(1, 2), a != Vec(1, 2), a == b];
+                 print \"a is ${{a}}\";
+                 print \"a is \" + a;
+                 switch (Vec(3, 4)) {{ case a: print \"a\"; case b: print \"b\"; }}"
+            )),
+            "(4, 6)\n(1, 1)\n[true, true, false, true]\n\
+             [true, false, false]\na is (1, 2)\na is (1, 2)\nb\n"
+        );
+    }
+
+    #[test]
+    fn instances_without_operator_methods_keep_the_usual_rules() {
+        assert_eq!(
+            run("class A {} var a = A();\nprint a == a; print a != A(); print a + \"!\";\na + 1;"),
+            "true\ntrue\nA instance!\nOperands must be two numbers or two strings.\n[line 3]"
+        );
+        assert_eq!(
+            run("class A { plus(x) {} }\nprint 1 + A();"),
+            "Operands must be two numbers or two strings.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn reports_misused_operator_methods() {
+        assert_eq!(
+            run("class A { plus() {} }\nA() + 1;"),
+            "Expected 0 arguments but got 1.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A { compare(x) { return \"less\"; } }\nA() < 1;"),
+            "Method 'compare' must return a number.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A {\n toString(x) {} }\nprint A();"),
+            "Expected 1 arguments but got 0.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn subclasses_inherit_and_override_members() {
+        assert_eq!(
+            run("class Animal {
+                     init(name) { this.name = name; }
+                     speak() { return this.name + \" makes a sound\"; }
+                     kind { return \"animal\"; }
+                     class create(name) { return this(name); }
+                 }
+                 class Dog < Animal {
+                     speak() { return this.name + \" barks\"; }
+                 }
+                 class Puppy < Dog {}
+                 var p = Puppy.create(\"Rex\");
+                 print p.speak();
+                 print p.kind;
+                 print p;"),
+            "Rex barks\nanimal\nPuppy instance\n"
+        );
+    }
+
+    #[test]
+    fn super_finds_members_above_the_declaring_class() {
+        assert_eq!(
+            run("class A { method() { print \"A method\"; } }
+                 class B < A {
+                     method() { print \"B method\"; }
+                     test() { super.method(); }
+                 }
+                 class C < B {}
+                 C().test();"),
+            "A method\n"
+        );
+        assert_eq!(
+            run("class A {
+                     init(x) { this.x = x; }
+                     size { return 1; }
+                     class make() { return \"A\"; }
+                 }
+                 class B < A {
+                     init(x) { super.init(x * 2); }
+                     size { return super.size + 1; }
+                     class make() { return super.make() + \"B\"; }
+                 }
+                 var b = B(2);
+                 print b.x;
+                 print b.size;
+                 print B.make();
+                 var init = b.init;
+                 print init(5) == b;"),
+            "4\n2\nAB\ntrue\n"
+        );
+    }
+
+    #[test]
+    fn reports_bad_inheritance() {
+        assert_eq!(
+            run("var NotAClass = \"so not a class\";\nclass A < NotAClass {}"),
+            "Superclass must be a class.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A {}\nclass B < A { f() { return super.missing; } }\nB().f();"),
+            "Undefined property 'missing'.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn reports_bad_property_access() {
+        assert_eq!(
+            run("class A {}\nA().missing;"),
+            "Undefined property 'missing'.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A {}\nA()?.missing;"),
+            "Undefined property 'missing'.\n[line 2]"
+        );
+        assert_eq!(
+            run("var a = 1;\na.x = -nil;"),
+            "Only instances have fields.\n[line 2]"
+        );
+        assert_eq!(
+            run("class A {}\nA(1);"),
+            "Expected 0 arguments but got 1.\n[line 2]"
+        );
+    }
+
+    #[test]
+    fn deep_recursion_is_a_runtime_error() {
+        let program = crate::check(
+            "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); }\n\
+             print count(49);\n\
+             print count(100000);",
+        )
+        .unwrap();
+        let output = Output::default();
+        let options = InterpreterOptions {
+            max_call_depth: 50,
+            ..InterpreterOptions::default()
+        };
+        let mut interpreter = Interpreter::with_output(output.clone()).with_options(options);
+        assert_eq!(
+            interpreter.interpret(&program).unwrap_err().to_string(),
+            "Stack overflow.\n[line 1]"
+        );
+        assert_eq!(output.0.take(), b"49\n");
+
+        // The calls that failed are no longer in progress.
+        let program = crate::check("print count(49);").unwrap();
+        interpreter.interpret(&program).unwrap();
+        assert_eq!(output.0.take(), b"49\n");
+    }
+
+    #[test]
+    fn the_default_call_depth_allows_deep_recursion() {
+        let printed = std::thread::Builder::new()
+            .stack_size(512 * 1024 * 1024)
+            .spawn(|| {
+                run(
+                    "fun count(n) { if (n == 0) return 0; return 1 + count(n - 1); }\n\
+                     print count(2000);",
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(printed, "2000\n");
+    }
+
+    #[test]
+    fn recursive_getters_and_setters_are_runtime_errors() {
+        let options = InterpreterOptions {
+            max_call_depth: 50,
+            ..InterpreterOptions::default()
+        };
+        for source in [
+            "class A { x { return this.x; } }\nprint A().x;",
+            "class A { set x(v) { this.x = v; } }\nA().x = 1;",
+            "class A { x { return this.x; } }\nclass B < A { y { return super.x; } }\nprint B().y;",
+        ] {
+            let program = crate::check(source).unwrap();
+            let mut interpreter =
+                Interpreter::with_output(io::sink()).with_options(options.clone());
+            let error = interpreter.interpret(&program).unwrap_err();
+            assert_eq!(error.message, "Stack overflow.", "{source}");
+        }
+    }
+
+    #[test]
+    fn calls_in_tail_position_take_no_call_depth() {
+        let program = crate::check(
+            "fun count(n, total) { if (n == 0) return total; return count(n - 1, total + 1); }\n\
+             fun even(n) { if (n == 0) return true; return odd(n - 1); }\n\
+             fun odd(n) { if (n == 0) return false; return even(n - 1); }\n\
+             class Counter {\n\
+               init(n) { this.n = n; }\n\
+               down() { if (this.n == 0) return \"done\"; this.n = this.n - 1; return this.down(); }\n\
+             }\n\
+             print count(10000, 0);\n\
+             print even(10001);\n\
+             print Counter(1000).down();\n\
+             fun make(n) { if (n == 0) return Counter(n); return make(n - 1); }\n\
+             print make(1000).n;\n\
+             fun wrong() { return count(1); }\n\
+             wrong();",
+        )
+        .unwrap();
+        let output = Output::default();
+        let options = InterpreterOptions {
+            max_call_depth: 50,
+            ..InterpreterOptions::default()
+        };
+        let mut interpreter = Interpreter::with_output(output.clone()).with_options(options);
+        assert_eq!(
+            interpreter.interpret(&program).unwrap_err().to_string(),
+            "Expected 2 arguments but got 1.\n[line 13]"
+        );
+        assert_eq!(output.0.take(), b"10000\nfalse\ndone\n0\n");
+    }
+
+    #[test]
+    fn tail_calls_see_their_own_closures() {
+        assert_eq!(
+            run("fun outer(x) {
+                   fun inner(y) { return x + y; }
+                   return inner(x * 10);
+                 }
+                 fun add(x) { fun f(y) { return x + y; } return f; }
+                 fun apply(f, n) { return f(n); }
+                 print outer(2);
+                 print apply(add(1), 2);"),
+            "22\n3\n"
+        );
+    }
+
+    #[test]
+    fn native_functions_run_rust_code() {
+        let program = crate::check(
+            "print double(21);\n\
+             print double;\n\
+             print double(\"a\");",
+        )
+        .unwrap();
+        let output = Output::default();
+        let mut interpreter = Interpreter::with_output(output.clone());
+        interpreter.define_native("double", 1, |interpreter, arguments| match arguments[0] {
+            Value::Number(n) => Ok(Value::Number(n * 2.0)),
+            _ => Err(interpreter.native_error("Argument must be a number.")),
+        });
+        let error = interpreter.interpret(&program).unwrap_err();
+        assert_eq!(error.to_string(), "Argument must be a number.\n[line 3]");
+        assert_eq!(output.0.take(), b"42\n<native fn>\n");
+
+        let program = crate::check("double(1, 2);").unwrap();
+        assert_eq!(
+            interpreter.interpret(&program).unwrap_err().to_string(),
+            "Expected 1 arguments but got 2.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn only_functions_can_be_called() {
+        assert_eq!(
+            run("\"f\"();"),
+            "Can only call functions and classes.\n[line 1]"
+        );
+        assert_eq!(run("nil(-nil);"), "Operand must be a number.\n[line 1]");
+    }
+
+    #[test]
+    fn stops_at_the_first_runtime_error() {
+        assert_eq!(
+            run("print 1;\nprint -nil;\nprint 2;"),
+            "1\nOperand must be a number.\n[line 2]"
+        );
+        assert_eq!(
+            run("{ nil.a; }\nprint 1;"),
+            "Only instances have properties.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn evaluates_arithmetic() {
+        assert_eq!(prints("1 + 2 * 3"), "7");
+        assert_eq!(prints("(1 + 2) * 3"), "9");
+        assert_eq!(prints("10 - 4 - 3"), "3");
+        assert_eq!(prints("7 / 2"), "3.5");
+        assert_eq!(prints("-(2 * 3)"), "-6");
+        assert_eq!(prints("- -1"), "1");
+        assert_eq!(prints("\"a\" + \"b\""), "ab");
+    }
+
+    #[test]
+    fn plus_converts_to_string_when_either_side_is_one() {
+        assert_eq!(prints("\"count: \" + 3"), "count: 3");
+        assert_eq!(prints("1.5 + \"x\""), "1.5x");
+        assert_eq!(prints("\"\" + nil + true"), "niltrue");
+        assert_eq!(prints("1 + 2 + \"3\""), "33");
+        assert_eq!(prints("\"a\" + [1, \"b\"]"), "a[1, \"b\"]");
+
+        let program = crate::check("print \"count: \" + 3;").unwrap();
+        let error = Interpreter::with_output(Output::default())
+            .with_options(InterpreterOptions::strict())
+            .interpret(&program)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Operands must be two numbers or two strings \
+             (string coercion is off under --strict).\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error_only_when_asked_for() {
+        assert_eq!(prints("1 / 0"), "Infinity");
+        assert_eq!(prints("-1 / 0"), "-Infinity");
+        assert_eq!(prints("0 / 0"), "NaN");
+
+        let program = crate::check("print 1 / 2;\nprint 1 / (1 - 1);").unwrap();
+        let output = Output::default();
+        let error = Interpreter::with_output(output.clone())
+            .with_options(InterpreterOptions::strict())
+            .interpret(&program)
+            .unwrap_err();
+        assert_eq!(output.0.take(), b"0.5\n");
+        assert_eq!(error.to_string(), "Division by zero.\n[line 2]");
+    }
+
+    #[test]
+    fn evaluates_modulo() {
+        let cases = [
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("-9223372036854775808 % -1", "0"),
+            ("1 % 0", "NaN"),
+            ("2 + 7 % 4 * 2", "8"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(prints(source), expected, "{source}");
+        }
+        for (source, expected) in [
+            ("\"7\" % 3", "Operands must be numbers."),
+            ("5.5 % 2", "Operands must be integers."),
+            ("5 % 0.5", "Operands must be integers."),
+            ("0 / 0 % 2", "Operands must be integers."),
+            ("1 / 0 % 2", "Operands must be integers."),
+        ] {
+            assert_eq!(error(source), format!("{expected}\n[line 1]"), "{source}");
+        }
+
+        let program = crate::check("print 7 % 2;\nprint 1 % 0;").unwrap();
+        let output = Output::default();
+        let error = Interpreter::with_output(output.clone())
+            .with_options(InterpreterOptions::strict())
+            .interpret(&program)
+            .unwrap_err();
+        assert_eq!(output.0.take(), b"1\n");
+        assert_eq!(error.to_string(), "Division by zero.\n[line 2]");
+    }
+
+    #[test]
+    fn evaluates_bitwise_operators() {
+        let cases = [
+            ("12 & 10", "8"),
+            ("12 | 10", "14"),
+            ("12 ^ 10", "6"),
+            ("~12", "-13"),
+            ("~-1", "0"),
+            ("-12 & 10", "0"),
+            ("-1 ^ 5", "-6"),
+            ("1 << 4", "16"),
+            ("1 << 63 == -9223372036854775808", "true"),
+            ("3 << 62 == -4611686018427387904", "true"),
+            ("-16 >> 2", "-4"),
+            ("16 >> 0", "16"),
+            ("-1 >> 63", "-1"),
+            ("4.0 & 6", "4"),
+            ("-0 | 0", "0"),
+            ("1 | 2 ^ 3 & 4 << 1", "3"),
+            ("1 + 1 << 2", "8"),
+            ("1 < 2 | 4", "true"),
+            ("-9223372036854775808 | 0 == -9223372036854775808", "true"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(prints(source), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn bitwise_operators_need_integers() {
+        let cases = [
+            ("1.5 & 1", "Operands must be integers."),
+            ("1 | 0.5", "Operands must be integers."),
+            ("(0 / 0) ^ 1", "Operands must be integers."),
+            ("1 / 0 & 1", "Operands must be integers."),
+            ("9223372036854775808 | 0", "Operands must be integers."),
+            ("1 << -1", "Shift amount must be from 0 to 63."),
+            ("1 >> 64", "Shift amount must be from 0 to 63."),
+            ("1 << 0.5", "Operands must be integers."),
+            ("~1.5", "Operand must be an integer."),
+            ("~(0 / 0)", "Operand must be an integer."),
+            ("~\"1\"", "Operand must be a number."),
+            ("true & 1", "Operands must be numbers."),
+            ("nil >> 1", "Operands must be numbers."),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(error(source), format!("{expected}\n[line 1]"), "{source}");
+        }
+    }
+
+    #[test]
+    fn evaluates_comparison_and_equality() {
+        assert_eq!(prints("1 < 2"), "true");
+        assert_eq!(prints("2 <= 1"), "false");
+        assert_eq!(prints("3 > 2 == true"), "true");
+        assert_eq!(prints("2 >= 2"), "true");
+        assert_eq!(prints("nil == nil"), "true");
+        assert_eq!(prints("1 == \"1\""), "false");
+        assert_eq!(prints("\"a\" != \"a\""), "false");
+        assert_eq!(prints("nil == false"), "false");
+    }
+
+    #[test]
+    fn compares_strings_lexicographically() {
+        assert_eq!(prints("\"apple\" < \"banana\""), "true");
+        assert_eq!(prints("\"b\" > \"abc\""), "true");
+        assert_eq!(prints("\"ab\" < \"abc\""), "true");
+        assert_eq!(prints("\"a\" <= \"a\""), "true");
+        assert_eq!(prints("\"a\" >= \"b\""), "false");
+        assert_eq!(prints("\"Z\" < \"a\""), "true");
+        assert_eq!(prints("\"\" < \"a\""), "true");
+        assert_eq!(error("\"1\" < 2"), "Operands must be numbers.\n[line 1]");
+        assert_eq!(error("1 >= \"2\""), "Operands must be numbers.\n[line 1]");
+    }
+
+    #[test]
+    fn comparisons_with_nan_are_false() {
+        assert_eq!(prints("0 / 0 < 1"), "false");
+        assert_eq!(prints("0 / 0 >= 1"), "false");
+        assert_eq!(prints("1 <= 0 / 0"), "false");
+    }
+
+    #[test]
+    fn negation_and_conditions_use_truthiness() {
+        assert_eq!(prints("!nil"), "true");
+        assert_eq!(prints("!false"), "true");
+        assert_eq!(prints("!0"), "false");
+        assert_eq!(prints("!\"\""), "false");
+        assert_eq!(prints("!![]"), "true");
+        assert_eq!(prints("0 ? 1 : 2"), "1");
+        assert_eq!(prints("nil ? 1 : 2"), "2");
+    }
+
+    #[test]
+    fn logical_operators_return_an_operand() {
+        assert_eq!(prints("nil or \"default\""), "default");
+        assert_eq!(prints("\"value\" or \"default\""), "value");
+        assert_eq!(prints("false or nil"), "nil");
+        assert_eq!(prints("1 and 2"), "2");
+        assert_eq!(prints("nil and 2"), "nil");
+        assert_eq!(prints("false and nil"), "false");
+        assert_eq!(prints("1 and nil or 3"), "3");
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        assert_eq!(prints("true or nil.x"), "true");
+        assert_eq!(prints("false and -\"a\""), "false");
+        assert_eq!(
+            error("false or nil.x"),
+            "Only instances have properties.\n[line 1]"
+        );
+        assert_eq!(
+            run("var a = 1; nil and (a = 2); true or (a = 3); print a;"),
+            "1\n"
+        );
+    }
+
+    #[test]
+    fn reports_operand_type_errors_at_the_operator() {
+        assert_eq!(error("-\"a\""), "Operand must be a number.\n[line 1]");
+        assert_eq!(error("1 < nil"), "Operands must be numbers.\n[line 1]");
+        assert_eq!(
+            error("1 +\nnil"),
+            "Operands must be two numbers or two strings.\n[line 1]"
+        );
+        assert_eq!(
+            error("1 * (2 -\ntrue)"),
+            "Operands must be numbers.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn evaluates_comma_and_interpolation() {
+        assert_eq!(prints("(1, 2, 3)"), "3");
+        assert_eq!(prints("\"x = ${1 + 1}, ${nil}!\""), "x = 2, nil!");
+        assert_eq!(prints("\"${[1, \"a\"]}\""), "[1, \"a\"]");
+    }
+
+    #[test]
+    fn evaluates_arrays_and_maps() {
+        assert_eq!(prints("[1, [2, 3]][1][0]"), "2");
+        assert_eq!(prints("{a: 1, \"b c\": [2]}"), "{\"a\": 1, \"b c\": [2]}");
+        assert_eq!(prints("{a: 1}[\"a\"]"), "1");
+        assert_eq!(prints("[1, 2][1] = 5"), "5");
+        assert_eq!(prints("[] == []"), "false");
+        assert_eq!(
+            error("[1][1]"),
+            "Array index 1 is out of bounds for length 1.\n[line 1]"
+        );
+        assert_eq!(
+            error("[1][0.5]"),
+            "Array index must be an integer.\n[line 1]"
+        );
+        assert_eq!(error("{}[\"a\"]"), "Undefined key \"a\".\n[line 1]");
+        assert_eq!(error("{}[1] = 2"), "Map key must be a string.\n[line 1]");
+        assert_eq!(
+            error("1[0]"),
+            "Only arrays and maps can be indexed.\n[line 1]"
+        );
+    }
+
+    #[test]
+    fn an_optional_chain_on_nil_is_nil() {
+        assert_eq!(prints("nil?.a.b(1)[2]"), "nil");
+        assert_eq!(error("1?.a"), "Only instances have properties.\n[line 1]");
+        assert_eq!(error("nil.a"), "Only instances have properties.\n[line 1]");
+    }
+}
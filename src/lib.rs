@@ -1,20 +1,194 @@
+use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::io::{self, BufRead, Write};
+use std::rc::Rc;
 
+pub mod arena;
+pub mod ast;
+pub mod ast_printer;
+pub mod callable;
+pub mod class;
+pub mod diagnostics;
+pub mod encoding;
+pub mod environment;
+pub mod function;
+pub mod heap;
 pub mod helpers;
+pub mod interpreter;
+pub mod native;
+pub mod parser;
+pub mod reader_scanner;
+pub mod relex;
+pub mod resolver;
 pub mod scanner;
+pub mod source_map;
+pub mod span;
+pub mod symbol;
 pub mod token;
 pub mod token_type;
+pub mod value;
 
-use scanner::Scanner;
+use ast::Stmt;
+use ast_printer::AstPrinter;
+use diagnostics::Diagnostics;
+use interpreter::{Interpreter, InterpreterOptions, RuntimeError};
+use parser::Parser;
+use reader_scanner::ReaderScanner;
+use resolver::{Locals, Resolver};
+use scanner::{ScanError, Scanner, ScannerOptions};
+use token::Token;
 
-pub fn run_file(path: &str) -> io::Result<()> {
-    let source = fs::read_to_string(path)?;
-    run(&source);
-    Ok(())
+/// Runs a script file, see [`run_reader`]. A file that is not UTF-8 fails
+/// with an `InvalidData` error wrapping an [`encoding::EncodingError`].
+pub fn run_file(
+    path: &str,
+    options: &ScannerOptions,
+    interpreter_options: &InterpreterOptions,
+) -> io::Result<Result<(), RunError>> {
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    encoding::check_source_start(&mut reader)?;
+    run_reader(reader, options, interpreter_options)
+}
+
+/// Checks and runs the script `reader` gives, like [`run`]. The script is
+/// scanned a line at a time with a [`ReaderScanner`], so its source is
+/// never held whole; a failure to read it is the outer error.
+pub fn run_reader(
+    reader: impl BufRead,
+    options: &ScannerOptions,
+    interpreter_options: &InterpreterOptions,
+) -> io::Result<Result<(), RunError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for result in ReaderScanner::with_options(reader, options.clone()) {
+        match result? {
+            Ok(token) => tokens.push(token),
+            Err(error) => errors.push(error),
+        }
+    }
+    let program = if errors.is_empty() {
+        check_tokens(tokens)
+    } else {
+        Err(Diagnostics::from(errors))
+    };
+    Ok(program.map_err(RunError::from).and_then(|program| {
+        let mut interpreter = Interpreter::new().with_options(interpreter_options.clone());
+        Ok(interpreter.interpret(&program)?)
+    }))
+}
+
+/// Why a program failed, see [`run`].
+#[derive(Debug)]
+pub enum RunError {
+    /// Scanning or parsing found problems, so nothing ran.
+    Static(Diagnostics),
+    /// The program stopped at a runtime error.
+    Runtime(RuntimeError),
+}
+
+impl RunError {
+    /// The status to exit with, as the reference implementation does:
+    /// 65 for static errors and 70 for runtime errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RunError::Static(_) => 65,
+            RunError::Runtime(_) => 70,
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::Static(diagnostics) => write!(f, "{diagnostics}"),
+            RunError::Runtime(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl Error for RunError {}
+
+impl From<Diagnostics> for RunError {
+    fn from(diagnostics: Diagnostics) -> Self {
+        RunError::Static(diagnostics)
+    }
+}
+
+impl From<RuntimeError> for RunError {
+    fn from(error: RuntimeError) -> Self {
+        RunError::Runtime(error)
+    }
 }
 
-pub fn run_prompt() -> io::Result<()> {
+/// Reads a script file as UTF-8, see [`encoding::decode_source`].
+pub fn read_source(path: &str) -> io::Result<String> {
+    encoding::decode_source(fs::read(path)?)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Scans `source` and renders the tokens as a pretty-printed JSON array,
+/// one object per token with its type, lexeme, literal, position and span.
+pub fn tokens_json(source: &str, options: &ScannerOptions) -> Result<String, Vec<ScanError>> {
+    let tokens = Scanner::new_with_options(source, options.clone()).scan_tokens()?;
+    Ok(serde_json::to_string_pretty(&tokens).expect("tokens serialize to JSON"))
+}
+
+/// A program that has been checked and is ready to run.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+    /// Where the program's local variable references were resolved to.
+    pub locals: Rc<Locals>,
+}
+
+/// Checks `source` without running it: scans, parses and resolves it and
+/// reports every problem found. Uses the default scanner options.
+pub fn check(source: &str) -> Result<Program, Diagnostics> {
+    check_with_options(source, &ScannerOptions::default())
+}
+
+/// Like [`check`], with the given scanner options.
+pub fn check_with_options(source: &str, options: &ScannerOptions) -> Result<Program, Diagnostics> {
+    let tokens = Scanner::new_with_options(source, options.clone()).scan_tokens()?;
+    check_tokens(tokens)
+}
+
+fn check_tokens(tokens: Vec<Token>) -> Result<Program, Diagnostics> {
+    resolve(Parser::new(tokens).parse()?)
+}
+
+/// Like [`check_with_options`], for a line typed at the REPL: a line that
+/// is a bare expression checks as a program that prints its value, see
+/// [`Parser::parse_repl_line`].
+pub fn check_line(source: &str, options: &ScannerOptions) -> Result<Program, Diagnostics> {
+    let tokens = Scanner::new_with_options(source, options.clone()).scan_tokens()?;
+    resolve(Parser::new(tokens).parse_repl_line()?)
+}
+
+fn resolve(statements: Vec<Stmt>) -> Result<Program, Diagnostics> {
+    let locals = Resolver::new().resolve(&statements)?;
+    Ok(Program {
+        statements,
+        locals: Rc::new(locals),
+    })
+}
+
+/// Checks `source` and renders the program with [`AstPrinter`], one
+/// statement per line.
+pub fn ast(source: &str, options: &ScannerOptions) -> Result<String, Diagnostics> {
+    let program = check_with_options(source, options)?;
+    Ok(AstPrinter.print(&program.statements))
+}
+
+/// Runs lines typed at a `> ` prompt until end of input. The lines share
+/// one interpreter, so what one line declares the next can use, and the
+/// value of a line that is a bare expression is printed.
+pub fn run_prompt(
+    options: &ScannerOptions,
+    interpreter_options: &InterpreterOptions,
+) -> io::Result<()> {
+    let mut interpreter = Interpreter::new().with_options(interpreter_options.clone());
     let stdin = io::stdin();
     let mut stdout = io::stdout();
     let mut reader = stdin.lock();
@@ -30,18 +204,58 @@ pub fn run_prompt() -> io::Result<()> {
         }
 
         let line = line.trim_end_matches(&['\n', '\r'][..]);
-        run(line);
+        let result = check_line(line, options)
+            .map_err(RunError::from)
+            .and_then(|program| Ok(interpreter.interpret(&program)?));
+        if let Err(error) = result {
+            eprintln!("{error}");
+        }
     }
 
     Ok(())
 }
 
-pub fn run(source: &str) {
-    let tokens = Scanner::new(source).scan_tokens();
-
-    for token in tokens {
-        println!("{token:?}");
+/// Scans arbitrary bytes and returns how many tokens and errors were
+/// produced. Invalid UTF-8 is replaced with U+FFFD so every input reaches
+/// the scanner. This is the fuzzing entry point: it must never panic, and
+/// with debug assertions on it also checks that every token span is a
+/// valid slice of the source holding the token's lexeme.
+pub fn scan_bytes(data: &[u8]) -> usize {
+    let source = String::from_utf8_lossy(data);
+    let mut count = 0;
+    for result in Scanner::new(&source).borrowed_tokens() {
+        if let Ok(token) = result {
+            debug_assert_eq!(
+                source.get(token.span.start..token.span.end),
+                Some(token.lexeme)
+            );
+        }
+        count += 1;
     }
+    count
+}
+
+/// Checks and runs `source` in a new interpreter. Nothing runs if
+/// checking finds problems, and running stops at the first runtime error.
+pub fn run(
+    source: &str,
+    options: &ScannerOptions,
+    interpreter_options: &InterpreterOptions,
+) -> Result<(), RunError> {
+    let mut interpreter = Interpreter::new().with_options(interpreter_options.clone());
+    run_in(&mut interpreter, source, options)
+}
+
+/// Like [`run`], in `interpreter`, where the globals earlier programs
+/// declared are still declared.
+pub fn run_in(
+    interpreter: &mut Interpreter,
+    source: &str,
+    options: &ScannerOptions,
+) -> Result<(), RunError> {
+    let program = check_with_options(source, options)?;
+    interpreter.interpret(&program)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -50,11 +264,171 @@ mod tests {
 
     #[test]
     fn run_does_not_panic_on_empty() {
-        run("");
+        run(
+            "",
+            &ScannerOptions::default(),
+            &InterpreterOptions::default(),
+        )
+        .unwrap();
     }
 
     #[test]
     fn run_does_not_panic_on_simple_source() {
-        run("print 123;");
+        run(
+            "print 123;",
+            &ScannerOptions::default(),
+            &InterpreterOptions::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn run_does_not_panic_on_scan_errors() {
+        run(
+            "@ \"unterminated",
+            &ScannerOptions::default(),
+            &InterpreterOptions::default(),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn scan_bytes_survives_malformed_input() {
+        let inputs: &[&[u8]] = &[
+            b"",
+            b"\xFF\xFE\xFD",
+            b"\"caf\xC3",
+            b"\"${\"${\"${",
+            b"\"\\u{110000}\\u{\\u",
+            b"/* /* \xE2\x82",
+            b"0x_ 0b2 1__0 1._ .5e",
+            b"\"\"\"\n\t\"\"",
+            b"///\n/// \xF0",
+            b"#!\xC0",
+        ];
+        for input in inputs {
+            assert!(scan_bytes(input) > 0);
+        }
+    }
+
+    #[test]
+    fn tokens_json_describes_each_token() {
+        let json: serde_json::Value = serde_json::from_str(
+            &tokens_json("var s = \"hi\";\nprint 1.5;", &ScannerOptions::default()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json[3],
+            serde_json::json!({
+                "token_type": "String",
+                "lexeme": "\"hi\"",
+                "literal": "hi",
+                "line": 1,
+                "column": 9,
+                "end_column": 13,
+                "span": { "start": 8, "end": 12 },
+            })
+        );
+        assert_eq!(json[6]["literal"], 1.5);
+        assert_eq!(json[6]["line"], 2);
+        assert_eq!(json[8]["token_type"], "Eof");
+    }
+
+    #[test]
+    fn tokens_json_reports_scan_errors() {
+        let errors = tokens_json("print @;", &ScannerOptions::default()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn check_line_prints_bare_expressions() {
+        let program = check_line("1 + 2", &ScannerOptions::default()).unwrap();
+        assert_eq!(AstPrinter.print(&program.statements), "(print (+ 1 2))");
+        check_with_options("1 + 2", &ScannerOptions::default()).unwrap_err();
+    }
+
+    #[test]
+    fn ast_prints_each_statement() {
+        let printed = ast("var a = -1;\nprint a * (2);", &ScannerOptions::default()).unwrap();
+        assert_eq!(printed, "(var a (- 1))\n(print (* a (group 2)))");
+    }
+
+    #[test]
+    fn ast_reports_scan_and_parse_errors() {
+        let options = ScannerOptions::default();
+        let errors = ast("print @;", &options).unwrap_err();
+        assert_eq!(
+            errors.to_string(),
+            "[line 1] Error: Unexpected character '@'."
+        );
+        let errors = ast("print 1", &options).unwrap_err();
+        assert_eq!(
+            errors.to_string(),
+            "[line 1] Error at end: Expect ';' after print statement."
+        );
+    }
+
+    #[test]
+    fn check_parses_without_running() {
+        let program = check("print 1;\nvar a = 2;").unwrap();
+        assert_eq!(program.statements.len(), 2);
+
+        let diagnostics = check("print 1").unwrap_err();
+        assert_eq!(
+            diagnostics.to_string(),
+            "[line 1] Error at end: Expect ';' after print statement."
+        );
+    }
+
+    #[test]
+    fn run_reports_static_and_runtime_errors() {
+        let options = ScannerOptions::default();
+        let interpreter_options = InterpreterOptions::default();
+        assert!(run("var a = 1;", &options, &interpreter_options).is_ok());
+
+        let error = run("var a = ;\n-nil;", &options, &interpreter_options).unwrap_err();
+        assert!(matches!(error, RunError::Static(_)));
+        assert_eq!(error.exit_code(), 65);
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at ';': Expect expression."
+        );
+
+        let error = run("var a = 1;\n-nil;", &options, &interpreter_options).unwrap_err();
+        assert!(matches!(error, RunError::Runtime(_)));
+        assert_eq!(error.exit_code(), 70);
+        assert_eq!(error.to_string(), "Operand must be a number.\n[line 2]");
+    }
+
+    #[test]
+    fn run_reader_fails_as_run_does() {
+        let options = ScannerOptions::default();
+        let interpreter_options = InterpreterOptions::default();
+        for source in ["print \"a\nb\" +;", "/* open\n", "print 1;\n-nil;"] {
+            let read = run_reader(source.as_bytes(), &options, &interpreter_options).unwrap();
+            let whole = run(source, &options, &interpreter_options);
+            assert_eq!(
+                read.unwrap_err().to_string(),
+                whole.unwrap_err().to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn run_file_rejects_invalid_utf8() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"print \"\xFF\";").unwrap();
+
+        let error = run_file(
+            file.path().to_str().unwrap(),
+            &ScannerOptions::default(),
+            &InterpreterOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "file is not valid UTF-8 (invalid byte at offset 7)"
+        );
     }
 }
@@ -1,16 +1,141 @@
+use rlox::interpreter::InterpreterOptions;
+use rlox::scanner::ScannerOptions;
 use std::process;
 
+const USAGE: &str = "Usage: rlox [--tokens-json | --ast | --check] [--crafting-interpreters] \
+                     [--no-nested-comments] [--strict] [--max-call-depth=N] [script | -]";
+
+// The stack the interpreter runs on, which `DEFAULT_MAX_CALL_DEPTH` calls
+// fit in with room to spare, see there.
+const STACK_SIZE: usize = 512 * 1024 * 1024;
+
+// What to do with a script instead of running it.
+enum Dump {
+    TokensJson,
+    Ast,
+    Check,
+}
+
 fn main() -> std::io::Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
 
-    if args.len() > 2 {
-        println!("Usage: rlox [script]");
-        process::exit(64);
-    } else if args.len() == 2 {
-        rlox::run_file(&args[1])?;
+fn run() -> std::io::Result<()> {
+    let mut crafting_interpreters = false;
+    let mut nested_comments = true;
+    let mut strict = false;
+    let mut max_call_depth = None;
+    let mut dump = None;
+    let mut script = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--tokens-json" => dump = Some(Dump::TokensJson),
+            "--ast" => dump = Some(Dump::Ast),
+            "--check" => dump = Some(Dump::Check),
+            "--crafting-interpreters" => crafting_interpreters = true,
+            "--no-nested-comments" => nested_comments = false,
+            "--strict" => strict = true,
+            _ if arg.starts_with("--max-call-depth=") => {
+                let depth = &arg["--max-call-depth=".len()..];
+                max_call_depth = Some(depth.parse().unwrap_or_else(|_| usage()));
+            }
+            _ if script.is_none() && !arg.starts_with("--") => script = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    // The dialect is the base that the single switches change, whichever
+    // order they were given in.
+    let mut options = if crafting_interpreters {
+        ScannerOptions::crafting_interpreters()
+    } else {
+        ScannerOptions::default()
+    };
+    if !nested_comments {
+        options.nested_comments = false;
+    }
+    let mut interpreter_options = if strict {
+        InterpreterOptions::strict()
     } else {
-        rlox::run_prompt()?;
+        InterpreterOptions::default()
+    };
+    if let Some(max_call_depth) = max_call_depth {
+        interpreter_options.max_call_depth = max_call_depth;
+    }
+
+    match (script, dump) {
+        (Some(path), None) => match run_script(&path, &options, &interpreter_options) {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                eprintln!("{error}");
+                process::exit(error.exit_code());
+            }
+            Err(error) => exit_on_io_error(&path, error),
+        },
+        (Some(path), Some(Dump::TokensJson)) => {
+            let source = rlox::read_source(&path).unwrap_or_else(|e| exit_on_io_error(&path, e));
+            match rlox::tokens_json(&source, &options) {
+                Ok(json) => println!("{json}"),
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("{error}");
+                    }
+                    process::exit(65);
+                }
+            }
+        }
+        (Some(path), Some(Dump::Ast)) => {
+            let source = rlox::read_source(&path).unwrap_or_else(|e| exit_on_io_error(&path, e));
+            match rlox::ast(&source, &options) {
+                Ok(printed) => println!("{printed}"),
+                Err(diagnostics) => {
+                    eprintln!("{diagnostics}");
+                    process::exit(65);
+                }
+            }
+        }
+        (Some(path), Some(Dump::Check)) => {
+            let source = rlox::read_source(&path).unwrap_or_else(|e| exit_on_io_error(&path, e));
+            if let Err(diagnostics) = rlox::check_with_options(&source, &options) {
+                eprintln!("{diagnostics}");
+                process::exit(65);
+            }
+        }
+        (None, None) => rlox::run_prompt(&options, &interpreter_options)?,
+        (None, Some(_)) => usage(),
     }
 
     Ok(())
 }
+
+// Runs the script at `path`, or the one piped to stdin if `path` is `-`.
+fn run_script(
+    path: &str,
+    options: &ScannerOptions,
+    interpreter_options: &InterpreterOptions,
+) -> std::io::Result<Result<(), rlox::RunError>> {
+    if path != "-" {
+        return rlox::run_file(path, options, interpreter_options);
+    }
+    let mut stdin = std::io::stdin().lock();
+    rlox::encoding::check_source_start(&mut stdin)?;
+    rlox::run_reader(stdin, options, interpreter_options)
+}
+
+fn usage() -> ! {
+    println!("{USAGE}");
+    process::exit(64);
+}
+
+fn exit_on_io_error(path: &str, error: std::io::Error) -> ! {
+    eprintln!("Error: {path}: {error}");
+    let code = match error.kind() {
+        std::io::ErrorKind::InvalidData => 65,
+        _ => 66,
+    };
+    process::exit(code);
+}
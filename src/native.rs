@@ -0,0 +1,367 @@
+use crate::callable::LoxCallable;
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a native function runs when called: Rust code given the
+/// interpreter and the call's arguments, as many as its arity. Its errors
+/// are made with [`Interpreter::native_error`].
+pub type NativeFn = dyn Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError>;
+
+/// A function written in Rust that a Lox program calls like any other.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: impl Into<String>,
+        arity: usize,
+        function: impl Fn(&mut Interpreter, &[Value]) -> Result<Value, RuntimeError> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(interpreter, &arguments)
+    }
+
+    fn as_native(&self) -> Option<&NativeFunction> {
+        Some(self)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Printed as jlox prints its natives, whatever the name.
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<native fn>")
+    }
+}
+
+/// Declares the globals every program starts with: `clock()`, and the
+/// math functions `sqrt`, `abs`, `floor`, `ceil`, `pow`, `min`, `max`,
+/// `sin`, `cos` and `log`, with the constants `PI` and `E`.
+///
+/// The math functions take numbers and work as Rust's `f64` methods do:
+/// angles are in radians, `log` is the natural logarithm, and a result
+/// that isn't a real number, such as `sqrt(-1)`, is NaN.
+///
+/// The string functions are `len`, `substring`, `upper`, `lower`, `trim`,
+/// `split`, `contains`, `indexOf` and `charAt`, see [`define_strings`].
+pub fn define_globals(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+
+    define_unary(interpreter, "sqrt", f64::sqrt);
+    define_unary(interpreter, "abs", f64::abs);
+    define_unary(interpreter, "floor", f64::floor);
+    define_unary(interpreter, "ceil", f64::ceil);
+    define_unary(interpreter, "sin", f64::sin);
+    define_unary(interpreter, "cos", f64::cos);
+    define_unary(interpreter, "log", f64::ln);
+    define_binary(interpreter, "pow", f64::powf);
+    define_binary(interpreter, "min", f64::min);
+    define_binary(interpreter, "max", f64::max);
+    interpreter.define_global("PI", Value::Number(std::f64::consts::PI));
+    interpreter.define_global("E", Value::Number(std::f64::consts::E));
+
+    define_strings(interpreter);
+}
+
+/// Declares the string functions. Strings are indexed by character, as
+/// the characters of a string are compared, rather than by byte:
+///
+/// - `len(s)` is how many characters `s` has, or how many elements or
+///   entries an array or map has.
+/// - `substring(s, start, end)` is the characters of `s` from `start` up
+///   to but not including `end`.
+/// - `upper(s)`, `lower(s)` and `trim(s)` are `s` in upper or lower case,
+///   or without whitespace at either end.
+/// - `split(s, separator)` is an array of the parts of `s` between each
+///   `separator`, or of its characters if `separator` is empty.
+/// - `contains(s, part)` is whether `part` is in `s`, and `indexOf(s,
+///   part)` where it first is, or -1 if it isn't.
+/// - `charAt(s, index)` is the one-character string at `index`.
+///
+/// An index must be an integer from 0 to the length of `s`, and only
+/// `end` may be the length itself.
+pub fn define_strings(interpreter: &mut Interpreter) {
+    interpreter.define_native("len", 1, |interpreter, arguments| {
+        let len = match &arguments[0] {
+            Value::String(s) => s.chars().count(),
+            Value::Array(elements) => elements.borrow().len(),
+            Value::Map(entries) => entries.borrow().len(),
+            _ => {
+                return Err(interpreter.native_error("Argument must be a string, array or map."));
+            }
+        };
+        Ok(Value::Number(len as f64))
+    });
+    interpreter.define_native("substring", 3, |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        let len = s.chars().count();
+        let start = index(interpreter, &arguments[1], len + 1)?;
+        let end = index(interpreter, &arguments[2], len + 1)?;
+        if end < start {
+            return Err(interpreter.native_error("Substring can't end before it starts."));
+        }
+        Ok(Value::String(
+            s.chars().skip(start).take(end - start).collect(),
+        ))
+    });
+    define_string_map(interpreter, "upper", str::to_uppercase);
+    define_string_map(interpreter, "lower", str::to_lowercase);
+    define_string_map(interpreter, "trim", |s| s.trim().to_string());
+    interpreter.define_native("split", 2, |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        let separator = string(interpreter, &arguments[1])?;
+        let parts = if separator.is_empty() {
+            s.chars().map(|c| Value::String(c.to_string())).collect()
+        } else {
+            s.split(separator)
+                .map(|part| Value::String(part.to_string()))
+                .collect()
+        };
+        let array = Rc::new(RefCell::new(parts));
+        interpreter.heap().track_array(&array);
+        Ok(Value::Array(array))
+    });
+    interpreter.define_native("contains", 2, |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        let part = string(interpreter, &arguments[1])?;
+        Ok(Value::Bool(s.contains(part)))
+    });
+    interpreter.define_native("indexOf", 2, |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        let part = string(interpreter, &arguments[1])?;
+        let index = match s.find(part) {
+            Some(byte) => s[..byte].chars().count() as f64,
+            None => -1.0,
+        };
+        Ok(Value::Number(index))
+    });
+    interpreter.define_native("charAt", 2, |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        let index = index(interpreter, &arguments[1], s.chars().count())?;
+        let c = s.chars().nth(index).expect("the index is in bounds");
+        Ok(Value::String(c.to_string()))
+    });
+}
+
+fn define_string_map(interpreter: &mut Interpreter, name: &str, function: fn(&str) -> String) {
+    interpreter.define_native(name, 1, move |interpreter, arguments| {
+        let s = string(interpreter, &arguments[0])?;
+        Ok(Value::String(function(s)))
+    });
+}
+
+// An argument that must be a string.
+fn string<'a>(interpreter: &Interpreter, argument: &'a Value) -> Result<&'a str, RuntimeError> {
+    match argument {
+        Value::String(s) => Ok(s),
+        _ => Err(interpreter.native_error("Argument must be a string.")),
+    }
+}
+
+// An argument that must be an index below `bound`.
+fn index(interpreter: &Interpreter, argument: &Value, bound: usize) -> Result<usize, RuntimeError> {
+    let Value::Number(n) = *argument else {
+        return Err(interpreter.native_error("Index must be a number."));
+    };
+    if n.fract() != 0.0 {
+        return Err(interpreter.native_error("Index must be an integer."));
+    }
+    if n < 0.0 || n >= bound as f64 {
+        return Err(interpreter.native_error(format!(
+            "Index {} is out of bounds.",
+            Value::format_number(n)
+        )));
+    }
+    Ok(n as usize)
+}
+
+fn define_unary(interpreter: &mut Interpreter, name: &str, function: fn(f64) -> f64) {
+    interpreter.define_native(name, 1, move |interpreter, arguments| {
+        let [n] = numbers(interpreter, arguments)?;
+        Ok(Value::Number(function(n)))
+    });
+}
+
+fn define_binary(interpreter: &mut Interpreter, name: &str, function: fn(f64, f64) -> f64) {
+    interpreter.define_native(name, 2, move |interpreter, arguments| {
+        let [a, b] = numbers(interpreter, arguments)?;
+        Ok(Value::Number(function(a, b)))
+    });
+}
+
+// The arguments of a native function that takes `N` numbers.
+fn numbers<const N: usize>(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+) -> Result<[f64; N], RuntimeError> {
+    let mut numbers = [0.0; N];
+    for (number, argument) in numbers.iter_mut().zip(arguments) {
+        let Value::Number(n) = argument else {
+            let message = if N == 1 {
+                "Argument must be a number."
+            } else {
+                "Arguments must be numbers."
+            };
+            return Err(interpreter.native_error(message));
+        };
+        *number = *n;
+    }
+    Ok(numbers)
+}
+
+/// `clock()`: the seconds since the Unix epoch, with a fractional part, for
+/// timing code by subtracting one reading from another.
+pub fn clock(_interpreter: &mut Interpreter, _arguments: &[Value]) -> Result<Value, RuntimeError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(Value::Number(elapsed.as_secs_f64()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> Value {
+        let program = crate::check(&format!("print {source};")).expect("source parses");
+        let [crate::ast::Stmt::Print { expression, .. }] = program.statements.as_slice() else {
+            panic!("expected one print statement");
+        };
+        Interpreter::new().evaluate(expression).unwrap()
+    }
+
+    #[test]
+    fn clock_gives_the_seconds_since_the_epoch() {
+        let Value::Number(seconds) = evaluate("clock()") else {
+            panic!("clock() should give a number");
+        };
+        // Some time after this was written, in September 2001 or later.
+        assert!(seconds > 1e9);
+        assert_eq!(evaluate("clock() - clock() <= 0"), Value::Bool(true));
+        assert_eq!(evaluate("clock").to_string(), "<native fn>");
+    }
+
+    #[test]
+    fn math_functions_work_on_numbers() {
+        for (source, expected) in [
+            ("sqrt(16)", "4"),
+            ("sqrt(-1)", "NaN"),
+            ("abs(-2.5)", "2.5"),
+            ("floor(-1.5)", "-2"),
+            ("ceil(1.2)", "2"),
+            ("pow(2, 10)", "1024"),
+            ("pow(4, 0.5)", "2"),
+            ("min(3, -1)", "-1"),
+            ("max(3, -1)", "3"),
+            ("sin(0)", "0"),
+            ("cos(PI)", "-1"),
+            ("log(E)", "1"),
+            ("log(0)", "-Infinity"),
+            ("floor(PI * 100) / 100", "3.14"),
+        ] {
+            assert_eq!(evaluate(source).to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn string_functions_index_by_character() {
+        for (source, expected) in [
+            ("len(\"\")", "0"),
+            ("len(\"héllo\")", "5"),
+            ("len([1, 2, 3])", "3"),
+            ("len({\"a\": 1})", "1"),
+            ("substring(\"héllo\", 1, 3)", "él"),
+            ("substring(\"abc\", 0, 3)", "abc"),
+            ("substring(\"abc\", 3, 3)", ""),
+            ("upper(\"straße\")", "STRASSE"),
+            ("lower(\"ABC\")", "abc"),
+            ("trim(\"  a b \\n\")", "a b"),
+            ("split(\"a,b,,c\", \",\")", "[\"a\", \"b\", \"\", \"c\"]"),
+            ("split(\"héy\", \"\")", "[\"h\", \"é\", \"y\"]"),
+            ("split(\"\", \",\")", "[\"\"]"),
+            ("contains(\"hello\", \"ell\")", "true"),
+            ("contains(\"hello\", \"\")", "true"),
+            ("contains(\"hello\", \"L\")", "false"),
+            ("indexOf(\"héllo\", \"l\")", "2"),
+            ("indexOf(\"hello\", \"z\")", "-1"),
+            ("charAt(\"héllo\", 1)", "é"),
+        ] {
+            assert_eq!(evaluate(source).to_string(), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn string_functions_check_their_arguments() {
+        for (source, message) in [
+            ("len(1)", "Argument must be a string, array or map."),
+            ("upper(nil)", "Argument must be a string."),
+            ("contains(\"a\", 1)", "Argument must be a string."),
+            ("substring(\"abc\", \"0\", 1)", "Index must be a number."),
+            ("substring(\"abc\", 0.5, 1)", "Index must be an integer."),
+            ("substring(\"abc\", 0, 4)", "Index 4 is out of bounds."),
+            (
+                "substring(\"abc\", 2, 1)",
+                "Substring can't end before it starts.",
+            ),
+            ("charAt(\"abc\", 3)", "Index 3 is out of bounds."),
+            ("charAt(\"abc\", -1)", "Index -1 is out of bounds."),
+        ] {
+            let program = crate::check(&format!("print {source};")).unwrap();
+            let error = Interpreter::with_output(std::io::sink())
+                .interpret(&program)
+                .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("{message}\n[line 1]"),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn math_functions_need_numbers() {
+        for (source, message) in [
+            ("sqrt(\"4\")", "Argument must be a number."),
+            ("pow(2, nil)", "Arguments must be numbers."),
+            ("max(true, 1)", "Arguments must be numbers."),
+        ] {
+            let program = crate::check(&format!("print {source};")).unwrap();
+            let error = Interpreter::with_output(std::io::sink())
+                .interpret(&program)
+                .unwrap_err();
+            assert_eq!(error.to_string(), format!("{message}\n[line 1]"));
+        }
+    }
+}
@@ -0,0 +1,2478 @@
+use crate::ast::{Expr, Stmt};
+use crate::span::Span;
+use crate::token::{Literal, Token};
+use crate::token_type::TokenType;
+use crate::value::Value;
+use std::fmt;
+
+/// A syntax error, reported at the token where parsing went wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The offending token; an `Eof` token if the source ended too soon.
+    pub token: Box<Token>,
+    /// What the parser was looking for, or `None` if the error is about
+    /// what it found instead, such as a call with too many arguments.
+    pub expected: Option<Expected>,
+    pub message: String,
+    /// Where `token` is in the source. Empty at the end of the source.
+    pub span: Span,
+}
+
+/// What the parser expected to see where a [`ParseError`] occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    /// A token of this type, such as the `;` that ends a statement or the
+    /// identifier naming a variable.
+    Token(TokenType),
+    /// The start of an expression.
+    Expression,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.token.token_type == TokenType::Eof {
+            write!(
+                f,
+                "[line {}] Error at end: {}",
+                self.token.line, self.message
+            )
+        } else {
+            write!(
+                f,
+                "[line {}] Error at '{}': {}",
+                self.token.line, self.token.lexeme, self.message
+            )
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A recursive-descent parser over the scanner's tokens. Each statement
+/// rule is a method; expressions are parsed by precedence climbing (Pratt
+/// parsing) over the table in `rule`, which gives the precedence and
+/// associativity the expression rules below spell out:
+///
+/// ```text
+/// program    → declaration* EOF ;
+/// declaration → classDecl | funDecl | varDecl | statement ;
+/// classDecl  → "class" IDENTIFIER ( "<" IDENTIFIER )? "{" member* "}" ;
+/// member     → "class"? function | IDENTIFIER block | "set" function ;
+/// funDecl    → "fun" function ;
+/// function   → IDENTIFIER "(" parameters? ")" block ;
+/// parameters → IDENTIFIER ( "," IDENTIFIER )* ","? ;
+/// varDecl    → "var" IDENTIFIER ( "=" assignment )? ";" ;
+/// statement  → exprStmt | forStmt | ifStmt | printStmt | returnStmt
+///            | whileStmt | doWhileStmt | breakStmt | continueStmt
+///            | switchStmt | block ;
+/// exprStmt   → expression ";" ;
+/// forStmt    → "for" "(" ( varDecl | exprStmt | ";" ) expression? ";"
+///              expression? ")" statement ;
+/// ifStmt     → "if" "(" expression ")" statement ( "else" statement )? ;
+/// printStmt  → "print" expression ";" ;
+/// returnStmt → "return" expression? ";" ;
+/// whileStmt  → "while" "(" expression ")" statement ;
+/// doWhileStmt → "do" statement "while" "(" expression ")" ";" ;
+/// breakStmt  → "break" ";" ;
+/// continueStmt → "continue" ";" ;
+/// switchStmt → "switch" "(" expression ")" "{" case* default? "}" ;
+/// case       → "case" arguments ":" declaration* ;
+/// default    → "default" ":" declaration* ;
+/// block      → "{" declaration* "}" ;
+///
+/// expression → comma ;
+/// comma      → assignment ( "," assignment )* ;
+/// assignment → ( call "." IDENTIFIER | call "[" expression "]"
+///              | IDENTIFIER ) "=" assignment
+///            | conditional ;
+/// conditional → logic_or ( "?" expression ":" conditional )? ;
+/// logic_or   → logic_and ( "or" logic_and )* ;
+/// logic_and  → equality ( "and" equality )* ;
+/// equality   → comparison ( ( "!=" | "==" ) comparison )* ;
+/// comparison → bit_or ( ( ">" | ">=" | "<" | "<=" ) bit_or )* ;
+/// bit_or     → bit_xor ( "|" bit_xor )* ;
+/// bit_xor    → bit_and ( "^" bit_and )* ;
+/// bit_and    → shift ( "&" shift )* ;
+/// shift      → term ( ( "<<" | ">>" ) term )* ;
+/// term       → factor ( ( "-" | "+" ) factor )* ;
+/// factor     → unary ( ( "/" | "*" | "%" ) unary )* ;
+/// unary      → ( "!" | "-" | "~" ) unary | call ;
+/// call       → primary ( "(" arguments? ")" | "." IDENTIFIER
+///              | "?." IDENTIFIER | "[" expression "]" )* ;
+/// arguments  → assignment ( "," assignment )* ","? ;
+/// primary    → NUMBER | STRING | "true" | "false" | "nil"
+///            | "(" expression ")" | "[" arguments? "]" | map | interpolation
+///            | IDENTIFIER | "this" | "super" "." IDENTIFIER ;
+/// map        → "{" ( entry ( "," entry )* ","? )? "}" ;
+/// entry      → ( STRING | IDENTIFIER ) ":" assignment ;
+/// interpolation → STRING_PART ( "${" expression "}" STRING_PART )+ ;
+/// ```
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    // Every error found so far; the parse carries on past each one.
+    errors: Vec<ParseError>,
+    // How many nested expressions and statements are being parsed, and how
+    // many are allowed before the Rust stack is at risk.
+    depth: usize,
+    max_depth: usize,
+    // How many loops and switches enclose the statement being parsed,
+    // within the innermost function. `continue` needs a loop, and `break`
+    // a loop or a switch.
+    loop_depth: usize,
+    switch_depth: usize,
+    // Whether the innermost function is a class's `init` method, which
+    // can't return a value.
+    in_initializer: bool,
+}
+
+/// How deeply expressions and statements may nest by default.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// How tightly an infix operator binds, from loosest to tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    None,
+    Comma,
+    Assignment,
+    Conditional,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Term,
+    Factor,
+    Unary,
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    // The next tighter level.
+    fn next(self) -> Self {
+        match self {
+            Precedence::None => Precedence::Comma,
+            Precedence::Comma => Precedence::Assignment,
+            Precedence::Assignment => Precedence::Conditional,
+            Precedence::Conditional => Precedence::Or,
+            Precedence::Or => Precedence::And,
+            Precedence::And => Precedence::Equality,
+            Precedence::Equality => Precedence::Comparison,
+            Precedence::Comparison => Precedence::BitOr,
+            Precedence::BitOr => Precedence::BitXor,
+            Precedence::BitXor => Precedence::BitAnd,
+            Precedence::BitAnd => Precedence::Shift,
+            Precedence::Shift => Precedence::Term,
+            Precedence::Term => Precedence::Factor,
+            Precedence::Factor => Precedence::Unary,
+            Precedence::Unary => Precedence::Call,
+            Precedence::Call | Precedence::Primary => Precedence::Primary,
+        }
+    }
+}
+
+// Parses an expression starting with the token just consumed.
+type PrefixFn = fn(&mut Parser) -> Result<Expr, ParseError>;
+// Parses the rest of an expression whose left operand has been parsed and
+// whose operator was just consumed.
+type InfixFn = fn(&mut Parser, Expr) -> Result<Expr, ParseError>;
+
+struct ParseRule {
+    prefix: Option<PrefixFn>,
+    infix: Option<InfixFn>,
+    precedence: Precedence,
+}
+
+// The Pratt table: how a token parses at the start of an expression, how
+// it parses after a complete operand, and how tightly it binds there. A
+// new operator is a new row here.
+fn rule(token_type: TokenType) -> ParseRule {
+    let (prefix, infix, precedence): (Option<PrefixFn>, Option<InfixFn>, _) = match token_type {
+        TokenType::LeftParen => (Some(Parser::grouping), Some(Parser::call), Precedence::Call),
+        TokenType::Dot => (None, Some(Parser::dot), Precedence::Call),
+        TokenType::QuestionDot => (None, Some(Parser::optional_chain), Precedence::Call),
+        TokenType::LeftBracket => (Some(Parser::array), Some(Parser::index), Precedence::Call),
+        TokenType::LeftBrace => (Some(Parser::map), None, Precedence::None),
+        TokenType::Minus => (Some(Parser::unary), Some(Parser::binary), Precedence::Term),
+        TokenType::Plus => (None, Some(Parser::binary), Precedence::Term),
+        TokenType::Slash | TokenType::Star | TokenType::Percent => {
+            (None, Some(Parser::binary), Precedence::Factor)
+        }
+        TokenType::LessLess | TokenType::GreaterGreater => {
+            (None, Some(Parser::binary), Precedence::Shift)
+        }
+        TokenType::Ampersand => (None, Some(Parser::binary), Precedence::BitAnd),
+        TokenType::Caret => (None, Some(Parser::binary), Precedence::BitXor),
+        TokenType::Pipe => (None, Some(Parser::binary), Precedence::BitOr),
+        TokenType::Bang | TokenType::Tilde => (Some(Parser::unary), None, Precedence::None),
+        TokenType::BangEqual | TokenType::EqualEqual => {
+            (None, Some(Parser::binary), Precedence::Equality)
+        }
+        TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual => {
+            (None, Some(Parser::binary), Precedence::Comparison)
+        }
+        TokenType::Identifier => (Some(Parser::variable), None, Precedence::None),
+        TokenType::StringPart => (Some(Parser::interpolation), None, Precedence::None),
+        TokenType::String
+        | TokenType::Number
+        | TokenType::False
+        | TokenType::True
+        | TokenType::Nil => (Some(Parser::literal), None, Precedence::None),
+        TokenType::Comma => (None, Some(Parser::comma), Precedence::Comma),
+        TokenType::Equal => (None, Some(Parser::assignment), Precedence::Assignment),
+        TokenType::Question => (None, Some(Parser::conditional), Precedence::Conditional),
+        TokenType::And => (None, Some(Parser::logical), Precedence::And),
+        TokenType::Or => (None, Some(Parser::logical), Precedence::Or),
+        TokenType::This => (Some(Parser::this), None, Precedence::None),
+        TokenType::Super => (Some(Parser::super_), None, Precedence::None),
+        _ => (None, None, Precedence::None),
+    };
+    ParseRule {
+        prefix,
+        infix,
+        precedence,
+    }
+}
+
+// What a function declaration declares, which decides its syntax and how
+// errors describe it. A getter has no parameter list and a setter takes
+// exactly one parameter. An initializer is a method named `init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    Function,
+    Method,
+    Initializer,
+    Getter,
+    Setter,
+}
+
+impl fmt::Display for FunctionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FunctionKind::Function => "function",
+            FunctionKind::Method | FunctionKind::Initializer => "method",
+            FunctionKind::Getter => "getter",
+            FunctionKind::Setter => "setter",
+        })
+    }
+}
+
+/// Functions and calls can have at most this many parameters or arguments.
+pub const MAX_ARGUMENTS: usize = 255;
+
+impl Parser {
+    /// Creates a parser over a token list ending in `Eof`, as produced by
+    /// `Scanner::scan_tokens`. Doc comments are not part of the grammar
+    /// and are dropped.
+    pub fn new(tokens: Vec<Token>) -> Self {
+        let tokens = tokens
+            .into_iter()
+            .filter(|token| token.token_type != TokenType::DocComment)
+            .collect();
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            loop_depth: 0,
+            switch_depth: 0,
+            in_initializer: false,
+        }
+    }
+
+    /// Sets how deeply expressions and statements may nest. Deeper
+    /// input is reported as a parse error instead of overflowing the stack
+    /// of the thread the parser runs on.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Parses a whole program. After a syntax error the parser skips to
+    /// the next statement boundary and carries on, so every error in the
+    /// source is returned together.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+        self.finish(statements)
+    }
+
+    /// Parses a line typed at the REPL. A line that is a single bare
+    /// expression, with no `;`, parses as a `print` of it so its value is
+    /// shown; any other line parses as a program, as [`Parser::parse`] does.
+    pub fn parse_repl_line(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let start = self.current;
+        if let Ok(expression) = self.expression()
+            && self.is_at_end()
+        {
+            let span = expression.span();
+            return self.finish(vec![Stmt::Print {
+                expression: Box::new(expression),
+                span,
+            }]);
+        }
+        self.current = start;
+        self.depth = 0;
+        self.errors.clear();
+        self.parse()
+    }
+
+    /// Parses a single expression that must span all of the input.
+    pub fn parse_expression(&mut self) -> Result<Expr, Vec<ParseError>> {
+        let result = self.expression().and_then(|expr| {
+            if self.is_at_end() {
+                Ok(expr)
+            } else {
+                Err(self.expected_error(
+                    self.peek(),
+                    Expected::Token(TokenType::Eof),
+                    "Expect end of expression.",
+                ))
+            }
+        });
+        match result {
+            Ok(expr) => self.finish(expr),
+            Err(error) => {
+                self.errors.push(error);
+                Err(std::mem::take(&mut self.errors))
+            }
+        }
+    }
+
+    fn finish<T>(&mut self, parsed: T) -> Result<T, Vec<ParseError>> {
+        if self.errors.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    // Parses a declaration, or records the error and synchronizes if it is
+    // malformed.
+    fn declaration(&mut self) -> Option<Stmt> {
+        let start = self.current;
+        match self.declaration_or_error() {
+            Ok(stmt) => Some(stmt),
+            Err(error) => {
+                self.errors.push(error);
+                self.synchronize(start);
+                None
+            }
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+        if self.match_any(&[TokenType::Fun]) {
+            let start = self.previous().span;
+            return self.function(FunctionKind::Function, start);
+        }
+        if self.match_any(&[TokenType::Var]) {
+            return self.var_declaration();
+        }
+        self.statement()
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        self.nested(
+            "Statement too deeply nested.",
+            Self::class_declaration_inner,
+        )
+    }
+
+    fn class_declaration_inner(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let name = self
+            .consume(TokenType::Identifier, "Expect class name.")?
+            .clone();
+
+        let superclass = if self.match_any(&[TokenType::Less]) {
+            let name = self
+                .consume(TokenType::Identifier, "Expect superclass name.")?
+                .clone();
+            let span = name.span;
+            Some(Box::new(Expr::Variable { name, span }))
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+        let mut methods = Vec::new();
+        let mut getters = Vec::new();
+        let mut setters = Vec::new();
+        let mut class_methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let start = self.peek().span;
+            // A method marked `class` belongs to the class object itself.
+            if self.match_any(&[TokenType::Class]) {
+                class_methods.push(self.function(FunctionKind::Method, start)?);
+                continue;
+            }
+            let kind = self.member_kind();
+            if kind == FunctionKind::Setter {
+                self.advance();
+            }
+            let member = self.function(kind, start)?;
+            match kind {
+                FunctionKind::Getter => getters.push(member),
+                FunctionKind::Setter => setters.push(member),
+                _ => methods.push(member),
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            getters,
+            setters,
+            class_methods,
+            span: self.span_from(start),
+        })
+    }
+
+    // Tells the members of a class body apart by their first tokens: a
+    // name followed by a block is a getter, and `set` followed by a name is
+    // a setter. `set` is only special there, so a method can be named
+    // `set`. A method named `init` is the class's initializer.
+    fn member_kind(&self) -> FunctionKind {
+        if !self.check(TokenType::Identifier) {
+            return FunctionKind::Method;
+        }
+        match self.peek_next().token_type {
+            TokenType::LeftBrace => FunctionKind::Getter,
+            TokenType::Identifier if self.peek().lexeme == "set" => FunctionKind::Setter,
+            _ if self.peek().lexeme == "init" => FunctionKind::Initializer,
+            _ => FunctionKind::Method,
+        }
+    }
+
+    // Parses a function's name, parameters and body. `start` is where the
+    // declaration began: the `fun` or `set` keyword, or the name of a
+    // method or getter.
+    fn function(&mut self, kind: FunctionKind, start: Span) -> Result<Stmt, ParseError> {
+        self.nested("Statement too deeply nested.", |parser| {
+            parser.function_inner(kind, start)
+        })
+    }
+
+    fn function_inner(&mut self, kind: FunctionKind, start: Span) -> Result<Stmt, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, &format!("Expect {kind} name."))?
+            .clone();
+
+        let params = if kind == FunctionKind::Getter {
+            Vec::new()
+        } else {
+            self.consume(
+                TokenType::LeftParen,
+                &format!("Expect '(' after {kind} name."),
+            )?;
+            self.parameters()?
+        };
+        if kind == FunctionKind::Setter && params.len() != 1 {
+            let error = self.error(&name, "A setter must have exactly one parameter.");
+            self.errors.push(error);
+        }
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        // A loop or switch around the declaration doesn't enclose the body.
+        let enclosing_loops = std::mem::replace(&mut self.loop_depth, 0);
+        let enclosing_switches = std::mem::replace(&mut self.switch_depth, 0);
+        let enclosing_initializer =
+            std::mem::replace(&mut self.in_initializer, kind == FunctionKind::Initializer);
+        let body = self.block();
+        self.loop_depth = enclosing_loops;
+        self.switch_depth = enclosing_switches;
+        self.in_initializer = enclosing_initializer;
+        let body = body?;
+
+        Ok(Stmt::Function {
+            name,
+            params,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses a parameter list whose `(` was just consumed, through the
+    // `)`. Too many parameters is reported but does not stop the parse.
+    fn parameters(&mut self) -> Result<Vec<Token>, ParseError> {
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let error = self.error(
+                        self.peek(),
+                        &format!("Can't have more than {MAX_ARGUMENTS} parameters."),
+                    );
+                    self.errors.push(error);
+                }
+                params.push(
+                    self.consume(TokenType::Identifier, "Expect parameter name.")?
+                        .clone(),
+                );
+                if !self.list_continues(TokenType::RightParen) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+        Ok(params)
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let name = self
+            .consume(TokenType::Identifier, "Expect variable name.")?
+            .clone();
+
+        let initializer = if self.match_any(&[TokenType::Equal]) {
+            Some(Box::new(self.single_expression()?))
+        } else {
+            None
+        };
+
+        self.consume_semicolon("Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var {
+            name,
+            initializer,
+            span: self.span_from(start),
+        })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        self.nested("Statement too deeply nested.", Self::statement_inner)
+    }
+
+    fn statement_inner(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_any(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_any(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_any(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_any(&[TokenType::Do]) {
+            return self.do_while_statement();
+        }
+        if self.match_any(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_any(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_any(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_jump_statement();
+        }
+        if self.match_any(&[TokenType::Switch]) {
+            return self.switch_statement();
+        }
+        if self.match_any(&[TokenType::LeftBrace]) {
+            let start = self.previous().span;
+            return Ok(Stmt::Block {
+                statements: self.block()?,
+                span: self.span_from(start),
+            });
+        }
+        self.expression_statement()
+    }
+
+    // There is no for loop in the tree: the clauses are desugared into
+    // `{ initializer; while (condition) body }`, with the increment kept on
+    // the `While` so that it still runs after a `continue`. A missing
+    // condition means `true`. The nodes the loop is desugared into span
+    // the whole `for` statement.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_any(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_any(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.condition()?)
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let body = self.loop_body()?;
+        let span = self.span_from(start);
+
+        let condition = condition.unwrap_or(Expr::Literal {
+            value: Value::Bool(true),
+            span,
+        });
+        let mut body = Stmt::While {
+            condition: Box::new(condition),
+            body: Box::new(body),
+            increment: increment.map(Box::new),
+            span,
+        };
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block {
+                statements: vec![initializer, body],
+                span,
+            };
+        }
+
+        Ok(body)
+    }
+
+    // An `else` binds to the nearest `if`, since the inner `if` claims it
+    // before returning.
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.condition()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = self.statement()?;
+        let else_branch = if self.match_any(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch,
+            span: self.span_from(start),
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.condition()?;
+        self.consume(TokenType::RightParen, "Expect ')' after while condition.")?;
+        let body = self.loop_body()?;
+
+        Ok(Stmt::While {
+            condition: Box::new(condition),
+            body: Box::new(body),
+            increment: None,
+            span: self.span_from(start),
+        })
+    }
+
+    // The body runs once before the condition is first checked, and a
+    // `continue` in it goes on to the condition.
+    fn do_while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let body = self.loop_body()?;
+        self.consume(TokenType::While, "Expect 'while' after do-while body.")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.condition()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expect ')' after do-while condition.",
+        )?;
+        self.consume_semicolon("Expect ';' after do-while statement.")?;
+
+        Ok(Stmt::DoWhile {
+            body: Box::new(body),
+            condition: Box::new(condition),
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses the body of a loop, where `break` and `continue` are allowed.
+    fn loop_body(&mut self) -> Result<Stmt, ParseError> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    // A `break` or `continue`, whose keyword was just consumed. Where there
+    // is nothing for it to leave it is reported but still parsed.
+    fn loop_jump_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let message = if keyword.token_type == TokenType::Break {
+            (self.loop_depth == 0 && self.switch_depth == 0)
+                .then_some("Can't use 'break' outside of a loop or switch.")
+        } else {
+            (self.loop_depth == 0).then_some("Can't use 'continue' outside of a loop.")
+        };
+        if let Some(message) = message {
+            let error = self.error(&keyword, message);
+            self.errors.push(error);
+        }
+        self.consume_semicolon(&format!("Expect ';' after '{}'.", keyword.lexeme))?;
+
+        let span = self.span_from(keyword.span);
+        Ok(if keyword.token_type == TokenType::Break {
+            Stmt::Break { keyword, span }
+        } else {
+            Stmt::Continue { keyword, span }
+        })
+    }
+
+    // Cases never fall through: the first case with a value equal to the
+    // subject runs and the switch is done, or the default case runs if no
+    // case matched. A `break` leaves the switch early. The default case
+    // must come last, and a switch can only have one.
+    fn switch_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'switch'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after switch subject.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before switch body.")?;
+
+        let mut cases = Vec::new();
+        let mut has_default = false;
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if has_default {
+                let error = self.error(self.peek(), "The default case must come last.");
+                self.errors.push(error);
+            }
+            has_default |= self.check(TokenType::Default);
+            cases.push(self.switch_case()?);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after switch body.")?;
+
+        Ok(Stmt::Switch {
+            span: self.span_from(keyword.span),
+            keyword,
+            subject: Box::new(subject),
+            cases,
+        })
+    }
+
+    // A `case` with its values and body, or the `default` case, which has
+    // no values.
+    fn switch_case(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.peek().clone();
+        let mut values = Vec::new();
+        if self.match_any(&[TokenType::Case]) {
+            loop {
+                values.push(self.single_expression()?);
+                if !self.list_continues(TokenType::Colon) {
+                    break;
+                }
+            }
+            self.consume(TokenType::Colon, "Expect ':' after case values.")?;
+        } else if self.match_any(&[TokenType::Default]) {
+            self.consume(TokenType::Colon, "Expect ':' after 'default'.")?;
+        } else {
+            return Err(self.error(&keyword, "Expect 'case' or 'default' in switch body."));
+        }
+
+        self.switch_depth += 1;
+        let mut body = Vec::new();
+        while !self.check(TokenType::Case)
+            && !self.check(TokenType::Default)
+            && !self.check(TokenType::RightBrace)
+            && !self.is_at_end()
+        {
+            if let Some(stmt) = self.declaration() {
+                body.push(stmt);
+            }
+        }
+        self.switch_depth -= 1;
+
+        Ok(Stmt::Case {
+            span: self.span_from(keyword.span),
+            keyword,
+            values,
+            body,
+        })
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let expression = self.expression()?;
+        self.consume_semicolon("Expect ';' after print statement.")?;
+        Ok(Stmt::Print {
+            expression: Box::new(expression),
+            span: self.span_from(start),
+        })
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = if self.check(TokenType::Semicolon) || self.check(TokenType::RightBrace) {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+        // An initializer always returns its instance, so only a bare
+        // `return` can leave it early.
+        if value.is_some() && self.in_initializer {
+            let error = self.error(&keyword, "Can't return a value from an initializer.");
+            self.errors.push(error);
+        }
+
+        self.consume_semicolon("Expect ';' after return value.")?;
+        Ok(Stmt::Return {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        })
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.peek().span;
+        let expression = self.expression()?;
+        self.consume_semicolon("Expect ';' after expression statement.")?;
+        Ok(Stmt::Expression {
+            expression: Box::new(expression),
+            span: self.span_from(start),
+        })
+    }
+
+    // Parses the declarations of a block whose `{` was just consumed.
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    // Parses the condition of an `if`, `while` or `for`. Error production:
+    // a top-level `=`, most likely meant as `==`, is reported, and the
+    // comparison is parsed as if it were written with `==`. An assignment
+    // can still be used as a condition by wrapping it in parentheses.
+    fn condition(&mut self) -> Result<Expr, ParseError> {
+        let mut condition = self.parse_precedence(Precedence::Assignment.next())?;
+
+        if self.check(TokenType::Equal) {
+            let operator = self.advance().clone();
+            let error = self.error(
+                &operator,
+                "Use '==' to compare values in a condition; '=' assigns.",
+            );
+            self.errors.push(error);
+            let right = self.parse_precedence(Precedence::Assignment.next())?;
+            condition = Expr::Binary {
+                span: condition.span().to(right.span()),
+                left: Box::new(condition),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        if self.match_any(&[TokenType::Comma]) {
+            condition = self.comma(condition)?;
+        }
+        Ok(condition)
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    // An expression that stops at a top-level comma, for places where a
+    // comma separates expressions instead of sequencing them.
+    fn single_expression(&mut self) -> Result<Expr, ParseError> {
+        self.parse_precedence(Precedence::Comma.next())
+    }
+
+    // Parses an expression whose operators all bind at least as tightly as
+    // `precedence`: a prefix rule for the first token, then infix rules for
+    // as long as the next operator binds tightly enough.
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        self.nested("Expression too deeply nested.", |parser| {
+            parser.parse_precedence_inner(precedence)
+        })
+    }
+
+    fn parse_precedence_inner(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        let prefix_rule = rule(self.peek().token_type);
+        let mut expr = match prefix_rule.prefix {
+            Some(prefix) => {
+                self.advance();
+                prefix(self)?
+            }
+            None if prefix_rule.infix.is_some() && prefix_rule.precedence < Precedence::Unary => {
+                self.missing_left_operand(prefix_rule.precedence)?
+            }
+            None => {
+                return Err(self.expected_error(
+                    self.peek(),
+                    Expected::Expression,
+                    "Expect expression.",
+                ));
+            }
+        };
+
+        loop {
+            let infix_rule = rule(self.peek().token_type);
+            let Some(infix) = infix_rule.infix else {
+                break;
+            };
+            if infix_rule.precedence < precedence {
+                break;
+            }
+            self.advance();
+            expr = infix(self, expr)?;
+        }
+
+        Ok(expr)
+    }
+
+    // Error production: a binary operator with nothing on its left, as in
+    // `* 3`. It is reported, and the right operand is parsed at the
+    // operator's precedence and stands in for the whole expression.
+    fn missing_left_operand(&mut self, precedence: Precedence) -> Result<Expr, ParseError> {
+        let operator = self.advance().clone();
+        let error = self.error(&operator, "Missing left-hand operand.");
+        self.errors.push(error);
+        self.parse_precedence(precedence.next())
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let right = self.parse_precedence(Precedence::Unary)?;
+        Ok(Expr::Unary {
+            span: operator.span.to(right.span()),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    // Binary operators are left-associative: the right operand only takes
+    // operators that bind more tightly than this one.
+    fn binary(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let right = self.parse_precedence(rule(operator.token_type).precedence.next())?;
+        Ok(Expr::Binary {
+            span: left.span().to(right.span()),
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    // Like `binary`, but builds `Logical` nodes, which short-circuit.
+    fn logical(&mut self, left: Expr) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        let right = self.parse_precedence(rule(operator.token_type).precedence.next())?;
+        Ok(Expr::Logical {
+            span: left.span().to(right.span()),
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    // The `?` of `condition ? then : else` was just consumed. The else
+    // branch is parsed at the same precedence, which makes the operator
+    // right-associative: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+    fn conditional(&mut self, condition: Expr) -> Result<Expr, ParseError> {
+        let then_branch = self.expression()?;
+        self.consume(
+            TokenType::Colon,
+            "Expect ':' after then branch of conditional expression.",
+        )?;
+        let else_branch = self.parse_precedence(Precedence::Conditional)?;
+        Ok(Expr::Ternary {
+            span: condition.span().to(else_branch.span()),
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    // The `=` after `target` was just consumed. The value is parsed at the
+    // same precedence, so `a = b = c` assigns `c` to `b` and then to `a`.
+    // Only a variable, a property or an element can be assigned to; any
+    // other target is reported at the `=` but does not stop the parse.
+    fn assignment(&mut self, target: Expr) -> Result<Expr, ParseError> {
+        let equals = self.previous().clone();
+        let value = Box::new(self.parse_precedence(Precedence::Assignment)?);
+        let span = target.span().to(value.span());
+
+        match target {
+            Expr::Variable { name, .. } => Ok(Expr::Assign { name, value, span }),
+            Expr::Get { object, name, .. } => Ok(Expr::Set {
+                object,
+                name,
+                value,
+                span,
+            }),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => Ok(Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+                span,
+            }),
+            target => {
+                let error = self.error(&equals, "Invalid assignment target.");
+                self.errors.push(error);
+                Ok(target)
+            }
+        }
+    }
+
+    // The first `,` of a sequence was just consumed. The operands are
+    // evaluated left to right and the last one is the result.
+    fn comma(&mut self, first: Expr) -> Result<Expr, ParseError> {
+        let mut expressions = vec![first];
+        loop {
+            expressions.push(self.single_expression()?);
+            if !self.match_any(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        Ok(Expr::Comma {
+            span: self.span_from(expressions[0].span()),
+            expressions,
+        })
+    }
+
+    fn dot(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect property name after '.'.")?
+            .clone();
+        Ok(Expr::Get {
+            span: object.span().to(name.span),
+            object: Box::new(object),
+            name,
+        })
+    }
+
+    // The first `?.` of a call chain was just consumed. The rest of the
+    // chain goes into an `OptionalChain` node with it, so that a nil
+    // receiver makes the whole of `a?.b.c()` nil rather than only `a?.b`.
+    fn optional_chain(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let mut expression = self.optional_get(object)?;
+        loop {
+            let infix_rule = rule(self.peek().token_type);
+            let Some(infix) = infix_rule.infix else {
+                break;
+            };
+            if infix_rule.precedence != Precedence::Call {
+                break;
+            }
+            expression = if self.advance().token_type == TokenType::QuestionDot {
+                self.optional_get(expression)?
+            } else {
+                infix(self, expression)?
+            };
+        }
+        Ok(Expr::OptionalChain {
+            span: expression.span(),
+            expression: Box::new(expression),
+        })
+    }
+
+    fn optional_get(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect property name after '?.'.")?
+            .clone();
+        Ok(Expr::OptionalGet {
+            span: object.span().to(name.span),
+            object: Box::new(object),
+            name,
+        })
+    }
+
+    // Parses the arguments of a call whose `(` was just consumed. Too many
+    // arguments is reported but does not stop the parse.
+    fn call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let error = self.error(
+                        self.peek(),
+                        &format!("Can't have more than {MAX_ARGUMENTS} arguments."),
+                    );
+                    self.errors.push(error);
+                }
+                arguments.push(self.single_expression()?);
+                if !self.list_continues(TokenType::RightParen) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self
+            .consume(TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+
+        Ok(Expr::Call {
+            span: callee.span().to(paren.span),
+            callee: Box::new(callee),
+            paren,
+            arguments,
+        })
+    }
+
+    // The `[` after `object` was just consumed.
+    fn index(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let index = self.expression()?;
+        let bracket = self
+            .consume(TokenType::RightBracket, "Expect ']' after index.")?
+            .clone();
+        Ok(Expr::Index {
+            span: object.span().to(bracket.span),
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+        })
+    }
+
+    // An array literal whose `[` was just consumed. Its elements are
+    // separated by commas, like the arguments of a call.
+    fn array(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let mut elements = Vec::new();
+        if !self.check(TokenType::RightBracket) {
+            loop {
+                elements.push(self.single_expression()?);
+                if !self.list_continues(TokenType::RightBracket) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBracket, "Expect ']' after array elements.")?;
+        Ok(Expr::Array {
+            elements,
+            span: self.span_from(start),
+        })
+    }
+
+    // A map literal whose `{` was just consumed. A `{` only starts a map
+    // where an expression is expected: at the start of a statement it
+    // starts a block. An identifier key stands for the string of its name.
+    fn map(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        if !self.check(TokenType::RightBrace) {
+            loop {
+                keys.push(self.map_key()?);
+                self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+                values.push(self.single_expression()?);
+                if !self.list_continues(TokenType::RightBrace) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::Map {
+            keys,
+            values,
+            span: self.span_from(start),
+        })
+    }
+
+    fn map_key(&mut self) -> Result<Expr, ParseError> {
+        if self.match_any(&[TokenType::String]) {
+            return self.literal();
+        }
+        let name = self.consume(TokenType::Identifier, "Expect map key.")?;
+        Ok(Expr::Literal {
+            value: Value::String(name.lexeme.to_string()),
+            span: name.span,
+        })
+    }
+
+    fn grouping(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let expression = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+        Ok(Expr::Grouping {
+            expression: Box::new(expression),
+            span: self.span_from(start),
+        })
+    }
+
+    // A string with `${...}` in it, whose first part was just consumed.
+    // The scanner splits it into string parts around each interpolated
+    // expression; the node keeps the expressions and the non-empty parts
+    // in order, to be stringified and joined when it is evaluated.
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let start = self.previous().span;
+        let mut parts = Vec::new();
+        self.string_part(&mut parts);
+        while self.match_any(&[TokenType::InterpolationStart]) {
+            parts.push(self.expression()?);
+            self.consume(
+                TokenType::InterpolationEnd,
+                "Expect '}' after interpolated expression.",
+            )?;
+            self.consume(TokenType::StringPart, "Expect rest of string.")?;
+            self.string_part(&mut parts);
+        }
+        Ok(Expr::Interpolation {
+            parts,
+            span: self.span_from(start),
+        })
+    }
+
+    // Adds the string part just consumed to `parts`, unless it is empty.
+    fn string_part(&mut self, parts: &mut Vec<Expr>) {
+        let token = self.previous();
+        if let Some(Literal::String(s)) = &token.literal
+            && !s.is_empty()
+        {
+            parts.push(Expr::Literal {
+                value: Value::String(s.clone()),
+                span: token.span,
+            });
+        }
+    }
+
+    fn literal(&mut self) -> Result<Expr, ParseError> {
+        let token = self.previous();
+        let value = match token.token_type {
+            TokenType::False => Value::Bool(false),
+            TokenType::True => Value::Bool(true),
+            TokenType::Nil => Value::Nil,
+            _ => match &token.literal {
+                Some(Literal::Number(n)) => Value::Number(*n),
+                Some(Literal::String(s)) => Value::String(s.clone()),
+                None => unreachable!("the scanner gives literals a value"),
+            },
+        };
+        Ok(Expr::Literal {
+            value,
+            span: token.span,
+        })
+    }
+
+    fn variable(&mut self) -> Result<Expr, ParseError> {
+        let name = self.previous().clone();
+        Ok(Expr::Variable {
+            span: name.span,
+            name,
+        })
+    }
+
+    fn this(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().clone();
+        Ok(Expr::This {
+            span: keyword.span,
+            keyword,
+        })
+    }
+
+    fn super_(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+        let method = self
+            .consume(TokenType::Identifier, "Expect superclass method name.")?
+            .clone();
+        Ok(Expr::Super {
+            span: keyword.span.to(method.span),
+            keyword,
+            method,
+        })
+    }
+
+    // Runs `parse` one level deeper, or fails at the current token if that
+    // would go past the depth limit.
+    fn nested<T>(
+        &mut self,
+        message: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if self.depth >= self.max_depth {
+            return Err(self.error(self.peek(), message));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Panic-mode recovery after an error in the declaration that began at
+    // token `start`: discards tokens until just after a `;` or just before a
+    // keyword that starts a statement. Braces are kept balanced, counting
+    // those the declaration opened, so a malformed body is skipped whole
+    // and a `}` closing an enclosing block is left for that block.
+    fn synchronize(&mut self, start: usize) {
+        let mut depth = self.tokens[start..self.current]
+            .iter()
+            .fold(0usize, |depth, token| match token.token_type {
+                TokenType::LeftBrace => depth + 1,
+                TokenType::RightBrace => depth.saturating_sub(1),
+                _ => depth,
+            });
+        if depth == 0 && self.current > start && self.check(TokenType::RightBrace) {
+            return;
+        }
+
+        while !self.is_at_end() {
+            match self.advance().token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace => depth = depth.saturating_sub(1),
+                TokenType::Semicolon if depth == 0 => return,
+                _ => {}
+            }
+
+            if depth > 0 {
+                continue;
+            }
+            match self.peek().token_type {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Do
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Switch
+                | TokenType::RightBrace => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn match_any(&mut self, types: &[TokenType]) -> bool {
+        if types.iter().any(|&t| self.check(t)) {
+            self.advance();
+            return true;
+        }
+        false
+    }
+
+    // Consumes the `,` after an item of a comma-separated list closed by
+    // `close`, and says whether another item follows. A comma right before
+    // `close` is a trailing one and ends the list; a list with no items,
+    // like `f(,)`, is still an error because an item is parsed first.
+    fn list_continues(&mut self, close: TokenType) -> bool {
+        self.match_any(&[TokenType::Comma]) && !self.check(close)
+    }
+
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<&Token, ParseError> {
+        if self.check(token_type) {
+            return Ok(self.advance());
+        }
+        Err(self.expected_error(self.peek(), Expected::Token(token_type), message))
+    }
+
+    // Consumes the `;` ending a statement. Error production: a `;` missing
+    // right before a `}` is reported, but the statement ends there anyway.
+    fn consume_semicolon(&mut self, message: &str) -> Result<(), ParseError> {
+        if self.check(TokenType::RightBrace) {
+            let error = self.expected_error(
+                self.peek(),
+                Expected::Token(TokenType::Semicolon),
+                "Missing ';' before '}'.",
+            );
+            self.errors.push(error);
+            return Ok(());
+        }
+        self.consume(TokenType::Semicolon, message)?;
+        Ok(())
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        !self.is_at_end() && self.peek().token_type == token_type
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    // The token after the next one, or `Eof` at the end.
+    fn peek_next(&self) -> &Token {
+        let next = (self.current + 1).min(self.tokens.len() - 1);
+        &self.tokens[next]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    // The span from `start` through the last token consumed, for the node
+    // whose tokens have just been parsed.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
+    fn error(&self, token: &Token, message: &str) -> ParseError {
+        ParseError {
+            token: Box::new(token.clone()),
+            expected: None,
+            message: message.to_string(),
+            span: token.span,
+        }
+    }
+
+    fn expected_error(&self, token: &Token, expected: Expected, message: &str) -> ParseError {
+        ParseError {
+            expected: Some(expected),
+            ..self.error(token, message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_printer::AstPrinter;
+    use crate::scanner::Scanner;
+
+    fn parse(src: &str) -> Result<Expr, ParseError> {
+        let tokens = Scanner::new(src).scan_tokens().expect("source scans");
+        Parser::new(tokens)
+            .parse_expression()
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    fn parse_program(src: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let tokens = Scanner::new(src).scan_tokens().expect("source scans");
+        Parser::new(tokens).parse()
+    }
+
+    fn render(statements: &[Stmt]) -> String {
+        let printed: Vec<String> = statements
+            .iter()
+            .map(|s| AstPrinter.print_stmt(s))
+            .collect();
+        printed.join(" ")
+    }
+
+    fn program_parses_to(src: &str, expected: &str) {
+        assert_eq!(
+            render(&parse_program(src).unwrap()),
+            expected,
+            "parsing {src:?}"
+        );
+    }
+
+    fn program_error(src: &str) -> String {
+        let errors = parse_program(src).unwrap_err();
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        messages.join("\n")
+    }
+
+    fn parses_to(src: &str, expected: &str) {
+        assert_eq!(
+            AstPrinter.print_expr(&parse(src).unwrap()),
+            expected,
+            "parsing {src:?}"
+        );
+    }
+
+    #[test]
+    fn parses_literals() {
+        parses_to("123", "123");
+        parses_to("45.67", "45.67");
+        parses_to("\"hi\"", "hi");
+        parses_to("true", "true");
+        parses_to("false", "false");
+        parses_to("nil", "nil");
+    }
+
+    #[test]
+    fn parses_the_book_example() {
+        parses_to("-123 * (45.67)", "(* (- 123) (group 45.67))");
+    }
+
+    #[test]
+    fn binary_operators_follow_precedence() {
+        parses_to("1 + 2 * 3", "(+ 1 (* 2 3))");
+        parses_to("1 * 2 + 3", "(+ (* 1 2) 3)");
+        parses_to("1 < 2 == 3 >= 4", "(== (< 1 2) (>= 3 4))");
+        parses_to("-1 - -2", "(- (- 1) (- 2))");
+        parses_to("!!true != false", "(!= (! (! true)) false)");
+    }
+
+    #[test]
+    fn binary_operators_are_left_associative() {
+        parses_to("1 - 2 - 3", "(- (- 1 2) 3)");
+        parses_to("8 / 4 / 2", "(/ (/ 8 4) 2)");
+        parses_to("1 == 2 == 3", "(== (== 1 2) 3)");
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        parses_to("(1 + 2) * 3", "(* (group (+ 1 2)) 3)");
+    }
+
+    #[test]
+    fn doc_comments_are_skipped() {
+        parses_to("/// one\n1 + /// two\n2", "(+ 1 2)");
+    }
+
+    #[test]
+    fn reports_missing_operand() {
+        let error = parse("1 +").unwrap_err();
+        assert_eq!(error.message, "Expect expression.");
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at end: Expect expression."
+        );
+    }
+
+    #[test]
+    fn reports_unclosed_group_at_offending_token() {
+        let error = parse("(1 + 2 3").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at '3': Expect ')' after expression."
+        );
+    }
+
+    #[test]
+    fn reports_trailing_tokens() {
+        let error = parse("1 2").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at '2': Expect end of expression."
+        );
+    }
+
+    #[test]
+    fn parses_print_and_expression_statements() {
+        program_parses_to("print 1 + 2; 3 * 4;", "(print (+ 1 2)) (; (* 3 4))");
+    }
+
+    #[test]
+    fn parses_variable_declarations() {
+        program_parses_to("var a; var b = a;", "(var a) (var b a)");
+    }
+
+    #[test]
+    fn parses_nested_blocks() {
+        program_parses_to(
+            "{ var a = 1; { print a; } } {}",
+            "(block (var a 1) (block (print a))) (block)",
+        );
+    }
+
+    #[test]
+    fn empty_program_has_no_statements() {
+        assert!(parse_program("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_semicolons_name_the_construct() {
+        assert_eq!(
+            program_error("print 1"),
+            "[line 1] Error at end: Expect ';' after print statement."
+        );
+        assert_eq!(
+            program_error("1 + 2\nprint 3;"),
+            "[line 2] Error at 'print': Expect ';' after expression statement."
+        );
+        assert_eq!(
+            program_error("var a = 1 var b;"),
+            "[line 1] Error at 'var': Expect ';' after variable declaration."
+        );
+    }
+
+    #[test]
+    fn reports_malformed_declarations_and_blocks() {
+        assert_eq!(
+            program_error("var 1 = 2;"),
+            "[line 1] Error at '1': Expect variable name."
+        );
+        assert_eq!(
+            program_error("{ print 1;"),
+            "[line 1] Error at end: Expect '}' after block."
+        );
+    }
+
+    #[test]
+    fn parses_logical_operators() {
+        parses_to("a or b and c", "(or a (and b c))");
+        parses_to("a and b or c and d", "(or (and a b) (and c d))");
+        parses_to("a == b and c", "(and (== a b) c)");
+    }
+
+    #[test]
+    fn parses_modulo_and_bitwise_operators() {
+        parses_to("a % b * c", "(* (% a b) c)");
+        parses_to("a + b % c", "(+ a (% b c))");
+        parses_to("a | b ^ c & d", "(| a (^ b (& c d)))");
+        parses_to("a & b << c + d", "(& a (<< b (+ c d)))");
+        parses_to("a >> b >> c", "(>> (>> a b) c)");
+        parses_to("a < b | c", "(< a (| b c))");
+        parses_to("a == b & c", "(== a (& b c))");
+        parses_to("~a & ~-b", "(& (~ a) (~ (- b)))");
+        assert_eq!(
+            program_error("print | 1;"),
+            "[line 1] Error at '|': Missing left-hand operand."
+        );
+    }
+
+    #[test]
+    fn parses_if_and_while() {
+        program_parses_to("if (a) print 1;", "(if a (print 1))");
+        program_parses_to(
+            "if (a) { print 1; } else print 2;",
+            "(if a (block (print 1)) (print 2))",
+        );
+        program_parses_to("while (a < 3) a;", "(while (< a 3) (; a))");
+    }
+
+    #[test]
+    fn dangling_else_binds_to_nearest_if() {
+        program_parses_to(
+            "if (a) if (b) print 1; else print 2;",
+            "(if a (if b (print 1) (print 2)))",
+        );
+    }
+
+    #[test]
+    fn for_loops_desugar_to_while() {
+        let cases = [
+            (
+                "for (var i = 0; i < 3; i + 1) print i;",
+                "(block (var i 0) (while (< i 3) (print i) (+ i 1)))",
+            ),
+            (
+                "for (i; i < 3; i + 1) print i;",
+                "(block (; i) (while (< i 3) (print i) (+ i 1)))",
+            ),
+            (
+                "for (; i < 3; i + 1) print i;",
+                "(while (< i 3) (print i) (+ i 1))",
+            ),
+            (
+                "for (var i = 0;; i + 1) print i;",
+                "(block (var i 0) (while true (print i) (+ i 1)))",
+            ),
+            (
+                "for (var i = 0; i < 3;) print i;",
+                "(block (var i 0) (while (< i 3) (print i)))",
+            ),
+            ("for (;; i + 1) print i;", "(while true (print i) (+ i 1))"),
+            ("for (; i < 3;) print i;", "(while (< i 3) (print i))"),
+            (
+                "for (var i;;) print i;",
+                "(block (var i) (while true (print i)))",
+            ),
+            ("for (;;) print i;", "(while true (print i))"),
+        ];
+        for (src, expected) in cases {
+            program_parses_to(src, expected);
+        }
+    }
+
+    #[test]
+    fn parses_break_and_continue_inside_loops() {
+        program_parses_to(
+            "while (true) { if (a) break; continue; }",
+            "(while true (block (if a (break)) (continue)))",
+        );
+        program_parses_to(
+            "for (;; i = i + 1) { for (;;) break; continue; }",
+            "(while true (block (while true (break)) (continue)) (= i (+ i 1)))",
+        );
+        program_parses_to(
+            "while (a) { fun f() { while (b) break; } }",
+            "(while a (block (fun f () (while b (break)))))",
+        );
+    }
+
+    #[test]
+    fn rejects_break_and_continue_outside_loops() {
+        assert_eq!(
+            program_error("break; if (a) { continue; }"),
+            "[line 1] Error at 'break': Can't use 'break' outside of a loop or switch.\n\
+             [line 1] Error at 'continue': Can't use 'continue' outside of a loop."
+        );
+        assert_eq!(
+            program_error("while (a) { fun f() { break; } }"),
+            "[line 1] Error at 'break': Can't use 'break' outside of a loop or switch."
+        );
+        assert_eq!(
+            program_error("while (a) { break print 1; }"),
+            "[line 1] Error at 'print': Expect ';' after 'break'."
+        );
+    }
+
+    #[test]
+    fn parses_switch_statements() {
+        program_parses_to(
+            "switch (x) { case 1, 2: print a; print b; case 3: default: print c; }",
+            "(switch x (case (1 2) (print a) (print b)) (case (3)) (default (print c)))",
+        );
+        program_parses_to("switch (f()) {}", "(switch (call f))");
+        program_parses_to(
+            "switch (x) { case 1: { break; } }",
+            "(switch x (case (1) (block (break))))",
+        );
+        program_parses_to(
+            "while (a) switch (x) { default: continue; }",
+            "(while a (switch x (default (continue))))",
+        );
+    }
+
+    #[test]
+    fn reports_malformed_switch_statements() {
+        assert_eq!(
+            program_error("switch (x) { print 1; }"),
+            "[line 1] Error at 'print': Expect 'case' or 'default' in switch body."
+        );
+        assert_eq!(
+            program_error("switch (x) { case 1 print 1; }"),
+            "[line 1] Error at 'print': Expect ':' after case values."
+        );
+        assert_eq!(
+            program_error("switch (x) { default: case 1: default: }"),
+            "[line 1] Error at 'case': The default case must come last.\n\
+             [line 1] Error at 'default': The default case must come last."
+        );
+        assert_eq!(
+            program_error("switch (x) { case 1: continue; }"),
+            "[line 1] Error at 'continue': Can't use 'continue' outside of a loop."
+        );
+        assert_eq!(
+            program_error("switch (x) { case 1: fun f() { break; } }"),
+            "[line 1] Error at 'break': Can't use 'break' outside of a loop or switch."
+        );
+    }
+
+    #[test]
+    fn parses_do_while_loops() {
+        program_parses_to(
+            "do { print i; i = i + 1; } while (i < 3);",
+            "(do (block (print i) (; (= i (+ i 1)))) (< i 3))",
+        );
+        program_parses_to(
+            "do if (a) break; else continue; while (b);",
+            "(do (if a (break) (continue)) b)",
+        );
+        program_parses_to("do do a; while (b); while (c);", "(do (do (; a) b) c)");
+    }
+
+    #[test]
+    fn reports_malformed_do_while_loops() {
+        assert_eq!(
+            program_error("do print 1; (a);"),
+            "[line 1] Error at '(': Expect 'while' after do-while body."
+        );
+        assert_eq!(
+            program_error("do {} while (a) print 1;"),
+            "[line 1] Error at 'print': Expect ';' after do-while statement."
+        );
+        assert_eq!(
+            program_error("do {} while (a = 1);"),
+            "[line 1] Error at '=': Use '==' to compare values in a condition; '=' assigns."
+        );
+    }
+
+    #[test]
+    fn reports_malformed_control_flow() {
+        assert_eq!(
+            program_error("if a) print 1;"),
+            "[line 1] Error at 'a': Expect '(' after 'if'."
+        );
+        assert_eq!(
+            program_error("while (a print 1;"),
+            "[line 1] Error at 'print': Expect ')' after while condition."
+        );
+        assert_eq!(
+            program_error("for (var i = 0; i < 3 i) print i;"),
+            "[line 1] Error at 'i': Expect ';' after loop condition."
+        );
+        assert_eq!(
+            program_error("for (;; i print i;"),
+            "[line 1] Error at 'print': Expect ')' after for clauses."
+        );
+    }
+
+    #[test]
+    fn parses_calls() {
+        parses_to("f()", "(call f)");
+        parses_to("f(1, a + b)(2)", "(call (call f 1 (+ a b)) 2)");
+        parses_to("-f(1)", "(- (call f 1))");
+    }
+
+    #[test]
+    fn parses_function_declarations_and_returns() {
+        program_parses_to(
+            "fun add(a, b) { return a + b; } fun nothing() { return; }",
+            "(fun add (a b) (return (+ a b))) (fun nothing () (return))",
+        );
+    }
+
+    #[test]
+    fn reports_malformed_functions_and_calls() {
+        assert_eq!(
+            program_error("fun (a) {}"),
+            "[line 1] Error at '(': Expect function name."
+        );
+        assert_eq!(
+            program_error("fun f(a b) {}"),
+            "[line 1] Error at 'b': Expect ')' after parameters."
+        );
+        assert_eq!(
+            program_error("fun f() print 1;"),
+            "[line 1] Error at 'print': Expect '{' before function body."
+        );
+        assert_eq!(
+            program_error("f(1, 2;"),
+            "[line 1] Error at ';': Expect ')' after arguments."
+        );
+        assert_eq!(
+            program_error("return 1"),
+            "[line 1] Error at end: Expect ';' after return value."
+        );
+    }
+
+    #[test]
+    fn accepts_trailing_commas_in_lists() {
+        parses_to("f(1, 2,)", "(call f 1 2)");
+        parses_to("f(a,)(b,)", "(call (call f a) b)");
+        parses_to("[1, 2,]", "(array 1 2)");
+        parses_to("{a: 1, b: 2,}", "(map (: a 1) (: b 2))");
+        program_parses_to("fun f(a, b,) {}", "(fun f (a b))");
+        program_parses_to(
+            "switch (x) { case 1, 2,: print x; }",
+            "(switch x (case (1 2) (print x)))",
+        );
+    }
+
+    #[test]
+    fn a_trailing_comma_needs_an_item_before_it() {
+        assert_eq!(
+            program_error("f(,);"),
+            "[line 1] Error at ',': Missing left-hand operand.\n\
+             [line 1] Error at ')': Expect expression."
+        );
+        assert_eq!(
+            program_error("f(1,,);"),
+            "[line 1] Error at ',': Missing left-hand operand.\n\
+             [line 1] Error at ')': Expect expression."
+        );
+        assert_eq!(
+            program_error("fun f(,) {}"),
+            "[line 1] Error at ',': Expect parameter name."
+        );
+        assert_eq!(
+            program_error("print [,];"),
+            "[line 1] Error at ',': Missing left-hand operand.\n\
+             [line 1] Error at ']': Expect expression."
+        );
+        assert_eq!(
+            program_error("print {,};"),
+            "[line 1] Error at ',': Expect map key."
+        );
+    }
+
+    #[test]
+    fn too_many_arguments_is_reported_without_stopping() {
+        let arguments: Vec<String> = (0..=MAX_ARGUMENTS).map(|i| i.to_string()).collect();
+        let src = format!("f({}); print;", arguments.join(", "));
+        assert_eq!(
+            program_error(&src),
+            "[line 1] Error at '255': Can't have more than 255 arguments.\n\
+             [line 1] Error at ';': Expect expression."
+        );
+
+        let params: Vec<String> = (0..=MAX_ARGUMENTS).map(|i| format!("p{i}")).collect();
+        let src = format!("fun f({}) {{}}", params.join(", "));
+        assert_eq!(
+            program_error(&src),
+            "[line 1] Error at 'p255': Can't have more than 255 parameters."
+        );
+
+        let arguments: Vec<String> = (0..MAX_ARGUMENTS).map(|i| i.to_string()).collect();
+        assert!(parse_program(&format!("f({});", arguments.join(", "))).is_ok());
+    }
+
+    #[test]
+    fn parses_property_access_this_and_super() {
+        parses_to("a.b.c", "(. (. a b) c)");
+        parses_to("a.b(1).c()", "(call (. (call (. a b) 1) c))");
+        parses_to("this.x", "(. this x)");
+        parses_to("super.init(1)", "(call (super init) 1)");
+    }
+
+    #[test]
+    fn parses_optional_chains() {
+        parses_to("a?.b", "(optional (?. a b))");
+        parses_to("a?.b.c(1)[0]", "(optional ([] (call (. (?. a b) c) 1) 0))");
+        parses_to("a?.b?.c", "(optional (?. (?. a b) c))");
+        parses_to("f()?.x", "(optional (?. (call f) x))");
+        parses_to("a.b?.c()", "(optional (call (?. (. a b) c)))");
+        parses_to("-a?.b", "(- (optional (?. a b)))");
+        parses_to("a?.b + c?.d", "(+ (optional (?. a b)) (optional (?. c d)))");
+        parses_to("a?.b ? c : d", "(?: (optional (?. a b)) c d)");
+        parses_to("a?b:c", "(?: a b c)");
+    }
+
+    #[test]
+    fn reports_malformed_optional_chains() {
+        assert_eq!(
+            program_error("a?.1;"),
+            "[line 1] Error at '1': Expect property name after '?.'."
+        );
+        assert_eq!(
+            program_error("a?.(1);"),
+            "[line 1] Error at '(': Expect property name after '?.'."
+        );
+    }
+
+    #[test]
+    fn parses_class_declarations() {
+        program_parses_to(
+            "class A { init(x) { this.x; } get() { return this.x; } }",
+            "(class A (fun init (x) (; (. this x))) (fun get () (return (. this x))))",
+        );
+        program_parses_to(
+            "class B < A { get() { return super.get(); } }",
+            "(class B < A (fun get () (return (call (super get)))))",
+        );
+        program_parses_to("class Empty {}", "(class Empty)");
+    }
+
+    #[test]
+    fn parses_getters_and_setters() {
+        program_parses_to(
+            "class Circle { area { return r * r; } set radius(r) { this.r = r; } }",
+            "(class Circle (get area (return (* r r))) (set radius (r) (; (= (. this r) r))))",
+        );
+        program_parses_to(
+            "class A { set(x) {} set {} }",
+            "(class A (fun set (x)) (get set))",
+        );
+    }
+
+    #[test]
+    fn parses_class_methods() {
+        program_parses_to(
+            "class Math { class square(n) { return n * n; } init() {} }",
+            "(class Math (fun init ()) (static square (n) (return (* n n))))",
+        );
+        parses_to("Math.square(3)", "(call (. Math square) 3)");
+        assert_eq!(
+            program_error("class A { class {} }"),
+            "[line 1] Error at '{': Expect method name."
+        );
+    }
+
+    #[test]
+    fn reports_malformed_getters_and_setters() {
+        assert_eq!(
+            program_error("class A { set x() {} set y(a, b) {} }"),
+            "[line 1] Error at 'x': A setter must have exactly one parameter.\n\
+             [line 1] Error at 'y': A setter must have exactly one parameter."
+        );
+        assert_eq!(
+            program_error("class A { set x {} }"),
+            "[line 1] Error at '{': Expect '(' after setter name."
+        );
+    }
+
+    #[test]
+    fn initializers_cannot_return_values() {
+        assert_eq!(
+            program_error("class A { init() { return 1; } }"),
+            "[line 1] Error at 'return': Can't return a value from an initializer."
+        );
+        program_parses_to(
+            "class A { init() { return; fun f() { return 1; } } class init() { return 2; } }",
+            "(class A (fun init () (return) (fun f () (return 1))) (static init () (return 2)))",
+        );
+    }
+
+    #[test]
+    fn reports_malformed_classes() {
+        assert_eq!(
+            program_error("class { }"),
+            "[line 1] Error at '{': Expect class name."
+        );
+        assert_eq!(
+            program_error("class A < { }"),
+            "[line 1] Error at '{': Expect superclass name."
+        );
+        assert_eq!(
+            program_error("class A { fun f() {} }"),
+            "[line 1] Error at 'fun': Expect method name."
+        );
+        assert_eq!(
+            program_error("class A { f() {}"),
+            "[line 1] Error at end: Expect '}' after class body."
+        );
+        assert_eq!(
+            program_error("super;"),
+            "[line 1] Error at ';': Expect '.' after 'super'."
+        );
+        assert_eq!(
+            program_error("a.1;"),
+            "[line 1] Error at '1': Expect property name after '.'."
+        );
+    }
+
+    fn parse_repl_line(src: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let tokens = Scanner::new(src).scan_tokens().expect("source scans");
+        Parser::new(tokens).parse_repl_line()
+    }
+
+    #[test]
+    fn repl_lines_that_are_bare_expressions_are_printed() {
+        let render_line = |src| render(&parse_repl_line(src).unwrap());
+        assert_eq!(render_line("1 + 2"), "(print (+ 1 2))");
+        assert_eq!(render_line("a = f(b)"), "(print (= a (call f b)))");
+        assert_eq!(render_line("1 + 2;"), "(; (+ 1 2))");
+        assert_eq!(render_line("var a = 1;"), "(var a 1)");
+        assert_eq!(render_line("print 1; 2;"), "(print 1) (; 2)");
+        assert_eq!(render_line(""), "");
+    }
+
+    #[test]
+    fn repl_lines_that_are_not_expressions_report_statement_errors() {
+        let errors = parse_repl_line("1 + 2 3").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at '3': Expect ';' after expression statement."
+        );
+        assert_eq!(parse_repl_line("var a = 1").unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn reports_missing_left_operand_and_keeps_parsing() {
+        assert_eq!(
+            program_error("print * 3; print == 1 < 2; print 4 +;"),
+            "[line 1] Error at '*': Missing left-hand operand.\n\
+             [line 1] Error at '==': Missing left-hand operand.\n\
+             [line 1] Error at ';': Expect expression."
+        );
+
+        let mut parser = Parser::new(Scanner::new("== 1 < 2").scan_tokens().unwrap());
+        let errors = parser.parse_expression().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Missing left-hand operand.");
+        assert_eq!(
+            parse("-3").map(|e| AstPrinter.print_expr(&e)).unwrap(),
+            "(- 3)"
+        );
+    }
+
+    #[test]
+    fn reports_assignment_used_as_comparison() {
+        assert_eq!(
+            program_error("if (a = 1) print a; while (b = c) {} for (; d = 2;) {}"),
+            "[line 1] Error at '=': Use '==' to compare values in a condition; '=' assigns.\n\
+             [line 1] Error at '=': Use '==' to compare values in a condition; '=' assigns.\n\
+             [line 1] Error at '=': Use '==' to compare values in a condition; '=' assigns."
+        );
+    }
+
+    #[test]
+    fn reports_missing_semicolon_before_closing_brace() {
+        assert_eq!(
+            program_error("{ print 1 } { var a = 2 } fun f() { return }"),
+            "[line 1] Error at '}': Missing ';' before '}'.\n\
+             [line 1] Error at '}': Missing ';' before '}'.\n\
+             [line 1] Error at '}': Missing ';' before '}'."
+        );
+        assert_eq!(
+            program_error("{ print 1 2 }"),
+            "[line 1] Error at '2': Expect ';' after print statement."
+        );
+    }
+
+    #[test]
+    fn synchronizes_to_report_every_error() {
+        assert_eq!(
+            program_error(
+                "var = 1; print 2;\nprint (3;\nvar a = 4 print a;\nfun f() { 1 + ; print 5; }"
+            ),
+            "[line 1] Error at '=': Expect variable name.\n\
+             [line 2] Error at ';': Expect ')' after expression.\n\
+             [line 3] Error at 'print': Expect ';' after variable declaration.\n\
+             [line 4] Error at ';': Expect expression."
+        );
+    }
+
+    #[test]
+    fn statements_after_an_error_are_still_parsed() {
+        let mut parser = Parser::new(Scanner::new("print ); print 1;").scan_tokens().unwrap());
+        assert_eq!(parser.parse().unwrap_err().len(), 1);
+        assert_eq!(parser.current, parser.tokens.len() - 1);
+
+        assert_eq!(
+            program_error("{ print } print 1;"),
+            "[line 1] Error at '}': Expect expression."
+        );
+        assert_eq!(
+            program_error("class A { f() { print; } g( {} } print (;"),
+            "[line 1] Error at ';': Expect expression.\n\
+             [line 1] Error at '{': Expect parameter name.\n\
+             [line 1] Error at ';': Expect expression."
+        );
+        assert_eq!(
+            program_error("{ print 1 2 } print (;"),
+            "[line 1] Error at '2': Expect ';' after print statement.\n\
+             [line 1] Error at ';': Expect expression."
+        );
+    }
+
+    #[test]
+    fn every_precedence_level_nests_correctly() {
+        parses_to(
+            "a or b and c == d < e + f * -g(h).i",
+            "(or a (and b (== c (< d (+ e (* f (- (. (call g h) i))))))))",
+        );
+        parses_to(
+            "a.i(h) * f - e > d != c and b or a",
+            "(or (and (!= (> (- (* (call (. a i) h) f) e) d) c) b) a)",
+        );
+        parses_to("a or b or c", "(or (or a b) c)");
+        parses_to("!a and -b", "(and (! a) (- b))");
+    }
+
+    #[test]
+    fn errors_describe_what_was_expected_and_where() {
+        let errors = parse_program("var x = (1 +\n;\nprint x\n").unwrap_err();
+        assert_eq!(errors.len(), 2);
+
+        assert_eq!(errors[0].expected, Some(Expected::Expression));
+        assert_eq!(errors[0].token.token_type, TokenType::Semicolon);
+        assert_eq!(errors[0].span, Span::new(13, 14));
+
+        assert_eq!(
+            errors[1].expected,
+            Some(Expected::Token(TokenType::Semicolon))
+        );
+        assert_eq!(errors[1].token.token_type, TokenType::Eof);
+        assert_eq!(errors[1].span, Span::new(23, 23));
+
+        let arguments = vec!["1"; MAX_ARGUMENTS + 1].join(", ");
+        let errors = parse_program(&format!("f({arguments});")).unwrap_err();
+        assert_eq!(errors[0].expected, None);
+    }
+
+    #[test]
+    fn parses_conditional_expressions() {
+        parses_to("a ? b : c", "(?: a b c)");
+        parses_to("a ? b : c ? d : e", "(?: a b (?: c d e))");
+        parses_to("a ? b ? c : d : e", "(?: a (?: b c d) e)");
+        parses_to(
+            "a or b ? c + 1 : d and e",
+            "(?: (or a b) (+ c 1) (and d e))",
+        );
+        parses_to("(a ? b : c) ? d : e", "(?: (group (?: a b c)) d e)");
+    }
+
+    #[test]
+    fn reports_conditional_without_else_branch() {
+        let error = parse("a ? b").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] Error at end: Expect ':' after then branch of conditional expression."
+        );
+        assert_eq!(error.expected, Some(Expected::Token(TokenType::Colon)));
+        assert_eq!(
+            parse("a ? b :").unwrap_err().to_string(),
+            "[line 1] Error at end: Expect expression."
+        );
+    }
+
+    #[test]
+    fn parses_comma_expressions() {
+        parses_to("a, b, c", "(, a b c)");
+        parses_to("a ? b, c : d, e", "(, (?: a (, b c) d) e)");
+        parses_to("(a, b) + c", "(+ (group (, a b)) c)");
+    }
+
+    #[test]
+    fn commas_in_argument_lists_separate_arguments() {
+        parses_to("f(a, b)", "(call f a b)");
+        parses_to("f((a, b), c)", "(call f (group (, a b)) c)");
+        program_parses_to(
+            "for (var i = 0; i < 3, j; i, j) print i;",
+            "(block (var i 0) (while (, (< i 3) j) (print i) (, i j)))",
+        );
+        assert_eq!(
+            program_error("var a = 1, b;"),
+            "[line 1] Error at ',': Expect ';' after variable declaration."
+        );
+    }
+
+    #[test]
+    fn parses_assignments() {
+        parses_to("a = 1", "(= a 1)");
+        parses_to("a = b = c + 1", "(= a (= b (+ c 1)))");
+        parses_to("a.b.c = d", "(= (. (. a b) c) d)");
+        parses_to("f(1).x = y ? 2 : 3", "(= (. (call f 1) x) (?: y 2 3))");
+        parses_to("a = 1, b = 2", "(, (= a 1) (= b 2))");
+        parses_to("f(a = 1, b)", "(call f (= a 1) b)");
+        program_parses_to("var a = b = 1;", "(var a (= b 1))");
+        program_parses_to(
+            "for (i = 0; i < 3; i = i + 1) {}",
+            "(block (; (= i 0)) (while (< i 3) (block) (= i (+ i 1))))",
+        );
+    }
+
+    #[test]
+    fn parses_array_literals_and_indexing() {
+        parses_to("[]", "(array)");
+        parses_to("[1, a + 2, [3]]", "(array 1 (+ a 2) (array 3))");
+        parses_to("a[0]", "([] a 0)");
+        parses_to("a[i][j + 1]", "([] ([] a i) (+ j 1))");
+        parses_to("-f()[0].x", "(- (. ([] (call f) 0) x))");
+        parses_to("[a, b][0]", "([] (array a b) 0)");
+        parses_to("a[i] = b[j] = 1", "(= ([] a i) (= ([] b j) 1))");
+        parses_to("a.b[0] = c", "(= ([] (. a b) 0) c)");
+        program_parses_to("var xs = [1, 2];", "(var xs (array 1 2))");
+    }
+
+    #[test]
+    fn reports_malformed_arrays_and_indexing() {
+        assert_eq!(
+            program_error("print [1, 2;"),
+            "[line 1] Error at ';': Expect ']' after array elements."
+        );
+        assert_eq!(
+            program_error("print a[1;"),
+            "[line 1] Error at ';': Expect ']' after index."
+        );
+        assert_eq!(
+            program_error("print a[];"),
+            "[line 1] Error at ']': Expect expression."
+        );
+    }
+
+    #[test]
+    fn parses_interpolated_strings() {
+        parses_to(r#""x = ${x + 1}""#, r#"(interpolate "x = " (+ x 1))"#);
+        parses_to(r#""${a}, ${b}!""#, r#"(interpolate a ", " b "!")"#);
+        parses_to(r#""${a, b}""#, "(interpolate (, a b))");
+        parses_to(
+            r#""a ${"b ${c}"} d""#,
+            r#"(interpolate "a " (interpolate "b " c) " d")"#,
+        );
+        parses_to(r#""n: ${n}" + s"#, r#"(+ (interpolate "n: " n) s)"#);
+        program_parses_to(
+            r#"print "x = ${x + 1}";"#,
+            r#"(print (interpolate "x = " (+ x 1)))"#,
+        );
+    }
+
+    #[test]
+    fn reports_malformed_interpolations() {
+        assert_eq!(
+            program_error(r#"print "a ${}";"#),
+            "[line 1] Error at '}': Expect expression."
+        );
+        assert_eq!(
+            program_error(r#"print "a ${b c}";"#),
+            "[line 1] Error at 'c': Expect '}' after interpolated expression."
+        );
+    }
+
+    #[test]
+    fn parses_map_literals() {
+        parses_to("{}", "(map)");
+        parses_to(
+            "{\"a b\": 1, c: d + 1, e: {}}",
+            "(map (: a b 1) (: c (+ d 1)) (: e (map)))",
+        );
+        parses_to("{a: 1, b: 2}[\"a\"]", "([] (map (: a 1) (: b 2)) a)");
+        program_parses_to("var m = {k: [1]};", "(var m (map (: k (array 1))))");
+        program_parses_to("print {};", "(print (map))");
+    }
+
+    #[test]
+    fn a_brace_starting_a_statement_is_a_block() {
+        program_parses_to("{}", "(block)");
+        program_parses_to("{ a; }", "(block (; a))");
+        program_parses_to("({a: 1});", "(; (group (map (: a 1))))");
+    }
+
+    #[test]
+    fn reports_malformed_map_literals() {
+        assert_eq!(
+            program_error("print {1: 2};"),
+            "[line 1] Error at '1': Expect map key."
+        );
+        assert_eq!(
+            program_error("print {a 2};"),
+            "[line 1] Error at '2': Expect ':' after map key."
+        );
+        assert_eq!(
+            program_error("print {a: 1;"),
+            "[line 1] Error at ';': Expect '}' after map entries."
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_assignment_targets() {
+        for src in [
+            "(a) = 3",
+            "a + b = c",
+            "-a = 1",
+            "a ? b : c = d",
+            "f() = 1",
+            "this = 1",
+            "[a] = 1",
+            "a?.b = 1",
+            "a?.b.c = 1",
+        ] {
+            let error = parse(src).unwrap_err();
+            assert_eq!(
+                error.message, "Invalid assignment target.",
+                "parsing {src:?}"
+            );
+            assert_eq!(error.token.token_type, TokenType::Equal, "parsing {src:?}");
+        }
+        assert_eq!(
+            program_error("1 = 2; print 3 = 4;"),
+            "[line 1] Error at '=': Invalid assignment target.\n\
+             [line 1] Error at '=': Invalid assignment target."
+        );
+    }
+
+    #[test]
+    fn assignment_in_a_condition_needs_parentheses() {
+        program_parses_to(
+            "while ((a = next()) != nil) print a;",
+            "(while (!= (group (= a (call next))) nil) (print a))",
+        );
+        assert_eq!(
+            program_error("if (a = 1) print a;"),
+            "[line 1] Error at '=': Use '==' to compare values in a condition; '=' assigns."
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_stack_overflow() {
+        let cases = [
+            "(".repeat(10_000),
+            "- ".repeat(10_000) + "1;",
+            "a = ".repeat(10_000) + "1;",
+            "{".repeat(10_000),
+            "if (a) ".repeat(10_000),
+            "fun f() {".repeat(10_000),
+            "class A { f() {".repeat(10_000),
+        ];
+        for src in cases {
+            let errors = parse_program(&src).unwrap_err();
+            assert!(
+                errors[0].message.ends_with(" too deeply nested."),
+                "parsing {:?}...: {}",
+                &src[..10],
+                errors[0]
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_up_to_the_limit_is_allowed() {
+        let src = format!("{}1{};", "(".repeat(50), ")".repeat(50));
+        let tokens = Scanner::new(&src).scan_tokens().unwrap();
+        assert!(
+            Parser::new(tokens.clone())
+                .with_max_depth(60)
+                .parse()
+                .is_ok()
+        );
+
+        let errors = Parser::new(tokens).with_max_depth(40).parse().unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 1] Error at '(': Expression too deeply nested."
+        );
+    }
+
+    #[test]
+    fn nodes_span_their_whole_source() {
+        let src = "print -a.b(1, 2) + (c ? d : e);";
+        let statements = parse_program(src).unwrap();
+        let Stmt::Print { expression, span } = &statements[0] else {
+            panic!("expected a print statement");
+        };
+        assert_eq!(span.slice(src), src);
+        assert_eq!(expression.span().slice(src), "-a.b(1, 2) + (c ? d : e)");
+
+        let Expr::Binary { left, right, .. } = &**expression else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(left.span().slice(src), "-a.b(1, 2)");
+        assert_eq!(right.span().slice(src), "(c ? d : e)");
+        let Expr::Unary { right: call, .. } = &**left else {
+            panic!("expected a unary expression");
+        };
+        assert_eq!(call.span().slice(src), "a.b(1, 2)");
+    }
+
+    #[test]
+    fn statement_spans_cover_keywords_and_bodies() {
+        let src = "class A < B { m(x) { return x = 1, 2; } }\nfor (;;) { a; }";
+        let statements = parse_program(src).unwrap();
+        assert_eq!(
+            statements[0].span().slice(src),
+            "class A < B { m(x) { return x = 1, 2; } }"
+        );
+        let Stmt::Class {
+            superclass,
+            methods,
+            ..
+        } = &statements[0]
+        else {
+            panic!("expected a class");
+        };
+        assert_eq!(superclass.as_ref().unwrap().span().slice(src), "B");
+        assert_eq!(methods[0].span().slice(src), "m(x) { return x = 1, 2; }");
+        let Stmt::Function { body, .. } = &methods[0] else {
+            panic!("expected a method");
+        };
+        assert_eq!(body[0].span().slice(src), "return x = 1, 2;");
+
+        // The desugared loop spans the whole `for`.
+        assert_eq!(statements[1].span().slice(src), "for (;;) { a; }");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::encoding::EncodingError;
+use crate::scanner::{Carry, ScanError, Scanner, ScannerOptions};
+use crate::token::Token;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+/// Scans tokens from a `BufRead` one line at a time, so only the current
+/// line is held in memory, along with the earlier lines of a string or doc
+/// comment still being scanned. A construct left open at the end of a line
+/// is carried on with from where it stopped, so each line is scanned once.
+/// Tokens carry the same lines, columns and spans as if the whole input
+/// had been passed to `Scanner`.
+pub struct ReaderScanner<R> {
+    reader: R,
+    options: ScannerOptions,
+    // The end of the previous lines that the next must be scanned after,
+    // followed by the line being scanned.
+    buffer: String,
+    // Byte offset of `buffer` within the input.
+    offset: usize,
+    carry: Carry,
+    line: Vec<u8>,
+    pending: VecDeque<Result<Token, ScanError>>,
+    finished: bool,
+}
+
+impl<R: BufRead> ReaderScanner<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ScannerOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ScannerOptions) -> Self {
+        Self {
+            reader,
+            options,
+            buffer: String::new(),
+            offset: 0,
+            carry: Carry::default(),
+            line: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    // Reads and scans the next line, queueing its tokens. Input that is not
+    // UTF-8 fails with an `InvalidData` error wrapping an
+    // [`EncodingError`].
+    fn fill(&mut self) -> io::Result<()> {
+        self.line.clear();
+        let at_eof = self.reader.read_until(b'\n', &mut self.line)? == 0;
+        let line = std::str::from_utf8(&self.line).map_err(|error| {
+            let offset = self.offset + self.buffer.len() + error.valid_up_to();
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                EncodingError::InvalidUtf8 { offset },
+            )
+        })?;
+        self.buffer.push_str(line);
+
+        let carry = std::mem::take(&mut self.carry);
+        let mut scanner = Scanner::continue_from(
+            &self.buffer,
+            Cow::Borrowed(&self.options),
+            self.offset,
+            carry,
+            !at_eof,
+        );
+        self.pending.extend(scanner.by_ref());
+        if at_eof {
+            self.finished = true;
+            return Ok(());
+        }
+
+        self.carry = scanner.carry();
+        self.buffer.drain(..self.carry.consumed);
+        self.offset += self.carry.consumed;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for ReaderScanner<R> {
+    type Item = io::Result<Result<Token, ScanError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(Ok(item));
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            if let Err(error) = self.fill() {
+                self.finished = true;
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token_type::TokenType;
+    use std::io::BufReader;
+
+    fn scan_reader(src: &str) -> Vec<Result<Token, ScanError>> {
+        let reader = BufReader::with_capacity(4, src.as_bytes());
+        ReaderScanner::new(reader)
+            .collect::<io::Result<Vec<_>>>()
+            .expect("reading from a byte slice cannot fail")
+    }
+
+    fn scan_whole(src: &str) -> Vec<Result<Token, ScanError>> {
+        Scanner::new(src).collect()
+    }
+
+    #[test]
+    fn matches_whole_source_scanning() {
+        let src =
+            "var a = \"multi\nline\";\n/* block\ncomment */ print a;\n\n\"x ${\n  a\n} y\" + 1.5";
+        assert_eq!(scan_reader(src), scan_whole(src));
+    }
+
+    #[test]
+    fn reports_errors_at_their_absolute_positions() {
+        let src = "print 1;\n  @\n\"unterminated\n";
+        assert_eq!(scan_reader(src), scan_whole(src));
+    }
+
+    #[test]
+    fn skips_shebang_on_the_first_line_only() {
+        let src = "#!/usr/bin/env rlox\nprint 1;\n";
+        assert_eq!(scan_reader(src), scan_whole(src));
+    }
+
+    #[test]
+    fn keeps_multi_line_doc_comments_together() {
+        let src = "/// one\n/// two\n\n/// three\nfun f() {}\n";
+        assert_eq!(scan_reader(src), scan_whole(src));
+    }
+
+    #[test]
+    fn newline_terminators_see_across_open_parentheses() {
+        let src = "print f(a,\n  b\n)\nprint 2";
+        let options = ScannerOptions {
+            newline_terminators: true,
+            ..ScannerOptions::default()
+        };
+        let reader = BufReader::with_capacity(4, src.as_bytes());
+        let from_reader: Vec<_> = ReaderScanner::with_options(reader, options.clone())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let whole: Vec<_> = Scanner::new_with_options(src, options).collect();
+        assert_eq!(from_reader, whole);
+    }
+
+    #[test]
+    fn empty_input_yields_only_eof() {
+        let items = scan_reader("");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].as_ref().unwrap().token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn invalid_utf8_is_an_io_error() {
+        let bytes: &[u8] = b"print 1;\n\xff\n";
+        let results: Vec<_> = ReaderScanner::new(bytes).collect();
+        assert!(results.iter().any(|r| r.is_err()));
+    }
+
+    #[test]
+    fn resumes_constructs_left_open_at_the_end_of_a_line() {
+        let sources = [
+            "/* a\n/* b\n */ c */ print 1;\n",
+            "print 1; /* a\nb",
+            "var s = \"abc\ndef\"; print s;\n",
+            "var s = \"abc\ndef\nprint 2;\n",
+            "var s = \"\"\"\n  one\n  \"\"\"; print s;\n",
+            "var s = \"\"\"\n  one\nprint 3;\n",
+            "/// a\n/// b",
+            "print \"x ${\n 1 +\n 2\n} y\n z\";\n",
+            "print \"x ${\n 1 +\n",
+        ];
+        for src in sources {
+            assert_eq!(scan_reader(src), scan_whole(src), "scanning {src:?}");
+        }
+    }
+
+    #[test]
+    fn keeps_no_lines_of_an_open_block_comment() {
+        let src = format!("/*\n{}", "a line of a long comment\n".repeat(100));
+        let mut scanner = ReaderScanner::new(src.as_bytes());
+        for _ in 0..100 {
+            scanner.fill().unwrap();
+            assert_eq!(scanner.buffer, "");
+        }
+    }
+}
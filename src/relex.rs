@@ -0,0 +1,278 @@
+use crate::scanner::Scanner;
+use crate::span::Span;
+use crate::token::Token;
+use crate::token_type::TokenType;
+use serde::Serialize;
+
+/// A change to a source text: the bytes in `range` of the old text are
+/// replaced with `text`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextEdit {
+    pub range: Span,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Span, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+
+    /// Returns `source` with the edit applied.
+    pub fn apply(&self, source: &str) -> String {
+        let mut edited = String::with_capacity(source.len() + self.text.len());
+        edited.push_str(&source[..self.range.start]);
+        edited.push_str(&self.text);
+        edited.push_str(&source[self.range.end..]);
+        edited
+    }
+
+    // How far text after the edit moves.
+    fn delta(&self) -> isize {
+        self.text.len() as isize - (self.range.end - self.range.start) as isize
+    }
+}
+
+// What the scanner carries from one token to the next, tracked over an
+// old token list so scanning can restart or stop at any token.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct State {
+    interpolation_depth: usize,
+    paren_depth: usize,
+    last_token: Option<TokenType>,
+}
+
+impl State {
+    fn after(mut self, token: &Token) -> State {
+        match token.token_type {
+            TokenType::InterpolationStart => self.interpolation_depth += 1,
+            TokenType::InterpolationEnd => self.interpolation_depth -= 1,
+            TokenType::LeftParen | TokenType::LeftBracket => self.paren_depth += 1,
+            TokenType::RightParen | TokenType::RightBracket => {
+                self.paren_depth = self.paren_depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+        if token.token_type != TokenType::DocComment {
+            self.last_token = Some(token.token_type);
+        }
+        self
+    }
+}
+
+// Whether the point just before `tokens[i]` is outside every string and
+// interpolation, where a fresh scanner could start or a full scan would be
+// between tokens.
+fn is_top_level(tokens: &[Token], states: &[State], i: usize) -> bool {
+    states[i].interpolation_depth == 0
+        && tokens
+            .get(i)
+            .is_none_or(|token| token.token_type != TokenType::InterpolationStart)
+        && (i == 0 || tokens[i - 1].token_type != TokenType::InterpolationEnd)
+}
+
+// Tokens the newline-terminator mode inserts have no `;` in the source.
+fn is_synthetic(token: &Token) -> bool {
+    token.token_type == TokenType::Semicolon && token.lexeme != ";"
+}
+
+impl Scanner<'_> {
+    /// Re-scans only the part of an edited source that the edit can affect.
+    ///
+    /// `self` must be a fresh scanner over the source *after* the edit, and
+    /// `old_tokens` the tokens of the source before it, as produced by a
+    /// scanner with the same options. Scanning restarts a couple of tokens
+    /// before the edit and stops as soon as it produces a token that
+    /// matches an old one past the edit in the same scanner state; the
+    /// remaining old tokens are reused with their positions shifted.
+    ///
+    /// The result is the token list a full scan of the new source would
+    /// yield, including `Eof`. Like the token list it replaces, it holds no
+    /// errors; lexical errors are skipped.
+    pub fn relex(self, old_tokens: &[Token], edit: &TextEdit) -> Vec<Token> {
+        let mut states = Vec::with_capacity(old_tokens.len() + 1);
+        let mut state = State::default();
+        states.push(state);
+        for token in old_tokens {
+            state = state.after(token);
+            states.push(state);
+        }
+
+        // Restart at a single-line token that ends more than two bytes before
+        // the edit, since the scanner looks up to two characters ahead.
+        let restart = (0..old_tokens.len())
+            .rev()
+            .find(|&i| {
+                let token = &old_tokens[i];
+                token.span.end + 2 < edit.range.start
+                    && is_top_level(old_tokens, &states, i)
+                    && !is_synthetic(token)
+                    && !token.lexeme.as_str().contains('\n')
+            })
+            .unwrap_or(0);
+        let (offset, line, column) = match old_tokens.get(restart) {
+            Some(token) if restart > 0 => (token.span.start, token.line, token.column),
+            _ => (0, 1, 1),
+        };
+
+        let (source, options) = self.into_source_and_options();
+        let mut scanner = Scanner::resume(&source[offset..], options, offset, line, column);
+        scanner.set_statement_state(states[restart].last_token, states[restart].paren_depth);
+
+        let delta = edit.delta();
+        let edit_end = (edit.range.start + edit.text.len()) as isize;
+        let mut tokens = old_tokens[..restart].to_vec();
+        while let Some(result) = scanner.next() {
+            let Ok(token) = result else {
+                continue;
+            };
+            // Look for an old token past the edit that this one repeats,
+            // with the scanner in the same state after both.
+            let old_start = token.span.start as isize - delta;
+            let synced = (token.span.start as isize >= edit_end && scanner.is_between_tokens())
+                .then(|| old_tokens.partition_point(|old| (old.span.start as isize) < old_start))
+                .and_then(|first| {
+                    (first..old_tokens.len())
+                        .take_while(|&j| old_tokens[j].span.start as isize == old_start)
+                        .find(|&j| {
+                            let old = &old_tokens[j];
+                            let after = states[j + 1];
+                            old.token_type == token.token_type
+                                && old.lexeme == token.lexeme
+                                && old.literal == token.literal
+                                && old.column == token.column
+                                && is_top_level(old_tokens, &states, j + 1)
+                                && (after.last_token, after.paren_depth)
+                                    == scanner.statement_state()
+                        })
+                });
+
+            if let Some(j) = synced {
+                let line_delta = token.line as isize - old_tokens[j].line as isize;
+                tokens.push(token);
+                tokens.extend(old_tokens[j + 1..].iter().map(|old| {
+                    let mut token = old.clone();
+                    token.line = (token.line as isize + line_delta) as usize;
+                    token.span = Span::new(
+                        (token.span.start as isize + delta) as usize,
+                        (token.span.end as isize + delta) as usize,
+                    );
+                    token
+                }));
+                return tokens;
+            }
+            tokens.push(token);
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScannerOptions;
+
+    fn scan_ok(src: &str, options: &ScannerOptions) -> Vec<Token> {
+        Scanner::new_with_options(src, options.clone())
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    // Applies `edit` to `old` and checks relexing matches a full rescan.
+    fn check_with(old: &str, edit: TextEdit, options: &ScannerOptions) {
+        let old_tokens = scan_ok(old, options);
+        let new = edit.apply(old);
+        let relexed = Scanner::new_with_options(&new, options.clone()).relex(&old_tokens, &edit);
+        assert_eq!(
+            relexed,
+            scan_ok(&new, options),
+            "editing {old:?} into {new:?}"
+        );
+    }
+
+    fn check(old: &str, edit: TextEdit) {
+        check_with(old, edit, &ScannerOptions::default());
+    }
+
+    const PROGRAM: &str =
+        "var a = 1;\nfun f(x) {\n  return x + a;\n}\nprint f(2); // done\nprint \"s ${a} t\";\n";
+
+    #[test]
+    fn relexes_edits_within_a_token() {
+        check(PROGRAM, TextEdit::new(Span::new(4, 5), "alpha"));
+        check(PROGRAM, TextEdit::new(Span::new(8, 9), "1.5"));
+        check(PROGRAM, TextEdit::new(Span::new(0, 0), "  "));
+    }
+
+    #[test]
+    fn relexes_edits_that_add_and_remove_lines() {
+        check(PROGRAM, TextEdit::new(Span::new(10, 10), "\nvar b = 2;\n"));
+        check(PROGRAM, TextEdit::new(Span::new(11, 23), ""));
+        check(
+            PROGRAM,
+            TextEdit::new(Span::new(PROGRAM.len(), PROGRAM.len()), "a\n"),
+        );
+    }
+
+    #[test]
+    fn relexes_edits_that_change_later_tokens() {
+        // Opening a comment or string changes everything after it.
+        check(PROGRAM, TextEdit::new(Span::new(11, 11), "/* "));
+        check(PROGRAM, TextEdit::new(Span::new(11, 11), "\""));
+        check(PROGRAM, TextEdit::new(Span::new(72, 73), ""));
+        check(PROGRAM, TextEdit::new(Span::new(74, 74), "${"));
+    }
+
+    #[test]
+    fn relexes_edits_in_every_position() {
+        for start in 0..=PROGRAM.len() {
+            for text in ["", "x", "\"", "/*", "*/", "}", "\n", "${"] {
+                check(PROGRAM, TextEdit::new(Span::new(start, start), text));
+                if start < PROGRAM.len() {
+                    check(PROGRAM, TextEdit::new(Span::new(start, start + 1), text));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn relexes_multi_line_tokens_and_comments() {
+        let src = "/// doc\n/// more\nvar s = \"one\ntwo\";\n/* a\n b */ var t = \"\"\"\n  x\n  \"\"\";\n\tprint s;";
+        for start in 0..=src.len() {
+            for text in ["", "x", "\"", "/", "*", "\n", "\t"] {
+                check(src, TextEdit::new(Span::new(start, start), text));
+                if start + 2 <= src.len() {
+                    check(src, TextEdit::new(Span::new(start, start + 2), text));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn relexes_with_newline_terminators() {
+        let options = ScannerOptions {
+            newline_terminators: true,
+            ..ScannerOptions::default()
+        };
+        let src = "var a = f(1,\n  2)\nprint a\n{ print a }\n";
+        for start in 0..=src.len() {
+            for text in ["", "x", "(", ")", "\n"] {
+                check_with(src, TextEdit::new(Span::new(start, start), text), &options);
+            }
+        }
+    }
+
+    #[test]
+    fn reuses_tokens_after_the_edit() {
+        let old = "var a = 1;\nvar b = 2;\nvar c = 3;\n";
+        let edit = TextEdit::new(Span::new(8, 9), "10");
+        let new = edit.apply(old);
+        let relexed = Scanner::new(&new).relex(&scan_ok(old, &ScannerOptions::default()), &edit);
+        let c = &relexed[11];
+        assert_eq!(c.lexeme, "c");
+        assert_eq!((c.line, c.column), (3, 5));
+        assert_eq!(c.span, Span::new(27, 28));
+    }
+}
@@ -0,0 +1,614 @@
+use crate::ast::{Expr, ExprVisitor, Object, Stmt, StmtVisitor};
+use crate::span::Span;
+use crate::symbol::Symbol;
+use crate::token::Token;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where each local variable was resolved to. References are keyed by the
+/// span of their name, or of their `this` or `super` keyword, and local
+/// declarations by the span of the name they declare. A reference or
+/// declaration that isn't here is of a global.
+pub type Locals = HashMap<Span, Slot>;
+
+/// Where a local variable lives at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    /// How many scopes out from the reference the variable is declared.
+    /// For a declaration it is 0.
+    pub depth: usize,
+    /// Which of its scope's variables it is, counting in the order they
+    /// are declared.
+    pub index: usize,
+}
+
+/// A reference or statement that can't mean anything where it is, such as
+/// `this` outside of a class, reported at its token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+    /// Where `token` is in the source.
+    pub span: Span,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] Error at '{}': {}",
+            self.token.line, self.token.lexeme, self.message
+        )
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+// The variables declared so far in a local scope.
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<Symbol, Variable>,
+    // How many slots the scope has used. Declaring a name again reuses its
+    // slot, as the interpreter overwrites it, so this can be more than the
+    // number of names only when parameters share a name.
+    slots: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Variable {
+    slot: usize,
+    // Whether the declaration has finished, so the variable can be read.
+    defined: bool,
+}
+
+// What kind of function the code being resolved is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FunctionKind {
+    None,
+    Function,
+}
+
+// What kind of class the code being resolved is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Works out, before a program runs, which declaration each variable
+/// reference means, so the interpreter can go straight to the right scope
+/// instead of searching outwards for the name.
+///
+/// That also pins a reference down when the function holding it is
+/// declared: a closure keeps meaning the variable it saw even if the
+/// block around it later declares another with the same name.
+///
+/// The resolver mirrors the scopes the interpreter makes: one per block,
+/// switch case and call, one holding `this` around each class's members,
+/// and one holding `super` outside that for a subclass. It numbers each
+/// scope's variables in the order the interpreter declares them, so the
+/// interpreter can keep a scope's variables in a `Vec` instead of a map.
+///
+/// It also reports what can be found wrong without running the program:
+/// a `return` outside any function, `this` outside of a class, `super`
+/// outside of a subclass, a class inheriting from itself, and a local
+/// variable read in its own initializer.
+pub struct Resolver {
+    // The local scopes around the code being resolved, innermost last.
+    // The global scope isn't here, so a name found nowhere is global.
+    scopes: Vec<Scope>,
+    locals: Locals,
+    function: FunctionKind,
+    class: ClassKind,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            locals: Locals::new(),
+            function: FunctionKind::None,
+            class: ClassKind::None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves every local variable reference in `statements`, or reports
+    /// every error found.
+    pub fn resolve(mut self, statements: &[Stmt]) -> Result<Locals, Vec<ResolveError>> {
+        self.resolve_statements(statements);
+        if self.errors.is_empty() {
+            Ok(self.locals)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolveError {
+            token: token.clone(),
+            message: message.to_string(),
+            span: token.span,
+        });
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    // Runs `resolve` inside a new scope declaring `names`, in slots
+    // numbered in order. If a name repeats, the last one is the one seen.
+    fn scoped(&mut self, names: &[Symbol], resolve: impl FnOnce(&mut Self)) {
+        let variables = names.iter().enumerate().map(|(slot, &name)| {
+            let variable = Variable {
+                slot,
+                defined: true,
+            };
+            (name, variable)
+        });
+        self.scopes.push(Scope {
+            variables: variables.collect(),
+            slots: names.len(),
+        });
+        resolve(self);
+        self.scopes.pop();
+    }
+
+    // Declares `name` in the innermost scope, with `defined` saying
+    // whether it can be read yet. At the top level it is a global, which
+    // the resolver doesn't track.
+    fn declare(&mut self, name: &Token, defined: bool) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        let slot = match scope.variables.get(&name.lexeme.symbol()) {
+            Some(variable) => variable.slot,
+            None => {
+                scope.slots += 1;
+                scope.slots - 1
+            }
+        };
+        scope
+            .variables
+            .insert(name.lexeme.symbol(), Variable { slot, defined });
+        self.locals.insert(
+            name.span,
+            Slot {
+                depth: 0,
+                index: slot,
+            },
+        );
+    }
+
+    fn resolve_local(&mut self, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                let variable = scope.variables.get(&name.lexeme.symbol())?;
+                Some(Slot {
+                    depth,
+                    index: variable.slot,
+                })
+            });
+        if let Some(slot) = found {
+            self.locals.insert(name.span, slot);
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt]) {
+        let enclosing = std::mem::replace(&mut self.function, FunctionKind::Function);
+        let params: Vec<_> = params.iter().map(|param| param.lexeme.symbol()).collect();
+        self.scoped(&params, |resolver| resolver.resolve_statements(body));
+        self.function = enclosing;
+    }
+
+    fn resolve_exprs(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.visit_expr(expr);
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_array_expr(&mut self, elements: &[Expr]) {
+        self.resolve_exprs(elements);
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr) {
+        self.visit_expr(value);
+        self.resolve_local(name);
+    }
+
+    fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, _paren: &Token, arguments: &[Expr]) {
+        self.visit_expr(callee);
+        self.resolve_exprs(arguments);
+    }
+
+    fn visit_comma_expr(&mut self, expressions: &[Expr]) {
+        self.resolve_exprs(expressions);
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+        self.visit_expr(object);
+    }
+
+    fn visit_grouping_expr(&mut self, expression: &Expr) {
+        self.visit_expr(expression);
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+    }
+
+    fn visit_interpolation_expr(&mut self, parts: &[Expr]) {
+        self.resolve_exprs(parts);
+    }
+
+    fn visit_literal_expr(&mut self, _value: &Object) {}
+
+    fn visit_logical_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
+        self.visit_expr(left);
+        self.visit_expr(right);
+    }
+
+    fn visit_map_expr(&mut self, keys: &[Expr], values: &[Expr]) {
+        for (key, value) in keys.iter().zip(values) {
+            self.visit_expr(key);
+            self.visit_expr(value);
+        }
+    }
+
+    fn visit_optional_chain_expr(&mut self, expression: &Expr) {
+        self.visit_expr(expression);
+    }
+
+    fn visit_optional_get_expr(&mut self, object: &Expr, _name: &Token) {
+        self.visit_expr(object);
+    }
+
+    fn visit_set_expr(&mut self, object: &Expr, _name: &Token, value: &Expr) {
+        self.visit_expr(object);
+        self.visit_expr(value);
+    }
+
+    fn visit_set_index_expr(
+        &mut self,
+        object: &Expr,
+        _bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) {
+        self.visit_expr(object);
+        self.visit_expr(index);
+        self.visit_expr(value);
+    }
+
+    fn visit_super_expr(&mut self, keyword: &Token, _method: &Token) {
+        match self.class {
+            ClassKind::None => self.error(keyword, "Can't use 'super' outside of a class."),
+            ClassKind::Class => {
+                self.error(keyword, "Can't use 'super' in a class with no superclass.");
+            }
+            ClassKind::Subclass => self.resolve_local(keyword),
+        }
+    }
+
+    fn visit_ternary_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+        self.visit_expr(condition);
+        self.visit_expr(then_branch);
+        self.visit_expr(else_branch);
+    }
+
+    fn visit_this_expr(&mut self, keyword: &Token) {
+        if self.class == ClassKind::None {
+            self.error(keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+        self.resolve_local(keyword);
+    }
+
+    fn visit_unary_expr(&mut self, _operator: &Token, right: &Expr) {
+        self.visit_expr(right);
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token) {
+        let variable = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.variables.get(&name.lexeme.symbol()));
+        if variable.is_some_and(|variable| !variable.defined) {
+            self.error(name, "Can't read local variable in its own initializer.");
+        }
+        self.resolve_local(name);
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) {
+        self.scoped(&[], |resolver| resolver.resolve_statements(statements));
+    }
+
+    fn visit_break_stmt(&mut self, _keyword: &Token) {}
+
+    fn visit_case_stmt(&mut self, _keyword: &Token, _values: &[Expr], _body: &[Stmt]) {
+        unreachable!("a case is resolved as part of its switch")
+    }
+
+    // The class's name is declared before its members are resolved, so
+    // they can refer to it, but after its superclass, which is evaluated
+    // before the class exists.
+    fn visit_class_stmt(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Stmt],
+        getters: &[Stmt],
+        setters: &[Stmt],
+        class_methods: &[Stmt],
+    ) {
+        if let Some(superclass) = superclass {
+            if let Expr::Variable { name: parent, .. } = superclass
+                && parent.lexeme == name.lexeme
+            {
+                self.error(parent, "A class can't inherit from itself.");
+            }
+            self.visit_expr(superclass);
+        }
+        self.declare(name, true);
+        let enclosing = std::mem::replace(
+            &mut self.class,
+            match superclass {
+                Some(_) => ClassKind::Subclass,
+                None => ClassKind::Class,
+            },
+        );
+        let resolve_members = |resolver: &mut Self| {
+            resolver.scoped(&[Symbol::THIS], |resolver| {
+                let members = methods.iter().chain(getters).chain(setters);
+                for member in members.chain(class_methods) {
+                    let Stmt::Function { params, body, .. } = member else {
+                        unreachable!("the parser only puts functions in a class body")
+                    };
+                    resolver.resolve_function(params, body);
+                }
+            });
+        };
+        match superclass {
+            Some(_) => self.scoped(&[Symbol::SUPER], resolve_members),
+            None => resolve_members(self),
+        }
+        self.class = enclosing;
+    }
+
+    fn visit_continue_stmt(&mut self, _keyword: &Token) {}
+
+    fn visit_do_while_stmt(&mut self, body: &Stmt, condition: &Expr) {
+        self.visit_stmt(body);
+        self.visit_expr(condition);
+    }
+
+    fn visit_expression_stmt(&mut self, expression: &Expr) {
+        self.visit_expr(expression);
+    }
+
+    // The name is declared before the body is resolved, so the function
+    // can call itself.
+    fn visit_function_stmt(&mut self, name: &Token, params: &[Token], body: &[Stmt]) {
+        self.declare(name, true);
+        self.resolve_function(params, body);
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) {
+        self.visit_expr(condition);
+        self.visit_stmt(then_branch);
+        if let Some(else_branch) = else_branch {
+            self.visit_stmt(else_branch);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, expression: &Expr) {
+        self.visit_expr(expression);
+    }
+
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) {
+        if self.function == FunctionKind::None {
+            self.error(keyword, "Can't return from top-level code.");
+        }
+        if let Some(value) = value {
+            self.visit_expr(value);
+        }
+    }
+
+    fn visit_switch_stmt(&mut self, _keyword: &Token, subject: &Expr, cases: &[Stmt]) {
+        self.visit_expr(subject);
+        for case in cases {
+            let Stmt::Case { values, body, .. } = case else {
+                unreachable!("the parser only puts cases in a switch")
+            };
+            self.resolve_exprs(values);
+            self.scoped(&[], |resolver| resolver.resolve_statements(body));
+        }
+    }
+
+    // The name is declared before the initializer is resolved, so that
+    // reading a local variable in its own initializer is caught. Globals
+    // aren't tracked, so `var a = a;` at the top level reads the old `a`.
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) {
+        self.declare(name, false);
+        if let Some(initializer) = initializer {
+            self.visit_expr(initializer);
+        }
+        self.declare(name, true);
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt, increment: Option<&Expr>) {
+        self.visit_expr(condition);
+        self.visit_stmt(body);
+        if let Some(increment) = increment {
+            self.visit_expr(increment);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scanner::Scanner;
+
+    // The depth each reference to `name` resolved to, in source order, or
+    // `None` for a global. Declarations of `name` are skipped.
+    fn depths(source: &str, name: &str) -> Vec<Option<usize>> {
+        let program = crate::check(source).expect("source parses");
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        tokens
+            .windows(2)
+            .filter(|pair| pair[1].lexeme == name)
+            .filter(|pair| !matches!(pair[0].lexeme.as_str(), "var" | "fun" | "class" | "("))
+            .map(|pair| program.locals.get(&pair[1].span).map(|slot| slot.depth))
+            .collect()
+    }
+
+    // The slot each declaration of a local variable was given, in source
+    // order.
+    fn declared_slots(source: &str) -> Vec<usize> {
+        let program = crate::check(source).expect("source parses");
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        tokens
+            .windows(2)
+            .filter(|pair| matches!(pair[0].lexeme.as_str(), "var" | "fun" | "class"))
+            .filter_map(|pair| program.locals.get(&pair[1].span))
+            .map(|slot| slot.index)
+            .collect()
+    }
+
+    #[test]
+    fn counts_scopes_out_to_the_declaration() {
+        assert_eq!(
+            depths("{ var a = 1; { { print a; } } }", "a"),
+            vec![Some(2)]
+        );
+        assert_eq!(depths("var a = 1; { print a; }", "a"), vec![None]);
+        assert_eq!(
+            depths("{ var a = 1; { var a = 2; a = a; } print a; }", "a"),
+            vec![Some(0), Some(0), Some(0)]
+        );
+    }
+
+    #[test]
+    fn numbers_each_scopes_variables_in_declaration_order() {
+        assert_eq!(
+            declared_slots("var g; { var a; fun b() { var c; } { var d; } class E {} }"),
+            vec![0, 1, 0, 0, 2]
+        );
+        assert_eq!(declared_slots("fun f(p, q) { var r; }"), vec![2]);
+        assert_eq!(declared_slots("{ var a; var b; var a; }"), vec![0, 1, 0]);
+
+        let program = crate::check("{ var a; var b; { print b; } }").unwrap();
+        let slots: Vec<_> = program.locals.values().copied().collect();
+        assert!(slots.contains(&super::Slot { depth: 1, index: 1 }));
+    }
+
+    fn errors(source: &str) -> String {
+        crate::check(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn function_bodies_share_the_parameters_scope() {
+        assert_eq!(
+            depths("fun f(p) { var l; print p; { print p; } } ", "p"),
+            vec![Some(0), Some(1)]
+        );
+        assert_eq!(depths("{ fun f() { return f; } }", "f"), vec![Some(1)]);
+    }
+
+    #[test]
+    fn class_members_see_this_and_super() {
+        assert_eq!(
+            depths(
+                "class A {} class B < A { m() { this; super.m; } get { return this; } }",
+                "this"
+            ),
+            vec![Some(1), Some(1)]
+        );
+        assert_eq!(
+            depths("class A {} class B < A { m() { this; super.m; } }", "super"),
+            vec![Some(2)]
+        );
+    }
+
+    #[test]
+    fn reports_returns_outside_functions() {
+        assert_eq!(
+            errors("print 1;\nreturn;"),
+            "[line 2] Error at 'return': Can't return from top-level code."
+        );
+        assert_eq!(
+            errors("{ if (true) return 1; }"),
+            "[line 1] Error at 'return': Can't return from top-level code."
+        );
+        assert!(crate::check("fun f() { { return 1; } } class A { m() { return; } }").is_ok());
+    }
+
+    #[test]
+    fn reports_this_and_super_outside_classes() {
+        assert_eq!(
+            errors("print this;\nfun f() { this.x = 1; }"),
+            "[line 1] Error at 'this': Can't use 'this' outside of a class.\n\
+             [line 2] Error at 'this': Can't use 'this' outside of a class."
+        );
+        assert_eq!(
+            errors("super.f();"),
+            "[line 1] Error at 'super': Can't use 'super' outside of a class."
+        );
+        assert_eq!(
+            errors("class A { f() { super.f(); } }"),
+            "[line 1] Error at 'super': Can't use 'super' in a class with no superclass."
+        );
+        assert_eq!(
+            errors("class A {} class B < A { f() { class C { g() { super.g(); } } } }"),
+            "[line 1] Error at 'super': Can't use 'super' in a class with no superclass."
+        );
+        assert!(
+            crate::check(
+                "class A {} class B < A { f() { fun g() { return this; } super.f(); } \
+                 class h() { return this; } }"
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn reports_a_class_inheriting_from_itself() {
+        assert_eq!(
+            errors("class A < A {}"),
+            "[line 1] Error at 'A': A class can't inherit from itself."
+        );
+    }
+
+    #[test]
+    fn reports_reading_a_local_in_its_own_initializer() {
+        assert_eq!(
+            errors("{ var a = 1; {\n var a = a + 1; } }"),
+            "[line 2] Error at 'a': Can't read local variable in its own initializer."
+        );
+        assert!(crate::check("var a = 1; var a = a + 1;").is_ok());
+    }
+}
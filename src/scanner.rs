@@ -1,35 +1,475 @@
 use crate::helpers::{is_alpha, is_alpha_numeric, is_digit, keyword_type};
-use crate::token::{Literal, Token};
+use crate::source_map::{self, SourceMap};
+use crate::span::Span;
+use crate::token::{BorrowedToken, Literal, Token};
 use crate::token_type::TokenType;
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// Dialect switches for the scanner. The defaults accept everything rlox
+/// supports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannerOptions {
+    /// Whether `/* /* */ */` nests, or ends at the first `*/`.
+    pub nested_comments: bool,
+    /// Whether a `#!` line at the start of the input is skipped.
+    pub allow_shebang: bool,
+    /// Whether identifiers may use Unicode letters, or only ASCII.
+    pub unicode_identifiers: bool,
+    /// Additional reserved words, checked after the built-in keywords.
+    pub extra_keywords: HashMap<String, TokenType>,
+    /// Whether a line break ends a statement. When set, a synthetic
+    /// `Semicolon` is emitted at a newline, before a `}`, or at the end of
+    /// input if the preceding token can end a statement, unless we are
+    /// inside parentheses, brackets or a string interpolation.
+    pub newline_terminators: bool,
+    /// Columns a tab advances to the next stop of, for column reporting.
+    pub tab_width: usize,
+}
+
+impl Default for ScannerOptions {
+    fn default() -> Self {
+        Self {
+            nested_comments: true,
+            allow_shebang: true,
+            unicode_identifiers: true,
+            extra_keywords: HashMap::new(),
+            newline_terminators: false,
+            tab_width: source_map::DEFAULT_TAB_WIDTH,
+        }
+    }
+}
+
+impl ScannerOptions {
+    /// The dialect of the book's jlox where our switches allow it: block
+    /// comments do not nest, identifiers are ASCII, and `#!` lines are not
+    /// skipped.
+    pub fn crafting_interpreters() -> Self {
+        Self {
+            nested_comments: false,
+            allow_shebang: false,
+            unicode_identifiers: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// A lexical error, located at the offending text in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+/// Where scanning a fragment of a larger input left off, for scanning the
+/// next fragment from, see [`Scanner::carry`].
+#[derive(Debug, Clone)]
+pub(crate) struct Carry {
+    /// How many bytes of the fragment are done with. The next fragment
+    /// starts with the rest of this one.
+    pub(crate) consumed: usize,
+    line: usize,
+    column: usize,
+    last_token: Option<TokenType>,
+    paren_depth: usize,
+    interpolations: Vec<usize>,
+    open: Option<Open>,
+}
+
+impl Default for Carry {
+    fn default() -> Self {
+        Self {
+            consumed: 0,
+            line: 1,
+            column: 1,
+            last_token: None,
+            paren_depth: 0,
+            interpolations: Vec::new(),
+            open: None,
+        }
+    }
+}
+
+// A construct a fragment ended inside, with what scanning it so far found.
+// Those that make a token start the next fragment, and are resumed at
+// `at` bytes into it, on the given line and column. A block comment makes
+// no token, so none of its text is kept: its line, column and byte offset
+// are where it was opened, for reporting it if it is never closed.
+#[derive(Debug, Clone)]
+enum Open {
+    BlockComment {
+        depth: usize,
+        opened: usize,
+        line: usize,
+        column: usize,
+    },
+    String {
+        continuation: bool,
+        value: String,
+        at: usize,
+        line: usize,
+        column: usize,
+    },
+    TripleQuotedString {
+        at: usize,
+        line: usize,
+        column: usize,
+    },
+    DocComment {
+        lines: Vec<String>,
+        at: usize,
+        line: usize,
+        column: usize,
+    },
+}
 
 pub struct Scanner<'a> {
     source: &'a str,
-    tokens: Vec<Token>,
+    options: Cow<'a, ScannerOptions>,
+    // Byte offset of `source` within the overall input, for spans.
+    offset: usize,
+    // Whether more input follows `source`, so that running out of it inside
+    // a string or comment leaves the construct open rather than reporting
+    // it unterminated.
+    partial: bool,
+    // The construct `source` ended inside of, or the one carried over from
+    // the fragment before to resume first.
+    open: Option<Open>,
+    // Tokens and errors produced but not yet handed out, in source order.
+    pending: VecDeque<Result<BorrowedToken<'a>, ScanError>>,
+    finished: bool,
     start: usize,
     current: usize,
     line: usize,
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    // Brace depth for each `${` we are currently inside, innermost last.
+    interpolations: Vec<usize>,
+    source_map: SourceMap<'a>,
+    // For newline terminators: the last token emitted, ignoring doc
+    // comments, and how many parentheses and brackets are open.
+    last_token: Option<TokenType>,
+    paren_depth: usize,
 }
 
 impl<'a> Scanner<'a> {
     pub fn new(source: &'a str) -> Self {
-        Self {
+        Self::new_with_options(source, ScannerOptions::default())
+    }
+
+    pub fn new_with_options(source: &'a str, options: ScannerOptions) -> Self {
+        Self::resume(source, Cow::Owned(options), 0, 1, 1)
+    }
+
+    /// Creates a scanner for the fragment of a larger input that follows
+    /// the one `carry` came from and begins at byte `offset`. `partial` is
+    /// set if more input follows it in turn.
+    pub(crate) fn continue_from(
+        source: &'a str,
+        options: Cow<'a, ScannerOptions>,
+        offset: usize,
+        carry: Carry,
+        partial: bool,
+    ) -> Self {
+        let mut scanner = Self::resume(source, options, offset, carry.line, carry.column);
+        scanner.partial = partial;
+        scanner.last_token = carry.last_token;
+        scanner.paren_depth = carry.paren_depth;
+        scanner.interpolations = carry.interpolations;
+        match &carry.open {
+            Some(Open::BlockComment { line, column, .. }) => {
+                (scanner.start_line, scanner.start_column) = (*line, *column);
+            }
+            Some(
+                Open::String {
+                    at, line, column, ..
+                }
+                | Open::TripleQuotedString { at, line, column }
+                | Open::DocComment {
+                    at, line, column, ..
+                },
+            ) => {
+                scanner.current = *at;
+                (scanner.line, scanner.column) = (*line, *column);
+            }
+            None => {}
+        }
+        scanner.open = carry.open;
+        scanner
+    }
+
+    /// Creates a scanner for a fragment of a larger input that begins at
+    /// byte `offset`, on the given line and column.
+    pub(crate) fn resume(
+        source: &'a str,
+        options: Cow<'a, ScannerOptions>,
+        offset: usize,
+        line: usize,
+        column: usize,
+    ) -> Self {
+        let tab_width = options.tab_width;
+        let mut scanner = Self {
             source,
-            tokens: Vec::new(),
+            options,
+            offset,
+            partial: false,
+            open: None,
+            pending: VecDeque::new(),
+            finished: false,
             start: 0,
             current: 0,
-            line: 1,
+            line,
+            column,
+            start_line: line,
+            start_column: column,
+            interpolations: Vec::new(),
+            source_map: SourceMap::empty(source).with_tab_width(tab_width),
+            last_token: None,
+            paren_depth: 0,
+        };
+
+        if offset == 0 && scanner.options.allow_shebang {
+            scanner.skip_shebang();
         }
+        scanner
     }
 
-    pub fn scan_tokens(mut self) -> Vec<Token> {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token();
+    /// Scans the whole source, returning every token (ending with `Eof`)
+    /// or every error encountered.
+    pub fn scan_tokens(self) -> Result<Vec<Token>, Vec<ScanError>> {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        for result in self {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn finish(&mut self) {
+        self.begin_token();
+        if !self.interpolations.is_empty() {
+            self.error("Unterminated string interpolation.");
+        }
+        self.terminate_statement(self.current, "");
+
+        self.pending.push_back(Ok(BorrowedToken {
+            token_type: TokenType::Eof,
+            lexeme: "",
+            literal: None,
+            line: self.line,
+            column: self.column,
+            end_column: self.column,
+            span: self.span(self.current, self.current),
+        }));
+        self.finished = true;
+    }
+
+    /// Line starts recorded while scanning. Once the scanner has been run
+    /// to the end this covers the whole source; offsets are relative to the
+    /// text passed to the scanner.
+    pub fn source_map(&self) -> &SourceMap<'a> {
+        &self.source_map
+    }
+
+    /// Where scanning a fragment left off, once it has been run to the
+    /// end, for scanning the next fragment with [`Scanner::continue_from`].
+    pub(crate) fn carry(&self) -> Carry {
+        let mut carry = Carry {
+            consumed: self.source.len(),
+            line: self.line,
+            column: self.column,
+            last_token: self.last_token,
+            paren_depth: self.paren_depth,
+            interpolations: self.interpolations.clone(),
+            open: self.open.clone(),
+        };
+        if !matches!(carry.open, None | Some(Open::BlockComment { .. })) {
+            carry.consumed = self.start;
+            (carry.line, carry.column) = (self.start_line, self.start_column);
+        }
+        carry
+    }
+
+    // Stops scanning a partial fragment inside `open`.
+    fn suspend(&mut self, open: Open) {
+        self.open = Some(open);
+        self.finished = true;
+    }
+
+    // Carries on with the construct the fragment before ended inside of.
+    fn resume_open(&mut self, open: Open) {
+        match open {
+            Open::BlockComment { depth, opened, .. } => self.block_comment(depth, opened),
+            Open::String {
+                continuation,
+                value,
+                ..
+            } => self.string_from(value, continuation),
+            Open::TripleQuotedString { .. } => self.triple_quoted_string_from(3),
+            Open::DocComment { lines, .. } => self.doc_comment_from(lines),
+        }
+    }
+
+    /// Whether the scanner sits between tokens at the top level: nothing
+    /// is queued and no string interpolation is open.
+    pub(crate) fn is_between_tokens(&self) -> bool {
+        self.pending.is_empty() && self.interpolations.is_empty()
+    }
+
+    /// The last token emitted and the number of open parentheses and
+    /// brackets, which
+    /// decide where newline terminators go.
+    pub(crate) fn statement_state(&self) -> (Option<TokenType>, usize) {
+        (self.last_token, self.paren_depth)
+    }
+
+    pub(crate) fn set_statement_state(
+        &mut self,
+        last_token: Option<TokenType>,
+        paren_depth: usize,
+    ) {
+        self.last_token = last_token;
+        self.paren_depth = paren_depth;
+    }
+
+    pub(crate) fn into_source_and_options(self) -> (&'a str, Cow<'a, ScannerOptions>) {
+        (self.source, self.options)
+    }
+
+    // Emits a synthetic `Semicolon` with the given lexeme at byte `at` if
+    // newline terminators are on and the statement so far can end here.
+    fn terminate_statement(&mut self, at: usize, lexeme: &'a str) {
+        let can_end = matches!(
+            self.last_token,
+            Some(
+                TokenType::Identifier
+                    | TokenType::String
+                    | TokenType::Number
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::This
+                    | TokenType::Super
+                    | TokenType::RightParen
+                    | TokenType::RightBracket
+                    | TokenType::Break
+                    | TokenType::Continue
+                    | TokenType::Return
+                    | TokenType::PlusPlus
+                    | TokenType::MinusMinus
+            )
+        );
+        if !self.options.newline_terminators
+            || !can_end
+            || self.paren_depth > 0
+            || !self.interpolations.is_empty()
+        {
+            return;
+        }
+
+        self.pending.push_back(Ok(BorrowedToken {
+            token_type: TokenType::Semicolon,
+            lexeme,
+            literal: None,
+            line: self.start_line,
+            column: self.start_column,
+            end_column: self.start_column + lexeme.len(),
+            span: self.span(at, at + lexeme.len()),
+        }));
+        self.last_token = Some(TokenType::Semicolon);
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.offset + start, self.offset + end)
+    }
+
+    // Reports a run of characters that cannot start any token as a single
+    // error, so `@@@` yields one diagnostic rather than three.
+    fn unexpected_characters(&mut self) {
+        while !self.is_at_end() && self.is_unexpected(self.peek()) {
+            self.advanced();
+        }
+
+        let text = self.lexeme();
+        let message = if text.chars().count() == 1 {
+            format!("Unexpected character '{}'.", text.escape_debug())
+        } else {
+            format!("Unexpected characters '{}'.", text.escape_debug())
+        };
+        self.error(&message);
+    }
+
+    fn is_unexpected(&self, c: char) -> bool {
+        let starts_token = matches!(
+            c,
+            '(' | ')'
+                | '['
+                | ']'
+                | '{'
+                | '}'
+                | ','
+                | '.'
+                | ';'
+                | '+'
+                | '-'
+                | '*'
+                | '/'
+                | '%'
+                | '&'
+                | '|'
+                | '^'
+                | '~'
+                | '?'
+                | ':'
+                | '!'
+                | '='
+                | '<'
+                | '>'
+                | '"'
+                | '\''
+                | ' '
+                | '\r'
+                | '\t'
+                | '\n'
+        );
+        !starts_token && !is_digit(c) && !self.is_identifier_start(c)
+    }
+
+    // Skips a `#!` interpreter line at the very start of the input, leaving
+    // its newline to be counted as usual.
+    fn skip_shebang(&mut self) {
+        if self.source.starts_with("#!") {
+            while self.peek() != '\n' && !self.is_at_end() {
+                self.advanced();
+            }
         }
+    }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
-        self.tokens
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
     }
 
     fn is_at_end(&self) -> bool {
@@ -40,16 +480,96 @@ impl<'a> Scanner<'a> {
         let c = self.advanced();
 
         match c {
-            '(' => self.add_token(TokenType::LeftParen),
-            ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '(' => {
+                self.paren_depth += 1;
+                self.add_token(TokenType::LeftParen);
+            }
+            ')' => {
+                self.paren_depth = self.paren_depth.saturating_sub(1);
+                self.add_token(TokenType::RightParen);
+            }
+            '[' => {
+                self.paren_depth += 1;
+                self.add_token(TokenType::LeftBracket);
+            }
+            ']' => {
+                self.paren_depth = self.paren_depth.saturating_sub(1);
+                self.add_token(TokenType::RightBracket);
+            }
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace);
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.add_token(TokenType::InterpolationEnd);
+                    self.begin_token();
+                    self.string(true);
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_token(TokenType::RightBrace);
+                }
+                None => {
+                    self.terminate_statement(self.start, "");
+                    self.add_token(TokenType::RightBrace);
+                }
+            },
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
-            '+' => self.add_token(TokenType::Plus),
-            '-' => self.add_token(TokenType::Minus),
+            '+' => {
+                let t = if self.matches('=') {
+                    TokenType::PlusEqual
+                } else if self.matches('+') {
+                    TokenType::PlusPlus
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(t);
+            }
+            '-' => {
+                let t = if self.matches('=') {
+                    TokenType::MinusEqual
+                } else if self.matches('-') {
+                    TokenType::MinusMinus
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(t);
+            }
             ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
+            '*' => {
+                let t = if self.matches('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(t);
+            }
+            '%' => {
+                let t = if self.matches('=') {
+                    TokenType::PercentEqual
+                } else {
+                    TokenType::Percent
+                };
+                self.add_token(t);
+            }
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            '~' => self.add_token(TokenType::Tilde),
+            '?' => {
+                let t = if self.matches('.') {
+                    TokenType::QuestionDot
+                } else {
+                    TokenType::Question
+                };
+                self.add_token(t);
+            }
+            ':' => self.add_token(TokenType::Colon),
             '!' => {
                 let t = if self.matches('=') {
                     TokenType::BangEqual
@@ -61,6 +581,8 @@ impl<'a> Scanner<'a> {
             '=' => {
                 let t = if self.matches('=') {
                     TokenType::EqualEqual
+                } else if self.matches('>') {
+                    TokenType::Arrow
                 } else {
                     TokenType::Equal
                 };
@@ -69,6 +591,8 @@ impl<'a> Scanner<'a> {
             '<' => {
                 let t = if self.matches('=') {
                     TokenType::LessEqual
+                } else if self.matches('<') {
+                    TokenType::LessLess
                 } else {
                     TokenType::Less
                 };
@@ -77,6 +601,8 @@ impl<'a> Scanner<'a> {
             '>' => {
                 let t = if self.matches('=') {
                     TokenType::GreaterEqual
+                } else if self.matches('>') {
+                    TokenType::GreaterGreater
                 } else {
                     TokenType::Greater
                 };
@@ -85,11 +611,16 @@ impl<'a> Scanner<'a> {
 
             '/' => {
                 if self.matches('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
+                    if self.peek() == '/' && self.peek_next() != '/' {
                         self.advanced();
+                        self.doc_comment();
+                    } else {
+                        self.skip_to_end_of_line();
                     }
                 } else if self.matches('*') {
-                    self.block_comment(true);
+                    self.block_comment(1, self.offset + self.start);
+                } else if self.matches('=') {
+                    self.add_token(TokenType::SlashEqual);
                 } else {
                     self.add_token(TokenType::Slash);
                 }
@@ -97,42 +628,59 @@ impl<'a> Scanner<'a> {
 
             ' ' | '\r' | '\t' => {
                 // Ignore whitespace
+                self.skip_ascii_while(|b| b == b' ' || b == b'\r' || b == b'\t');
             }
             '\n' => {
-                self.line += 1;
+                // Line and column are tracked in `advanced`
+                self.terminate_statement(self.start, "\n");
+            }
+
+            '"' => {
+                if self.peek() == '"' && self.peek_next() == '"' {
+                    self.advanced();
+                    self.advanced();
+                    self.triple_quoted_string();
+                } else {
+                    self.string(false);
+                }
             }
 
-            '"' => self.string(),
+            '\'' => self.single_quoted_string(),
 
             _ => {
                 if is_digit(c) {
                     self.number();
-                } else if is_alpha(c) {
+                } else if self.is_identifier_start(c) {
                     self.identifier();
                 } else {
-                    self.error_at_line("Unexpected character.");
+                    self.unexpected_characters();
                 }
             }
         }
     }
 
-    fn block_comment(&mut self, allow_nesting: bool) {
-        let mut depth: usize = 1;
+    // Skips the rest of a block comment `depth` levels deep, opened at byte
+    // `opened` of the input.
+    fn block_comment(&mut self, mut depth: usize, opened: usize) {
+        let allow_nesting = self.options.nested_comments;
 
         while depth > 0 {
             if self.is_at_end() {
-                self.error_at_line("Unterminated block comment.");
+                if self.partial {
+                    let (line, column) = (self.start_line, self.start_column);
+                    return self.suspend(Open::BlockComment {
+                        depth,
+                        opened,
+                        line,
+                        column,
+                    });
+                }
+                self.unterminated("block comment", opened);
                 return;
             }
 
             let c = self.peek();
 
-            if c == '\n' {
-                self.line += 1;
-                self.advanced();
-                continue;
-            }
-
             if c == '/' && self.peek_next() == '*' {
                 if allow_nesting {
                     self.advanced();
@@ -156,123 +704,596 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    // Scans a `///` comment along with any `///` lines directly following
+    // it into one DocComment token. The literal holds the text of each line,
+    // minus the slashes and one leading space, joined with newlines.
+    fn doc_comment(&mut self) {
+        let lines = vec![self.doc_comment_line()];
+        self.doc_comment_from(lines);
+    }
+
+    // Scans the `///` lines following the `lines` already scanned.
+    fn doc_comment_from(&mut self, mut lines: Vec<String>) {
+        while !self.is_at_end() {
+            let next_line = &self.source[self.current + 1..];
+            if next_line.is_empty() {
+                if self.partial {
+                    // The next line may still continue this comment.
+                    let (at, line, column) = (self.current - self.start, self.line, self.column);
+                    return self.suspend(Open::DocComment {
+                        lines,
+                        at,
+                        line,
+                        column,
+                    });
+                }
+                break;
+            }
+
+            let indent = next_line.len() - next_line.trim_start_matches([' ', '\t']).len();
+            let next_line = &next_line[indent..];
+            if !next_line.starts_with("///") || next_line.starts_with("////") {
+                break;
+            }
+
+            for _ in 0..(1 + indent + 3) {
+                self.advanced();
+            }
+            lines.push(self.doc_comment_line());
+        }
+
+        self.add_token_literal(TokenType::DocComment, Literal::String(lines.join("\n")));
+    }
+
+    // The text of a `///` line after the slashes, minus one leading space.
+    fn doc_comment_line(&mut self) -> String {
+        let line_start = self.current;
+        self.skip_to_end_of_line();
+        let text = self.source[line_start..self.current].trim_end_matches('\r');
+        text.strip_prefix(' ').unwrap_or(text).to_string()
+    }
+
     fn identifier(&mut self) {
-        while is_alpha_numeric(self.peek()) {
+        self.skip_ascii_while(|b| b.is_ascii_alphanumeric() || b == b'_');
+        while self.is_identifier_continue(self.peek()) {
             self.advanced();
         }
 
         let text = self.lexeme();
-        let token_type = keyword_type(&text).unwrap_or(TokenType::Identifier);
+        let token_type = keyword_type(text)
+            .or_else(|| self.options.extra_keywords.get(text).copied())
+            .unwrap_or(TokenType::Identifier);
         self.add_token(token_type);
     }
 
+    fn is_identifier_start(&self, c: char) -> bool {
+        if self.options.unicode_identifiers {
+            is_alpha(c)
+        } else {
+            c.is_ascii_alphabetic() || c == '_'
+        }
+    }
+
+    fn is_identifier_continue(&self, c: char) -> bool {
+        if self.options.unicode_identifiers {
+            is_alpha_numeric(c)
+        } else {
+            c.is_ascii_alphanumeric() || c == '_'
+        }
+    }
+
     fn number(&mut self) {
-        while is_digit(self.peek()) {
-            self.advanced();
+        if &self.source[self.start..self.current] == "0" {
+            let radix = match self.peek() {
+                'x' | 'X' => Some(16),
+                'o' | 'O' => Some(8),
+                'b' | 'B' => Some(2),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                self.advanced();
+                self.radix_number(radix);
+                return;
+            }
         }
 
+        self.digits(10);
+        let mut separator_error = check_separators(&self.source[self.start..self.current]);
+
         if self.peek() == '.' && is_digit(self.peek_next()) {
             self.advanced();
 
-            while is_digit(self.peek()) {
-                self.advanced();
-            }
+            let fraction_start = self.current;
+            self.digits(10);
+            separator_error =
+                separator_error.or(check_separators(&self.source[fraction_start..self.current]));
         }
 
-        let text = self.lexeme();
+        if let Some(message) = separator_error {
+            self.error(message);
+            return;
+        }
+
+        let text = self.lexeme().replace('_', "");
 
         let value: f64 = text.parse().unwrap_or_else(|_| {
-            self.error_at_line("Invalid number literal.");
+            self.error("Invalid number literal.");
             0.0
         });
 
         self.add_token_literal(TokenType::Number, Literal::Number(value));
     }
 
+    // Consumes a run of digits in `radix` along with any `_` separators.
+    fn digits(&mut self, radix: u32) {
+        self.skip_ascii_while(|b| (b as char).is_digit(radix) || b == b'_');
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.advanced();
+        }
+    }
+
+    // Scans the digits of a `0x`, `0o` or `0b` literal. The value is
+    // accumulated as an f64 so long literals lose precision rather than
+    // overflowing.
+    fn radix_number(&mut self, radix: u32) {
+        let name = match radix {
+            16 => "hexadecimal",
+            8 => "octal",
+            _ => "binary",
+        };
+
+        let digits_start = self.current;
+        self.digits(radix);
+        let digits = &self.source[digits_start..self.current];
+
+        let value = digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+        let has_digits = digits.chars().any(|c| c != '_');
+        let separator_error = check_separators(digits);
+
+        if is_alpha_numeric(self.peek()) {
+            let bad = self.peek();
+            while is_alpha_numeric(self.peek()) {
+                self.advanced();
+            }
+            self.error(&format!("Invalid digit '{bad}' in {name} literal."));
+            return;
+        }
+
+        if !has_digits {
+            let prefix = &self.source[self.start..digits_start];
+            self.error(&format!("Expected {name} digits after '{prefix}'."));
+            return;
+        }
+
+        if let Some(message) = separator_error {
+            self.error(message);
+            return;
+        }
+
+        self.add_token_literal(TokenType::Number, Literal::Number(value));
+    }
+
     fn peek_next(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
+        match self.source.as_bytes().get(self.current) {
+            None => '\0',
+            Some(&byte) if byte.is_ascii() => self.char_at(self.current + 1),
+            Some(_) => self.char_at(self.current + self.peek().len_utf8()),
         }
+    }
 
-        let mut it = self.source[self.current..].chars();
-        let _first = it.next();
-        it.next().unwrap_or('\0')
+    // Scans a string segment up to the closing quote or the next `${`.
+    // `continuation` is set when resuming a string after an interpolation,
+    // in which case the final segment is a StringPart rather than a String.
+    fn string(&mut self, continuation: bool) {
+        self.string_from(String::new(), continuation);
     }
 
-    fn string(&mut self) {
+    // Scans the rest of a string segment whose text so far is `value`.
+    fn string_from(&mut self, mut value: String, continuation: bool) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.add_token_literal(TokenType::StringPart, Literal::String(value));
+                self.begin_token();
+                self.advanced();
+                self.advanced();
+                self.add_token(TokenType::InterpolationStart);
+                self.interpolations.push(0);
+                return;
+            }
+
+            let c = self.advanced();
+            match c {
+                '\\' => {
+                    if let Some(escaped) = self.escape_sequence() {
+                        value.push(escaped);
+                    }
+                }
+                _ => value.push(c),
             }
-            self.advanced();
         }
 
         if self.is_at_end() {
-            self.error_at_line("Unterminated string.");
+            if self.partial {
+                let (at, line, column) = (self.current - self.start, self.line, self.column);
+                return self.suspend(Open::String {
+                    continuation,
+                    value,
+                    at,
+                    line,
+                    column,
+                });
+            }
+            self.unterminated("string", self.offset + self.start);
+            self.resynchronize();
             return;
         }
 
         self.advanced();
 
-        let value = self.source[(self.start + 1)..(self.current - 1)].to_string();
-        self.add_token_literal(TokenType::String, Literal::String(value));
+        let token_type = if continuation {
+            TokenType::StringPart
+        } else {
+            TokenType::String
+        };
+        self.add_token_literal(token_type, Literal::String(value));
     }
 
-    fn add_token_literal(&mut self, token_type: TokenType, literal: Literal) {
-        self.add_token_opt_literal(token_type, Some(literal));
+    // Lox has no single-quoted strings, but `'...'` is a common slip. When
+    // the quote closes on the same line, report the whole thing once
+    // instead of an error for each quote and tokens for the words between.
+    fn single_quoted_string(&mut self) {
+        let rest = &self.source[self.current..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        if !line.contains('\'') {
+            self.unexpected_characters();
+            return;
+        }
+
+        while self.advanced() != '\'' {}
+        self.error("Strings must use double quotes, not single quotes.");
     }
 
-    fn error_at_line(&self, message: &str) {
-        eprintln!("[line {}] Error: {}", self.line, message);
+    // Scans a raw `"""..."""` string: quotes, backslashes and newlines are
+    // kept as written. Quotes directly before the closing delimiter belong
+    // to the content, so `"""say "hi""""` holds `say "hi"`.
+    fn triple_quoted_string(&mut self) {
+        self.triple_quoted_string_from(self.current);
     }
-    fn matches(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
+
+    // Scans on through a raw string whose content starts at byte
+    // `content_start`.
+    fn triple_quoted_string_from(&mut self, content_start: usize) {
+        loop {
+            if self.is_at_end() {
+                if self.partial {
+                    let (at, line, column) = (self.current - self.start, self.line, self.column);
+                    return self.suspend(Open::TripleQuotedString { at, line, column });
+                }
+                self.unterminated("triple-quoted string", self.offset + self.start);
+                self.resynchronize();
+                return;
+            }
+
+            if self.source[self.current..].starts_with(r#"""""#) {
+                while self.source[self.current..].starts_with(r#""""""#) {
+                    self.advanced();
+                }
+                break;
+            }
+
+            self.advanced();
         }
-        if self.peek() != expected {
-            return false;
+
+        let value = strip_block_indentation(&self.source[content_start..self.current]);
+
+        for _ in 0..3 {
+            self.advanced();
         }
-        self.current += expected.len_utf8();
-        true
+
+        self.add_token_literal(TokenType::String, Literal::String(value));
     }
 
-    fn peek(&self) -> char {
+    // Decodes the character following a backslash. Unknown escapes are
+    // reported at the backslash and dropped so the rest of the string
+    // still scans.
+    fn escape_sequence(&mut self) -> Option<char> {
         if self.is_at_end() {
-            '\0'
-        } else {
-            self.source[self.current..].chars().next().unwrap_or('\0')
+            return None;
+        }
+
+        let (line, column, start) = (self.line, self.column - 1, self.current - 1);
+        let c = self.advanced();
+        let decoded = match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            '0' => Ok('\0'),
+            '$' => Ok('$'),
+            'u' => self.unicode_escape(),
+            _ => Err(format!(
+                "Invalid escape sequence '\\{}'.",
+                c.escape_default()
+            )),
+        };
+
+        match decoded {
+            Ok(c) => Some(c),
+            Err(message) => {
+                let span = self.span(start, self.current);
+                self.error_at(&message, line, column, span);
+                None
+            }
         }
     }
 
-    fn advanced(&mut self) -> char {
-        let c = self.source[self.current..].chars().next().unwrap_or('\0');
-        self.current += c.len_utf8();
-        c
+    // Decodes the `{XXXX}` part of a `\u{XXXX}` escape into a char.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        if !self.matches('{') {
+            return Err("Expected '{' after '\\u' in unicode escape.".to_string());
+        }
+
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.advanced());
+        }
+
+        if !self.matches('}') {
+            return Err("Expected '}' to close unicode escape.".to_string());
+        }
+
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(format!(
+                "Unicode escape '\\u{{{digits}}}' must have 1 to 6 hex digits."
+            ));
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("Invalid unicode code point '\\u{{{digits}}}' in escape."))
     }
 
-    fn add_token(&mut self, token_type: TokenType) {
-        self.add_token_opt_literal(token_type, None);
+    fn add_token_literal(&mut self, token_type: TokenType, literal: Literal) {
+        self.add_token_opt_literal(token_type, Some(literal));
     }
 
-    fn add_token_opt_literal(&mut self, token_type: TokenType, literal: Option<Literal>) {
-        let text = self.lexeme();
-        self.tokens
-            .push(Token::new(token_type, text, literal, self.line));
+    // Reports an error covering the token scanned so far.
+    fn error(&mut self, message: &str) {
+        let span = self.span(self.start, self.current);
+        self.error_at(message, self.line, self.start_column, span);
     }
 
-    fn lexeme(&self) -> String {
-        self.source[self.start..self.current].to_string()
+    // Reports a construct opened at byte `opened` of the input that runs to
+    // the end of it. The error is placed at where the construct was opened,
+    // which is usually far more useful than the end of the file.
+    fn unterminated(&mut self, what: &str, opened: usize) {
+        let message = format!(
+            "Unterminated {what} opened at line {}, column {}; reached end of file.",
+            self.start_line, self.start_column
+        );
+        let span = Span::new(opened, self.offset + self.current);
+        self.error_at(&message, self.start_line, self.start_column, span);
     }
-}
 
-#[cfg(test)]
-mod tests {
+    // Recovers from a string that ran to the end of the input by treating
+    // only the rest of its opening line as the string and scanning on from
+    // the next line, so the code after it still produces tokens.
+    fn resynchronize(&mut self) {
+        self.current = self.start;
+        self.line = self.start_line;
+        self.column = self.start_column;
+        self.skip_to_end_of_line();
+    }
+
+    fn error_at(&mut self, message: &str, line: usize, column: usize, span: Span) {
+        self.pending.push_back(Err(ScanError {
+            line,
+            column,
+            message: message.to_string(),
+            span,
+        }));
+    }
+
+    fn matches(&mut self, expected: char) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+        if self.peek() != expected {
+            return false;
+        }
+        self.advanced();
+        true
+    }
+
+    fn peek(&self) -> char {
+        self.char_at(self.current)
+    }
+
+    // Reads the char starting at byte `index`, or '\0' past the end. Source
+    // is overwhelmingly ASCII, so a single byte is checked first and only
+    // non-ASCII input pays for UTF-8 decoding.
+    #[inline]
+    fn char_at(&self, index: usize) -> char {
+        match self.source.as_bytes().get(index) {
+            None => '\0',
+            Some(&byte) if byte.is_ascii() => byte as char,
+            Some(_) => self.source[index..].chars().next().unwrap_or('\0'),
+        }
+    }
+
+    // Advances over a run of ASCII bytes matching `pred` without decoding
+    // chars. `pred` must not accept `\n`, which needs line bookkeeping.
+    #[inline]
+    fn skip_ascii_while(&mut self, pred: impl Fn(u8) -> bool) {
+        let tab_width = self.options.tab_width;
+        for &b in &self.source.as_bytes()[self.current..] {
+            if !b.is_ascii() || !pred(b) {
+                break;
+            }
+            self.current += 1;
+            self.column = source_map::next_column(self.column, b as char, tab_width);
+        }
+    }
+
+    fn skip_to_end_of_line(&mut self) {
+        self.skip_ascii_while(|b| b != b'\n');
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advanced();
+        }
+    }
+
+    fn advanced(&mut self) -> char {
+        if self.is_at_end() {
+            return '\0';
+        }
+
+        let c = self.peek();
+        self.current += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+            self.source_map.add_line_start(self.current);
+        } else {
+            self.column = source_map::next_column(self.column, c, self.options.tab_width);
+        }
+        c
+    }
+
+    fn add_token(&mut self, token_type: TokenType) {
+        self.add_token_opt_literal(token_type, None);
+    }
+
+    fn add_token_opt_literal(&mut self, token_type: TokenType, literal: Option<Literal>) {
+        if token_type != TokenType::DocComment {
+            self.last_token = Some(token_type);
+        }
+        self.pending.push_back(Ok(BorrowedToken {
+            token_type,
+            lexeme: self.lexeme(),
+            literal,
+            line: self.line,
+            column: self.start_column,
+            end_column: self.column,
+            span: self.span(self.start, self.current),
+        }));
+    }
+
+    fn lexeme(&self) -> &'a str {
+        &self.source[self.start..self.current]
+    }
+}
+
+/// Lexes lazily: each call scans just enough source to produce the next
+/// token or error. The final item is always the `Eof` token.
+impl Iterator for Scanner<'_> {
+    type Item = Result<Token, ScanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_borrowed()
+            .map(|result| result.map(BorrowedToken::into_owned))
+    }
+}
+
+impl<'a> Scanner<'a> {
+    /// Lexes lazily like the `Iterator` impl, but yields tokens whose
+    /// lexemes borrow from the source rather than being interned.
+    pub fn borrowed_tokens(mut self) -> impl Iterator<Item = Result<BorrowedToken<'a>, ScanError>> {
+        std::iter::from_fn(move || self.next_borrowed())
+    }
+
+    fn next_borrowed(&mut self) -> Option<Result<BorrowedToken<'a>, ScanError>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+
+            if self.finished {
+                return None;
+            }
+
+            if let Some(open) = self.open.take() {
+                self.resume_open(open);
+            } else if self.is_at_end() {
+                if self.partial {
+                    self.finished = true;
+                } else {
+                    self.finish();
+                }
+            } else {
+                self.begin_token();
+                self.scan_token();
+            }
+        }
+    }
+}
+
+// A triple-quoted string whose opening delimiter ends its line is a block:
+// the first newline is dropped, a final whitespace-only line before the
+// closing delimiter is dropped, and the indentation shared by every
+// non-blank line is removed. Other triple-quoted strings are kept as is.
+fn strip_block_indentation(content: &str) -> String {
+    let Some(body) = content
+        .strip_prefix("\r\n")
+        .or_else(|| content.strip_prefix('\n'))
+    else {
+        return content.to_string();
+    };
+
+    let mut lines: Vec<&str> = body.split('\n').collect();
+    if lines
+        .last()
+        .is_some_and(|last| last.chars().all(|c| c == ' ' || c == '\t'))
+    {
+        lines.pop();
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Checks that every `_` in a run of digits sits between two digits.
+fn check_separators(digits: &str) -> Option<&'static str> {
+    if digits.starts_with('_') || digits.ends_with('_') {
+        Some("Digit separator '_' must be between two digits.")
+    } else if digits.contains("__") {
+        Some("Digit separators '_' cannot be repeated.")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
-    use crate::token::Literal;
+    use crate::token::{Lexeme, Literal};
     use crate::token_type::TokenType;
 
     fn scan(src: &str) -> Vec<crate::token::Token> {
-        Scanner::new(src).scan_tokens()
+        Scanner::new(src)
+            .scan_tokens()
+            .unwrap_or_else(|errors| panic!("unexpected scan errors: {errors:?}"))
+    }
+
+    fn scan_errors(src: &str) -> Vec<ScanError> {
+        match Scanner::new(src).scan_tokens() {
+            Ok(tokens) => panic!("expected scan errors, got {tokens:?}"),
+            Err(errors) => errors,
+        }
     }
 
     fn token_types(src: &str) -> Vec<TokenType> {
@@ -286,7 +1307,7 @@ mod tests {
 
     #[test]
     fn scans_single_character_tokens() {
-        let token_types = token_types("( ) { } , . - + ; * /");
+        let token_types = token_types("( ) { } [ ] , . - + ; * / %");
         assert_eq!(
             token_types,
             vec![
@@ -294,6 +1315,8 @@ mod tests {
                 TokenType::RightParen,
                 TokenType::LeftBrace,
                 TokenType::RightBrace,
+                TokenType::LeftBracket,
+                TokenType::RightBracket,
                 TokenType::Comma,
                 TokenType::Dot,
                 TokenType::Minus,
@@ -301,48 +1324,573 @@ mod tests {
                 TokenType::Semicolon,
                 TokenType::Star,
                 TokenType::Slash,
+                TokenType::Percent,
                 TokenType::Eof
             ]
         );
     }
 
     #[test]
-    fn scans_one_or_two_character_operators() {
-        let token_types = token_types("! != = == < <= > >=");
+    fn scans_one_or_two_character_operators() {
+        let token_types = token_types("! != = == < <= > >=");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Bang,
+                TokenType::BangEqual,
+                TokenType::Equal,
+                TokenType::EqualEqual,
+                TokenType::Less,
+                TokenType::LessEqual,
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_yields_tokens_and_errors_in_source_order() {
+        let items: Vec<Result<TokenType, String>> = Scanner::new("1 @ 2")
+            .map(|r| r.map(|t| t.token_type).map_err(|e| e.message))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                Ok(TokenType::Number),
+                Err("Unexpected character '@'.".to_string()),
+                Ok(TokenType::Number),
+                Ok(TokenType::Eof),
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_scans_lazily() {
+        let mut scanner = Scanner::new("print 1; \"never reached");
+        let first = scanner.next().unwrap().unwrap();
+        assert_eq!(first.token_type, TokenType::Print);
+        // Only the first token has been scanned; the unterminated string
+        // further along has not been touched yet.
+        assert!(scanner.pending.is_empty());
+        assert_eq!(scanner.current, 5);
+    }
+
+    #[test]
+    fn percent_is_an_operator_between_numbers() {
+        let tokens = scan("10%3");
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Number,
+                TokenType::Percent,
+                TokenType::Number,
+                TokenType::Eof
+            ]
+        );
+        assert_eq!(tokens[1].lexeme, "%");
+    }
+
+    #[test]
+    fn scans_bitwise_operators() {
+        let token_types = token_types("& | ^ ~ << >>");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::Ampersand,
+                TokenType::Pipe,
+                TokenType::Caret,
+                TokenType::Tilde,
+                TokenType::LessLess,
+                TokenType::GreaterGreater,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn shift_operators_use_maximal_munch() {
+        // There is no shift-assignment token: `<<=` is a shift then `=`,
+        // and `<<<` is a shift then `<`.
+        let token_types = token_types("<<= >>= <<< < < <=");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::LessLess,
+                TokenType::Equal,
+                TokenType::GreaterGreater,
+                TokenType::Equal,
+                TokenType::LessLess,
+                TokenType::Less,
+                TokenType::Less,
+                TokenType::Less,
+                TokenType::LessEqual,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_conditional_operator_tokens() {
+        let expected = vec![
+            TokenType::Identifier,
+            TokenType::Question,
+            TokenType::Number,
+            TokenType::Colon,
+            TokenType::String,
+            TokenType::Eof,
+        ];
+        assert_eq!(token_types("ok ? 1 : \"no\""), expected);
+        assert_eq!(token_types("ok?1:\"no\""), expected);
+    }
+
+    #[test]
+    fn scans_optional_chaining_operator() {
+        assert_eq!(
+            token_types("a?.b ? . c"),
+            vec![
+                TokenType::Identifier,
+                TokenType::QuestionDot,
+                TokenType::Identifier,
+                TokenType::Question,
+                TokenType::Dot,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_compound_assignment_operators() {
+        let token_types = token_types("a += 1; a -= 1; a *= 2; a /= 2; a %= 3;");
+        let operators: Vec<TokenType> = token_types.into_iter().skip(1).step_by(4).collect();
+        assert_eq!(
+            operators,
+            vec![
+                TokenType::PlusEqual,
+                TokenType::MinusEqual,
+                TokenType::StarEqual,
+                TokenType::SlashEqual,
+                TokenType::PercentEqual,
+            ]
+        );
+    }
+
+    #[test]
+    fn slash_equal_does_not_interfere_with_comments() {
+        let token_types = token_types("/= // x /= y\n/* /= */ / =");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::SlashEqual,
+                TokenType::Slash,
+                TokenType::Equal,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_increment_and_decrement() {
+        let token_types = token_types("++i; i--;");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::PlusPlus,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Identifier,
+                TokenType::MinusMinus,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn separated_minus_signs_stay_separate() {
+        assert_eq!(
+            token_types("a - -b"),
+            vec![
+                TokenType::Identifier,
+                TokenType::Minus,
+                TokenType::Minus,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+        // Maximal munch: `a---b` is `a -- - b`.
+        assert_eq!(
+            token_types("a---b"),
+            vec![
+                TokenType::Identifier,
+                TokenType::MinusMinus,
+                TokenType::Minus,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_arrow_for_lambda_shorthand() {
+        assert_eq!(
+            token_types("(a, b) => a"),
+            vec![
+                TokenType::LeftParen,
+                TokenType::Identifier,
+                TokenType::Comma,
+                TokenType::Identifier,
+                TokenType::RightParen,
+                TokenType::Arrow,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn arrow_is_distinct_from_equal_then_greater() {
+        assert_eq!(
+            token_types("= > ==> >="),
+            vec![
+                TokenType::Equal,
+                TokenType::Greater,
+                TokenType::EqualEqual,
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn borrowed_tokens_slice_the_source() {
+        let src = String::from("var answer = 42;");
+        let tokens: Vec<BorrowedToken> = Scanner::new(&src)
+            .borrowed_tokens()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokens[1].lexeme, "answer");
+        let range = src.as_bytes().as_ptr_range();
+        assert!(range.contains(&tokens[1].lexeme.as_ptr()));
+        assert_eq!(tokens[5].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn interns_names_but_not_literals() {
+        let tokens = Scanner::new("var answer = \"forty\" + 2;")
+            .scan_tokens()
+            .unwrap();
+        assert!(matches!(tokens[0].lexeme, Lexeme::Symbol(_)));
+        assert!(matches!(tokens[1].lexeme, Lexeme::Symbol(_)));
+        assert!(matches!(&tokens[3].lexeme, Lexeme::Literal(text) if &**text == "\"forty\""));
+        assert!(matches!(&tokens[5].lexeme, Lexeme::Literal(text) if &**text == "2"));
+    }
+
+    #[test]
+    fn borrowed_tokens_convert_to_owned_tokens() {
+        let src = "print \"a ${b}\" + 0x1F;\n/// doc\nx";
+        let owned: Vec<Token> = {
+            let line = src.to_string();
+            Scanner::new(&line)
+                .borrowed_tokens()
+                .map(|r| r.unwrap().into_owned())
+                .collect()
+        };
+        assert_eq!(owned, scan(src));
+    }
+
+    #[test]
+    fn ignores_whitespace() {
+        let token_types = token_types(" \r\t     \t\r ");
+        assert_eq!(token_types, vec![TokenType::Eof]);
+    }
+
+    #[test]
+    fn newlines_increment_line_counter() {
+        let tokens = scan("\n\n+");
+        assert_eq!(tokens[0].token_type, TokenType::Plus);
+        assert_eq!(tokens[0].line, 3);
+        assert_eq!(tokens[1].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn tokens_record_start_and_end_columns() {
+        let tokens = scan("var foo = 12;\n  print foo;");
+        let positions: Vec<(usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.line, t.column, t.end_column))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1, 4),
+                (1, 5, 8),
+                (1, 9, 10),
+                (1, 11, 13),
+                (1, 13, 14),
+                (2, 3, 8),
+                (2, 9, 12),
+                (2, 12, 13),
+                (2, 13, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_slice_the_original_source() {
+        let src = "var s = \"h\u{e9}\\n\";\nprint s;";
+        let tokens = scan(src);
+        let slices: Vec<&str> = tokens.iter().map(|t| t.span.slice(src)).collect();
+        assert_eq!(
+            slices,
+            vec![
+                "var",
+                "s",
+                "=",
+                "\"h\u{e9}\\n\"",
+                ";",
+                "print",
+                "s",
+                ";",
+                ""
+            ]
+        );
+        assert_eq!(tokens[3].span.start, 8);
+        assert_eq!(tokens[3].span.end, 15);
+        assert_eq!(tokens[8].span.start, src.len());
+    }
+
+    #[test]
+    fn tabs_advance_columns_to_the_next_tab_stop() {
+        let tokens = scan("\tvar\ta // x\tb");
+        assert_eq!(tokens[0].column, 5);
+        assert_eq!(tokens[0].end_column, 8);
+        assert_eq!(tokens[1].column, 9);
+
+        let errors = scan_errors("  \t@");
+        assert_eq!(errors[0].column, 5);
+
+        let options = ScannerOptions {
+            tab_width: 8,
+            ..ScannerOptions::default()
+        };
+        let tokens: Vec<Token> = Scanner::new_with_options("\tx", options)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(tokens[0].column, 9);
+    }
+
+    #[test]
+    fn columns_count_multibyte_characters_once() {
+        let tokens = scan("\"héllo wörld\" + 1");
+        assert_eq!(tokens[0].column, 1);
+        assert_eq!(tokens[0].end_column, 14);
+        assert_eq!(tokens[1].column, 15);
+        assert_eq!(tokens[2].column, 17);
+    }
+
+    #[test]
+    fn shebang_line_is_skipped() {
+        let tokens = scan("#!/usr/bin/env rlox\nprint 1;");
+        assert_eq!(tokens[0].token_type, TokenType::Print);
+        assert_eq!((tokens[0].line, tokens[0].column), (2, 1));
+        assert_eq!(tokens[0].span, Span::new(20, 25));
+    }
+
+    #[test]
+    fn shebang_is_only_allowed_on_the_first_line() {
+        let errors = scan_errors("print 1;\n#!/usr/bin/env rlox");
+        assert_eq!(errors[0].message, "Unexpected character '#'.");
+        assert_eq!(errors[0].line, 2);
+    }
+
+    #[test]
+    fn scanning_records_a_source_map() {
+        let src = "var a = \"x\ny\";\n/* c\n */ print a;\n";
+        let mut scanner = Scanner::new(src);
+        let tokens: Vec<Token> = scanner.by_ref().map(Result::unwrap).collect();
+        let map = scanner.source_map();
+
+        assert_eq!(map, &SourceMap::new(src));
+        let print = &tokens[5];
+        assert_eq!(print.lexeme, "print");
+        assert_eq!(map.location(print.span.start), (4, 5));
+        assert_eq!(map.line_text(4), Some(" */ print a;"));
+    }
+
+    fn scan_newline_terminated(src: &str) -> Vec<Token> {
+        let options = ScannerOptions {
+            newline_terminators: true,
+            ..ScannerOptions::default()
+        };
+        Scanner::new_with_options(src, options)
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn newline_terminators_end_statements_at_line_breaks() {
+        let tokens = scan_newline_terminated("var a = 1\nprint a\n");
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Print,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+
+        let semicolon = &tokens[4];
+        assert_eq!(semicolon.lexeme, "\n");
+        assert_eq!((semicolon.line, semicolon.column), (1, 10));
+        assert_eq!(semicolon.span, Span::new(9, 10));
+    }
+
+    #[test]
+    fn newline_terminators_skip_lines_that_cannot_end_a_statement() {
+        let types: Vec<TokenType> = scan_newline_terminated("var a =\n  1 +\n  2;\n\n// note\n")
+            .iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::Plus,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators_are_suppressed_inside_parentheses() {
+        let types: Vec<TokenType> = scan_newline_terminated("f(a,\n  b\n)\n")
+            .iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Identifier,
+                TokenType::LeftParen,
+                TokenType::Identifier,
+                TokenType::Comma,
+                TokenType::Identifier,
+                TokenType::RightParen,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators_are_suppressed_inside_brackets() {
+        let types: Vec<TokenType> = scan_newline_terminated("[1,\n  2\n]\n")
+            .iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::LeftBracket,
+                TokenType::Number,
+                TokenType::Comma,
+                TokenType::Number,
+                TokenType::RightBracket,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn newline_terminators_close_statements_before_braces_and_eof() {
+        let tokens = scan_newline_terminated("{ print 1 }\nprint 2");
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::LeftBrace,
+                TokenType::Print,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::RightBrace,
+                TokenType::Print,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[3].lexeme, "");
+        assert_eq!(tokens[3].span, Span::new(10, 10));
+        assert_eq!(tokens[7].span, Span::new(19, 19));
+    }
+
+    #[test]
+    fn newlines_are_not_terminators_by_default() {
         assert_eq!(
-            token_types,
-            vec![
-                TokenType::Bang,
-                TokenType::BangEqual,
-                TokenType::Equal,
-                TokenType::EqualEqual,
-                TokenType::Less,
-                TokenType::LessEqual,
-                TokenType::Greater,
-                TokenType::GreaterEqual,
-                TokenType::Eof
-            ]
+            token_types("print 1\n"),
+            vec![TokenType::Print, TokenType::Number, TokenType::Eof]
         );
     }
 
     #[test]
-    fn ignores_whitespace() {
-        let token_types = token_types(" \r\t     \t\r ");
-        assert_eq!(token_types, vec![TokenType::Eof]);
+    fn line_comment_is_ignored_until_newline() {
+        let token_types = token_types("// hello\n+");
+        assert_eq!(token_types, vec![TokenType::Plus, TokenType::Eof]);
     }
 
     #[test]
-    fn newlines_increment_line_counter() {
-        let tokens = scan("\n\n+");
-        assert_eq!(tokens[0].token_type, TokenType::Plus);
+    fn consecutive_doc_comment_lines_become_one_token() {
+        let src = "/// Adds two numbers.\n  ///\n  ///Returns the sum.\nfun add(a, b) {}";
+        let tokens = scan(src);
+        assert_eq!(tokens[0].token_type, TokenType::DocComment);
+        assert_eq!(
+            string_value(&tokens[0]),
+            "Adds two numbers.\n\nReturns the sum."
+        );
         assert_eq!(tokens[0].line, 3);
-        assert_eq!(tokens[1].token_type, TokenType::Eof);
+        assert_eq!(tokens[0].span, Span::new(0, 49));
+        assert_eq!(tokens[1].token_type, TokenType::Fun);
+        assert_eq!(tokens[1].line, 4);
     }
 
     #[test]
-    fn line_comment_is_ignored_until_newline() {
-        let token_types = token_types("// hello\n+");
-        assert_eq!(token_types, vec![TokenType::Plus, TokenType::Eof]);
+    fn blank_line_or_code_ends_a_doc_comment() {
+        let token_types = token_types("/// a\n\n/// b\nx /// c\n/// d");
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::DocComment,
+                TokenType::DocComment,
+                TokenType::Identifier,
+                TokenType::DocComment,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn four_slashes_are_a_plain_comment() {
+        assert_eq!(
+            token_types("//// not docs\n//\n1"),
+            vec![TokenType::Number, TokenType::Eof]
+        );
     }
 
     #[test]
@@ -376,6 +1924,297 @@ mod tests {
         }
     }
 
+    #[test]
+    fn string_literal_decodes_escape_sequences() {
+        let tokens = scan(r#""a\nb\tc\rd\"e\\f\0g""#);
+        assert_eq!(tokens[0].token_type, TokenType::String);
+
+        match tokens[0].literal.as_ref() {
+            Some(Literal::String(value)) => assert_eq!(value, "a\nb\tc\rd\"e\\f\0g"),
+            other => panic!("expected decoded Literal::String, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn escaped_quote_does_not_terminate_string() {
+        let token_types = token_types(r#""say \"hi\"" +"#);
+        assert_eq!(
+            token_types,
+            vec![TokenType::String, TokenType::Plus, TokenType::Eof]
+        );
+    }
+
+    #[test]
+    fn unknown_escape_is_reported_at_the_backslash() {
+        let errors = scan_errors("\n  \"a\\qb\"");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Invalid escape sequence '\\q'.");
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(errors[0].column, 5);
+        assert_eq!(errors[0].span, Span::new(5, 7));
+    }
+
+    #[test]
+    fn string_literal_decodes_unicode_escapes() {
+        let tokens = scan(r#""\u{48}i \u{1F600} \u{e9}""#);
+
+        match tokens[0].literal.as_ref() {
+            Some(Literal::String(value)) => assert_eq!(value, "Hi \u{1F600} \u{e9}"),
+            other => panic!("expected decoded Literal::String, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_unicode_escapes_are_reported() {
+        // Surrogates and values above U+10FFFF are not chars; missing braces
+        // and digit overflow are malformed.
+        let errors = scan_errors(r#""a\u{D800}b\u{110000}c\u41d\u{1234567}e""#);
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid unicode code point '\\u{D800}' in escape.",
+                "Invalid unicode code point '\\u{110000}' in escape.",
+                "Expected '{' after '\\u' in unicode escape.",
+                "Unicode escape '\\u{1234567}' must have 1 to 6 hex digits.",
+            ]
+        );
+        assert_eq!(errors[0].span, Span::new(2, 10));
+    }
+
+    fn string_value(token: &crate::token::Token) -> &str {
+        match token.literal.as_ref() {
+            Some(Literal::String(value)) => value,
+            other => panic!("expected Literal::String, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn scans_string_interpolation_segments() {
+        let tokens = scan(r#""hello ${name}!""#);
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::StringPart,
+                TokenType::InterpolationStart,
+                TokenType::Identifier,
+                TokenType::InterpolationEnd,
+                TokenType::StringPart,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(string_value(&tokens[0]), "hello ");
+        assert_eq!(tokens[2].lexeme, "name");
+        assert_eq!(string_value(&tokens[4]), "!");
+    }
+
+    #[test]
+    fn interpolation_tracks_nested_braces_and_strings() {
+        let token_types = token_types(r#""a ${ {} } b ${"c ${d}"}""#);
+        assert_eq!(
+            token_types,
+            vec![
+                TokenType::StringPart,
+                TokenType::InterpolationStart,
+                TokenType::LeftBrace,
+                TokenType::RightBrace,
+                TokenType::InterpolationEnd,
+                TokenType::StringPart,
+                TokenType::InterpolationStart,
+                TokenType::StringPart,
+                TokenType::InterpolationStart,
+                TokenType::Identifier,
+                TokenType::InterpolationEnd,
+                TokenType::StringPart,
+                TokenType::InterpolationEnd,
+                TokenType::StringPart,
+                TokenType::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn dollar_without_brace_and_escaped_dollar_are_literal() {
+        let tokens = scan(r#""$5 \${x}""#);
+        assert_eq!(tokens[0].token_type, TokenType::String);
+        assert_eq!(string_value(&tokens[0]), "$5 ${x}");
+    }
+
+    #[test]
+    fn unterminated_interpolation_is_reported() {
+        let errors = scan_errors(r#""a ${b"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unterminated string interpolation.");
+    }
+
+    #[test]
+    fn runs_of_unexpected_characters_are_one_error() {
+        let errors = scan_errors("a = @@@###;\n$ `\u{7}`");
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unexpected characters '@@@###'.",
+                "Unexpected character '$'.",
+                "Unexpected characters '`\\u{7}`'.",
+            ]
+        );
+        assert_eq!(errors[0].span, Span::new(4, 10));
+        assert_eq!(errors[0].column, 5);
+    }
+
+    #[test]
+    fn unexpected_characters_stop_before_a_token() {
+        for (src, token_type) in [
+            ("@[", TokenType::LeftBracket),
+            ("@]", TokenType::RightBracket),
+        ] {
+            let results: Vec<_> = Scanner::new(src).borrowed_tokens().collect();
+            let Err(error) = &results[0] else {
+                panic!("expected an error first, got {results:?}");
+            };
+            assert_eq!(error.message, "Unexpected character '@'.");
+            assert_eq!(results[1].as_ref().unwrap().token_type, token_type);
+            assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1, "{src}");
+        }
+
+        let messages: Vec<_> = scan_errors("@'a'").into_iter().map(|e| e.message).collect();
+        assert_eq!(
+            messages,
+            [
+                "Unexpected character '@'.",
+                "Strings must use double quotes, not single quotes."
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_string_is_reported_at_its_opening_quote() {
+        let errors = scan_errors("print 1;\nprint \"oops;\nprint 2;\nprint 3;\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (2, 7));
+        assert_eq!(errors[0].span, Span::new(15, 40));
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 2] Error: Unterminated string opened at line 2, column 7; reached end of file."
+        );
+    }
+
+    #[test]
+    fn scanning_resumes_on_the_line_after_an_unterminated_string() {
+        let src = "print \"oops;\nvar a = 1;\n";
+        let results: Vec<Result<Token, ScanError>> = Scanner::new(src).collect();
+        let errors: Vec<&ScanError> = results.iter().filter_map(|r| r.as_ref().err()).collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+
+        let types: Vec<TokenType> = results
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Print,
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+        let eof = results.last().unwrap().as_ref().unwrap();
+        assert_eq!((eof.line, eof.column), (3, 1));
+    }
+
+    #[test]
+    fn single_quoted_strings_are_one_error() {
+        let errors = scan_errors("print 'two words' + 'x';");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors[0].message,
+            "Strings must use double quotes, not single quotes."
+        );
+        assert_eq!(errors[0].span, Span::new(6, 17));
+        assert_eq!(errors[1].span, Span::new(20, 23));
+
+        let errors = scan_errors("print 'oops;\nprint 1;");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Unexpected character '\\''.");
+    }
+
+    #[test]
+    fn unterminated_block_comment_is_reported_where_it_opens() {
+        let errors = scan_errors("1\n  /* never\nclosed");
+        assert_eq!((errors[0].line, errors[0].column), (2, 3));
+        assert_eq!(
+            errors[0].message,
+            "Unterminated block comment opened at line 2, column 3; reached end of file."
+        );
+    }
+
+    #[test]
+    fn reports_every_scan_error_with_location() {
+        let errors = scan_errors("var a = 1;\n  @ \"open");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "Unexpected character '@'.");
+        assert_eq!((errors[0].line, errors[0].column), (2, 3));
+        assert_eq!(errors[0].span, Span::new(13, 14));
+        assert_eq!(
+            errors[1].message,
+            "Unterminated string opened at line 2, column 5; reached end of file."
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "[line 2] Error: Unexpected character '@'."
+        );
+    }
+
+    #[test]
+    fn triple_quoted_string_keeps_quotes_and_backslashes() {
+        let tokens = scan(r#""""say "hi" \n ${x}""" +"#);
+        assert_eq!(tokens[0].token_type, TokenType::String);
+        assert_eq!(string_value(&tokens[0]), r#"say "hi" \n ${x}"#);
+        assert_eq!(tokens[1].token_type, TokenType::Plus);
+    }
+
+    #[test]
+    fn quotes_before_closing_delimiter_belong_to_content() {
+        let tokens = scan(r#""""a "quoted""""""#);
+        assert_eq!(string_value(&tokens[0]), r#"a "quoted"""#);
+        assert_eq!(tokens[1].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn triple_quoted_block_strips_common_indentation() {
+        let src = "print \"\"\"\n    first\n      indented\n\n    last\n    \"\"\";\nx";
+        let tokens = scan(src);
+        assert_eq!(string_value(&tokens[1]), "first\n  indented\n\nlast");
+        assert_eq!(tokens[2].token_type, TokenType::Semicolon);
+        assert_eq!(tokens[2].line, 6);
+        assert_eq!(tokens[3].line, 7);
+    }
+
+    #[test]
+    fn unterminated_triple_quoted_string_is_reported() {
+        let errors = scan_errors("\"\"\"abc\n\"\"");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].message,
+            "Unterminated triple-quoted string opened at line 1, column 1; reached end of file."
+        );
+    }
+
+    #[test]
+    fn empty_string_is_not_a_triple_quote() {
+        let tokens = scan(r#""" + 1"#);
+        assert_eq!(string_value(&tokens[0]), "");
+        assert_eq!(tokens[1].token_type, TokenType::Plus);
+    }
+
     #[test]
     fn scans_integer_number_literal() {
         let tokens = scan("123");
@@ -402,6 +2241,76 @@ mod tests {
         assert_eq!(tokens[1].token_type, TokenType::Eof);
     }
 
+    fn number_value(token: &crate::token::Token) -> f64 {
+        match token.literal.as_ref() {
+            Some(Literal::Number(n)) => *n,
+            other => panic!("expected Literal::Number, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn scans_hex_octal_and_binary_literals() {
+        let tokens = scan("0xFF 0Xff 0o755 0b1010 0x0 0");
+        let values: Vec<f64> = tokens[..6].iter().map(number_value).collect();
+        assert_eq!(values, vec![255.0, 255.0, 493.0, 10.0, 0.0, 0.0]);
+        assert_eq!(tokens[0].lexeme, "0xFF");
+        assert_eq!(tokens[6].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn radix_prefix_without_digits_is_an_error() {
+        let errors = scan_errors("0x;\n0b");
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Expected hexadecimal digits after '0x'.",
+                "Expected binary digits after '0b'."
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_digit_for_radix_is_an_error() {
+        let errors = scan_errors("0b102 0o8 0xFG");
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid digit '2' in binary literal.",
+                "Invalid digit '8' in octal literal.",
+                "Invalid digit 'G' in hexadecimal literal.",
+            ]
+        );
+        assert_eq!(errors[0].span, Span::new(0, 5));
+    }
+
+    #[test]
+    fn digit_separators_are_ignored_in_values() {
+        let tokens = scan("1_000_000 1.234_567 0xFF_FF 0b1010_1010 0o7_7");
+        let values: Vec<f64> = tokens[..5].iter().map(number_value).collect();
+        assert_eq!(values, vec![1_000_000.0, 1.234_567, 65535.0, 170.0, 63.0]);
+        assert_eq!(tokens[0].lexeme, "1_000_000");
+    }
+
+    #[test]
+    fn misplaced_digit_separators_are_errors() {
+        let errors = scan_errors("1_ 1__0 1_.5 2.5_ 0x_F 0b1_");
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Digit separator '_' must be between two digits.",
+                "Digit separators '_' cannot be repeated.",
+                "Digit separator '_' must be between two digits.",
+                "Digit separator '_' must be between two digits.",
+                "Digit separator '_' must be between two digits.",
+                "Digit separator '_' must be between two digits.",
+            ]
+        );
+        assert_eq!(errors[1].span, Span::new(3, 7));
+    }
+
     #[test]
     fn dot_is_not_fractional_part_without_trailing_digit() {
         let token_types = token_types("123.");
@@ -426,6 +2335,96 @@ mod tests {
         assert_eq!(tokens[0].lexeme, "foo123");
     }
 
+    #[test]
+    fn scans_unicode_identifiers() {
+        let src = "var café = 1; print naïve_π2 + über;";
+        let tokens = scan(src);
+        assert_eq!(tokens[1].token_type, TokenType::Identifier);
+        assert_eq!(tokens[1].lexeme, "café");
+        assert_eq!(tokens[1].span.slice(src), "café");
+        assert_eq!((tokens[1].column, tokens[1].end_column), (5, 9));
+        assert_eq!(tokens[3].column, 12);
+        assert_eq!(tokens[6].lexeme, "naïve_π2");
+        assert_eq!(tokens[8].lexeme, "über");
+    }
+
+    #[test]
+    fn emoji_and_symbols_are_not_identifiers() {
+        let errors = scan_errors("var 😀 = 1; a § b;");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].message, "Unexpected character '😀'.");
+        assert_eq!(errors[0].column, 5);
+        assert_eq!(errors[0].span, Span::new(4, 8));
+    }
+
+    fn scan_with(src: &str, options: ScannerOptions) -> Result<Vec<TokenType>, Vec<ScanError>> {
+        Scanner::new_with_options(src, options)
+            .scan_tokens()
+            .map(|tokens| tokens.into_iter().map(|t| t.token_type).collect())
+    }
+
+    #[test]
+    fn crafting_interpreters_mode_ends_comments_at_first_terminator() {
+        let types: Vec<TokenType> =
+            Scanner::new_with_options("/* /* */ */", ScannerOptions::crafting_interpreters())
+                .map(|result| result.unwrap().token_type)
+                .collect();
+        assert_eq!(
+            types,
+            vec![TokenType::Star, TokenType::Slash, TokenType::Eof]
+        );
+    }
+
+    #[test]
+    fn non_nested_comments_end_at_first_terminator() {
+        let options = ScannerOptions {
+            nested_comments: false,
+            ..ScannerOptions::default()
+        };
+        assert_eq!(
+            scan_with("/* a /* b */ 1 */", options),
+            Ok(vec![
+                TokenType::Number,
+                TokenType::Star,
+                TokenType::Slash,
+                TokenType::Eof
+            ])
+        );
+    }
+
+    #[test]
+    fn shebang_can_be_disallowed() {
+        let options = ScannerOptions {
+            allow_shebang: false,
+            ..ScannerOptions::default()
+        };
+        let errors = scan_with("#!/usr/bin/env rlox\n1", options).unwrap_err();
+        assert_eq!(errors[0].message, "Unexpected character '#'.");
+    }
+
+    #[test]
+    fn unicode_identifiers_can_be_disabled() {
+        let options = ScannerOptions {
+            unicode_identifiers: false,
+            ..ScannerOptions::default()
+        };
+        let errors = scan_with("var café;", options).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, Span::new(7, 9));
+    }
+
+    #[test]
+    fn extra_keywords_are_recognized() {
+        let options = ScannerOptions {
+            extra_keywords: HashMap::from([("let".to_string(), TokenType::Var)]),
+            ..ScannerOptions::default()
+        };
+        assert_eq!(
+            scan_with("let lettuce", options),
+            Ok(vec![TokenType::Var, TokenType::Identifier, TokenType::Eof])
+        );
+    }
+
     #[test]
     fn recognizes_keywords() {
         let token_types = token_types(
@@ -456,6 +2455,41 @@ mod tests {
         )
     }
 
+    #[test]
+    fn recognizes_loop_control_keywords() {
+        let tokens = scan("break continue breaker continued");
+        let types: Vec<TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Break,
+                TokenType::Continue,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[2].lexeme, "breaker");
+    }
+
+    #[test]
+    fn recognizes_switch_keywords() {
+        let types: Vec<TokenType> = scan("switch case default defaults")
+            .iter()
+            .map(|t| t.token_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Switch,
+                TokenType::Case,
+                TokenType::Default,
+                TokenType::Identifier,
+                TokenType::Eof,
+            ]
+        );
+    }
+
     #[test]
     fn keywords_are_not_prefixes_of_identifiers() {
         let tokens = scan("class classy");
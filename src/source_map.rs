@@ -0,0 +1,156 @@
+/// Line start offsets for a source text, for turning byte offsets (such as
+/// token spans) into line/column positions and for quoting source lines in
+/// diagnostics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceMap<'src> {
+    source: &'src str,
+    line_starts: Vec<usize>,
+    tab_width: usize,
+}
+
+/// Tabs advance to the next multiple of this many columns by default.
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// The column after `c` when `c` starts at `column`. A tab moves to the
+/// next tab stop; a `tab_width` of 0 or 1 counts it as one column.
+#[inline]
+pub(crate) fn next_column(column: usize, c: char, tab_width: usize) -> usize {
+    if c == '\t' && tab_width > 1 {
+        (column - 1) / tab_width * tab_width + tab_width + 1
+    } else {
+        column + 1
+    }
+}
+
+impl<'src> SourceMap<'src> {
+    /// Builds a map for `source` by finding every line start up front.
+    pub fn new(source: &'src str) -> Self {
+        let mut map = Self::empty(source);
+        for (offset, byte) in source.bytes().enumerate() {
+            if byte == b'\n' {
+                map.add_line_start(offset + 1);
+            }
+        }
+        map
+    }
+
+    /// A map that knows only about the first line; further lines are added
+    /// with [`SourceMap::add_line_start`] as the scanner reaches them.
+    pub(crate) fn empty(source: &'src str) -> Self {
+        Self {
+            source,
+            line_starts: vec![0],
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    /// Sets how many columns a tab expands to in [`SourceMap::location`].
+    pub fn with_tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    pub(crate) fn add_line_start(&mut self, offset: usize) {
+        if offset > *self.line_starts.last().unwrap_or(&0) {
+            self.line_starts.push(offset);
+        }
+    }
+
+    pub fn source(&self) -> &'src str {
+        self.source
+    }
+
+    /// Number of lines recorded so far.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The 1-based line and column of a byte offset. Columns count chars,
+    /// so multibyte characters occupy a single column, and tabs expand to
+    /// the next tab stop. Offsets past the end are clamped to the end of
+    /// the source.
+    pub fn location(&self, offset: usize) -> (usize, usize) {
+        let offset = self.clamp(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.source[line_start..offset]
+            .chars()
+            .fold(1, |column, c| next_column(column, c, self.tab_width));
+        (line, column)
+    }
+
+    /// The text of a 1-based line, without its line terminator.
+    pub fn line_text(&self, line: usize) -> Option<&'src str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        let text = &self.source[start..end];
+        Some(text.trim_end_matches('\n').trim_end_matches('\r'))
+    }
+
+    // Moves an offset back onto a char boundary within the source.
+    fn clamp(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_offsets_on_each_line() {
+        let map = SourceMap::new("var a;\nprint a;\n\nx");
+        assert_eq!(map.location(0), (1, 1));
+        assert_eq!(map.location(4), (1, 5));
+        assert_eq!(map.location(6), (1, 7));
+        assert_eq!(map.location(7), (2, 1));
+        assert_eq!(map.location(13), (2, 7));
+        assert_eq!(map.location(16), (3, 1));
+        assert_eq!(map.location(17), (4, 1));
+        assert_eq!(map.location(100), (4, 2));
+    }
+
+    #[test]
+    fn columns_count_chars_not_bytes() {
+        let map = SourceMap::new("var café = 1;");
+        assert_eq!(map.location(9), (1, 9));
+        assert_eq!(map.location(11), (1, 11));
+        // An offset inside `é` is moved to its first byte.
+        assert_eq!(map.location(8), (1, 8));
+    }
+
+    #[test]
+    fn tabs_expand_to_tab_stops() {
+        let map = SourceMap::new("\tx\n  \ty\na\tb");
+        assert_eq!(map.location(1), (1, 5));
+        assert_eq!(map.location(6), (2, 5));
+        assert_eq!(map.location(10), (3, 5));
+
+        let map = map.with_tab_width(8);
+        assert_eq!(map.location(1), (1, 9));
+        assert_eq!(map.location(6), (2, 9));
+
+        let map = map.with_tab_width(1);
+        assert_eq!(map.location(1), (1, 2));
+    }
+
+    #[test]
+    fn returns_line_text_without_terminators() {
+        let map = SourceMap::new("first\r\nsecond\n\nlast");
+        assert_eq!(map.line_count(), 4);
+        assert_eq!(map.line_text(1), Some("first"));
+        assert_eq!(map.line_text(2), Some("second"));
+        assert_eq!(map.line_text(3), Some(""));
+        assert_eq!(map.line_text(4), Some("last"));
+        assert_eq!(map.line_text(0), None);
+        assert_eq!(map.line_text(5), None);
+    }
+}
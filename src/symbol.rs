@@ -0,0 +1,162 @@
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// An interned string. Symbols are cheap to copy, compare and hash; the
+/// text behind them is stored once and can be recovered with
+/// [`Symbol::as_str`].
+///
+/// Each thread has its own interner, so a symbol only means something on
+/// the thread that made it, and can't be sent to another. The well-known
+/// names below are the exception: they are interned first on every thread.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32, PhantomData<*const ()>);
+
+macro_rules! well_known {
+    ($($name:ident = $text:literal,)*) => {
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        enum WellKnown {
+            $($name,)*
+        }
+
+        impl Symbol {
+            $(pub const $name: Symbol = Symbol(WellKnown::$name as u32, PhantomData);)*
+        }
+
+        const WELL_KNOWN: &[&str] = &[$($text,)*];
+    };
+}
+
+// The names the interpreter and resolver look up themselves, so they
+// needn't be interned each time.
+well_known! {
+    INIT = "init",
+    THIS = "this",
+    SUPER = "super",
+    PLUS = "plus",
+    MINUS = "minus",
+    TIMES = "times",
+    DIVIDE = "divide",
+    MODULO = "modulo",
+    COMPARE = "compare",
+    EQUALS = "equals",
+    TO_STRING = "toString",
+}
+
+struct Interner {
+    ids: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        let mut interner = Self {
+            ids: HashMap::new(),
+            strings: Vec::new(),
+        };
+        for &text in WELL_KNOWN {
+            interner.insert(text);
+        }
+        interner
+    }
+}
+
+impl Interner {
+    fn insert(&mut self, text: &'static str) -> Symbol {
+        let symbol = Symbol(self.strings.len() as u32, PhantomData);
+        self.strings.push(text);
+        self.ids.insert(text, symbol);
+        symbol
+    }
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::default();
+}
+
+impl Symbol {
+    /// Returns the symbol for `text`, storing it on first use. Only names
+    /// are interned, so what is stored stays bounded by the names a
+    /// program uses; the text is kept for the rest of the process.
+    pub fn intern(text: &str) -> Symbol {
+        INTERNER.with_borrow_mut(|interner| match interner.ids.get(text) {
+            Some(&symbol) => symbol,
+            None => interner.insert(Box::leak(text.into())),
+        })
+    }
+
+    /// Resolves the symbol back to its text.
+    pub fn as_str(self) -> &'static str {
+        INTERNER.with_borrow(|interner| interner.strings[self.0 as usize])
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(text: &str) -> Self {
+        Symbol::intern(text)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_the_same_text_yields_the_same_symbol() {
+        let a = Symbol::intern("interned_name");
+        let b = Symbol::intern(&String::from("interned_name"));
+        assert_eq!(a, b);
+        assert_ne!(a, Symbol::intern("other_name"));
+    }
+
+    #[test]
+    fn symbols_resolve_back_to_their_text() {
+        let symbol = Symbol::intern("café");
+        assert_eq!(symbol.as_str(), "café");
+        assert_eq!(symbol, "café");
+        assert_eq!(symbol.to_string(), "café");
+        assert_eq!(format!("{symbol:?}"), "\"café\"");
+    }
+
+    #[test]
+    fn well_known_names_are_the_same_on_every_thread() {
+        assert_eq!(Symbol::intern("init"), Symbol::INIT);
+        assert_eq!(Symbol::TO_STRING.as_str(), "toString");
+        let there = std::thread::spawn(|| Symbol::intern("super") == Symbol::SUPER)
+            .join()
+            .unwrap();
+        assert!(there);
+    }
+}
@@ -1,31 +1,151 @@
+use crate::span::Span;
+use crate::symbol::Symbol;
 use crate::token_type::TokenType;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Serialized as a bare JSON string or number.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
 pub enum Literal {
     String(String),
     Number(f64),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// The text of a token. Names, keywords and punctuation recur throughout a
+/// program, so they are interned; a literal is usually written once, so its
+/// text is kept by the token alone and freed with it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Lexeme {
+    Symbol(Symbol),
+    Literal(Rc<str>),
+}
+
+impl Lexeme {
+    fn new(token_type: TokenType, text: &str) -> Self {
+        match token_type {
+            TokenType::String
+            | TokenType::StringPart
+            | TokenType::Number
+            | TokenType::DocComment => Lexeme::Literal(text.into()),
+            _ => Lexeme::Symbol(Symbol::intern(text)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Lexeme::Symbol(symbol) => symbol.as_str(),
+            Lexeme::Literal(text) => text,
+        }
+    }
+
+    /// The lexeme as a symbol, interning it if it is a literal's.
+    pub fn symbol(&self) -> Symbol {
+        match self {
+            Lexeme::Symbol(symbol) => *symbol,
+            Lexeme::Literal(text) => Symbol::intern(text),
+        }
+    }
+}
+
+impl From<Symbol> for Lexeme {
+    fn from(symbol: Symbol) -> Self {
+        Lexeme::Symbol(symbol)
+    }
+}
+
+impl Serialize for Lexeme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for Lexeme {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Token {
     pub token_type: TokenType,
-    pub lexeme: String,
+    pub lexeme: Lexeme,
     pub literal: Option<Literal>,
     pub line: usize,
+    /// 1-based column of the first character, counted in chars.
+    pub column: usize,
+    /// Column just past the last character, on the line the token ends.
+    pub end_column: usize,
+    /// Byte offsets of the lexeme within the source.
+    pub span: Span,
 }
 
 impl Token {
     pub fn new(
         token_type: TokenType,
-        lexeme: String,
+        lexeme: Lexeme,
         literal: Option<Literal>,
         line: usize,
+        column: usize,
+        end_column: usize,
+        span: Span,
     ) -> Self {
         Self {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            end_column,
+            span,
         }
     }
 }
+
+/// A token whose lexeme borrows from the source text instead of being
+/// interned, for callers that keep the source alive and want scanning to
+/// avoid per-token work. [`BorrowedToken::into_owned`] converts it to a
+/// [`Token`] when the source is about to go away, as in the REPL.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BorrowedToken<'src> {
+    pub token_type: TokenType,
+    pub lexeme: &'src str,
+    pub literal: Option<Literal>,
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub span: Span,
+}
+
+impl BorrowedToken<'_> {
+    pub fn into_owned(self) -> Token {
+        Token::new(
+            self.token_type,
+            Lexeme::new(self.token_type, self.lexeme),
+            self.literal,
+            self.line,
+            self.column,
+            self.end_column,
+            self.span,
+        )
+    }
+}
+
+impl From<BorrowedToken<'_>> for Token {
+    fn from(token: BorrowedToken<'_>) -> Self {
+        token.into_owned()
+    }
+}
@@ -1,10 +1,14 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
 pub enum TokenType {
     // Single character tokens
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -12,6 +16,14 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Percent,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
+    Question,
+    QuestionDot,
+    Colon,
 
     // One or two character tokens
     Bang,
@@ -22,15 +34,39 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentEqual,
+    PlusPlus,
+    MinusMinus,
+    Arrow,
 
     // Literal characters tokens
     Identifier,
     String,
     Number,
 
+    // String interpolation tokens: "a ${b} c" scans as
+    // StringPart InterpolationStart <b> InterpolationEnd StringPart
+    StringPart,
+    InterpolationStart,
+    InterpolationEnd,
+
+    // Consecutive `///` lines, with the comment text as a string literal
+    DocComment,
+
     // Keywords characters tokens
     And,
+    Break,
+    Case,
     Class,
+    Continue,
+    Default,
+    Do,
     Else,
     False,
     For,
@@ -41,6 +77,7 @@ pub enum TokenType {
     Print,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
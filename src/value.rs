@@ -0,0 +1,232 @@
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A Lox value. Literals in the syntax tree hold one, and the interpreter
+/// computes with them.
+///
+/// `==` is Lox equality: values of different types are never equal, and
+/// arrays, maps, callables, classes and instances are equal only to
+/// themselves. Displaying a value gives what `print` shows, which is also
+/// what `+` joins to a string.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// An array, shared by every value that refers to it.
+    Array(Rc<RefCell<Vec<Value>>>),
+    /// A map from string keys, shared by every value that refers to it.
+    Map(Rc<RefCell<BTreeMap<String, Value>>>),
+    /// A function, or anything else a program can call.
+    Callable(Rc<dyn LoxCallable>),
+    /// A class, which is called to make instances of it.
+    Class(Rc<LoxClass>),
+    /// An instance of a class, shared by every value that refers to it.
+    Instance(Rc<RefCell<LoxInstance>>),
+}
+
+impl Value {
+    /// Whether the value counts as true in a condition: everything but
+    /// `nil` and `false` does.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// A number as `print` shows it, which is how jlox shows it: Java's
+    /// `Double.toString`, without a trailing `.0`. So `2` rather than
+    /// `2.0`, `2.5`, `-0`, `Infinity`, `-Infinity` and `NaN`. Magnitudes
+    /// from 1e7 up, and below 1e-3, are written as Java writes them, with
+    /// at least one digit after the point and an exponent: `1.0E22`,
+    /// `1.23456789012E11` or `-2.5E-8`. Every number is written with as few
+    /// digits as read back as it.
+    pub fn format_number(n: f64) -> String {
+        if n.is_nan() {
+            "NaN".to_string()
+        } else if n.is_infinite() {
+            if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+        } else if n != 0.0 && !(1e-3..1e7).contains(&n.abs()) {
+            let scientific = format!("{n:e}");
+            let (mantissa, exponent) = scientific
+                .split_once('e')
+                .expect("`{:e}` writes an exponent");
+            if mantissa.contains('.') {
+                format!("{mantissa}E{exponent}")
+            } else {
+                format!("{mantissa}.0E{exponent}")
+            }
+        } else {
+            format!("{n}")
+        }
+    }
+
+    // `enclosing` holds the arrays and maps being written, so one that
+    // contains itself is written as `[...]` or `{...}` where it recurs.
+    // Strings are quoted inside arrays and maps.
+    fn write(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        quoted: bool,
+        enclosing: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match self {
+            Value::Nil => f.write_str("nil"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => f.write_str(&Value::format_number(*n)),
+            Value::String(s) if quoted => write!(f, "{s:?}"),
+            Value::String(s) => f.write_str(s),
+            Value::Array(elements) => {
+                let id = Rc::as_ptr(elements).cast();
+                if enclosing.contains(&id) {
+                    return f.write_str("[...]");
+                }
+                enclosing.push(id);
+                f.write_str("[")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    element.write(f, true, enclosing)?;
+                }
+                enclosing.pop();
+                f.write_str("]")
+            }
+            Value::Map(entries) => {
+                let id = Rc::as_ptr(entries).cast();
+                if enclosing.contains(&id) {
+                    return f.write_str("{...}");
+                }
+                enclosing.push(id);
+                f.write_str("{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{key:?}: ")?;
+                    value.write(f, true, enclosing)?;
+                }
+                enclosing.pop();
+                f.write_str("}")
+            }
+            Value::Callable(callable) => write!(f, "{callable}"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{}", instance.borrow()),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Callable(a), Value::Callable(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Numbers print as [`Value::format_number`] gives them.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false, &mut Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn array(elements: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    #[test]
+    fn only_nil_and_false_are_falsey() {
+        assert!(!Value::Nil.is_truthy());
+        assert!(!Value::Bool(false).is_truthy());
+        assert!(Value::Bool(true).is_truthy());
+        assert!(Value::Number(0.0).is_truthy());
+        assert!(Value::String(String::new()).is_truthy());
+        assert!(array(vec![]).is_truthy());
+    }
+
+    #[test]
+    fn equality_needs_the_same_type() {
+        assert_eq!(Value::Nil, Value::Nil);
+        assert_eq!(Value::Number(1.0), Value::Number(1.0));
+        assert_eq!(Value::String("a".into()), Value::String("a".into()));
+        assert_ne!(Value::Number(1.0), Value::String("1".into()));
+        assert_ne!(Value::Nil, Value::Bool(false));
+        assert_ne!(Value::Number(0.0), Value::Bool(false));
+        assert_ne!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+    }
+
+    #[test]
+    fn arrays_are_equal_only_to_themselves() {
+        let a = array(vec![Value::Nil]);
+        assert_eq!(a, a.clone());
+        assert_ne!(a, array(vec![Value::Nil]));
+    }
+
+    #[test]
+    fn displays_values_as_print_shows_them() {
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+        assert_eq!(Value::Number(-2.5).to_string(), "-2.5");
+        assert_eq!(Value::String("hi".into()).to_string(), "hi");
+        assert_eq!(
+            array(vec![Value::Number(1.0), Value::String("a".into())]).to_string(),
+            "[1, \"a\"]"
+        );
+    }
+
+    #[test]
+    fn formats_numbers_as_jlox_does() {
+        for (n, expected) in [
+            (2.0, "2"),
+            (2.5, "2.5"),
+            (-3.0, "-3"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (0.0, "0"),
+            (-0.0, "-0"),
+            (f64::INFINITY, "Infinity"),
+            (f64::NEG_INFINITY, "-Infinity"),
+            (f64::NAN, "NaN"),
+            (9999999.0, "9999999"),
+            (1e7, "1.0E7"),
+            (-12345678.0, "-1.2345678E7"),
+            (123456789012.0, "1.23456789012E11"),
+            (9007199254740993.0, "9.007199254740992E15"),
+            (1e22, "1.0E22"),
+            (-1.5e300, "-1.5E300"),
+            (f64::MAX, "1.7976931348623157E308"),
+            (0.001, "0.001"),
+            (0.0001, "1.0E-4"),
+            (2.5e-8, "2.5E-8"),
+        ] {
+            assert_eq!(Value::format_number(n), expected, "{n:?}");
+            assert_eq!(Value::Number(n).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn displays_an_array_that_contains_itself() {
+        let a = array(vec![Value::Nil]);
+        if let Value::Array(elements) = &a {
+            elements.borrow_mut()[0] = a.clone();
+        }
+        assert_eq!(a.to_string(), "[[...]]");
+    }
+}